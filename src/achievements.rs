@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{AppState, LoopCounter};
+use crate::inventory::{Item, ItemGet, PickUpItems};
+use crate::rules::GameRules;
+use crate::shop::Upgrades;
+use crate::toast::Toast;
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<AchievementUnlocked>()
+            .insert_resource(UnlockedAchievements::default())
+            .add_systems(Update, unlock_first_candy.after(PickUpItems).run_if(in_state(AppState::Playing)))
+            .add_systems(OnEnter(AppState::GameOver), unlock_end_of_run_achievements)
+            .add_systems(Update, announce_achievements);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Achievement {
+    FirstCandy,
+    FullCircle,
+    CleanSweep,
+}
+
+impl Achievement {
+    /// Stable identifier used by store backends.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Achievement::FirstCandy => "first_candy",
+            Achievement::FullCircle => "full_circle",
+            Achievement::CleanSweep => "clean_sweep",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Achievement::FirstCandy => "First Candy",
+            Achievement::FullCircle => "Full Circle",
+            Achievement::CleanSweep => "Clean Sweep",
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct AchievementUnlocked(pub Achievement);
+
+#[derive(Resource, Default)]
+struct UnlockedAchievements(HashSet<Achievement>);
+
+impl UnlockedAchievements {
+    fn unlock(&mut self, achievement: Achievement, event_writer: &mut EventWriter<AchievementUnlocked>) {
+        if self.0.insert(achievement) {
+            event_writer.send(AchievementUnlocked(achievement));
+        }
+    }
+}
+
+fn unlock_first_candy(
+    mut unlocked: ResMut<UnlockedAchievements>,
+    mut item_events: EventReader<ItemGet>,
+    mut event_writer: EventWriter<AchievementUnlocked>,
+) {
+    if item_events.iter().any(|event| event.item.is_candy()) {
+        unlocked.unlock(Achievement::FirstCandy, &mut event_writer);
+    }
+}
+
+fn unlock_end_of_run_achievements(
+    mut unlocked: ResMut<UnlockedAchievements>,
+    loop_counter: Res<LoopCounter>,
+    rules: Res<GameRules>,
+    upgrades: Res<Upgrades>,
+    items: Query<&Item>,
+    mut event_writer: EventWriter<AchievementUnlocked>,
+) {
+    if !loop_counter.is_final_loop(&rules, &upgrades) {
+        return;
+    }
+
+    unlocked.unlock(Achievement::FullCircle, &mut event_writer);
+    if !items.iter().any(|item| item.is_candy()) {
+        unlocked.unlock(Achievement::CleanSweep, &mut event_writer);
+    }
+}
+
+fn announce_achievements(mut achievements: EventReader<AchievementUnlocked>, mut toasts: EventWriter<Toast>) {
+    for AchievementUnlocked(achievement) in achievements.iter() {
+        toasts.send(Toast::success(format!("Achievement unlocked: {}", achievement.name())));
+    }
+}
@@ -0,0 +1,16 @@
+//! Screenshot comparisons of the main screens: `cargo run --features screenshot-tests --bin screenshots`, or with
+//! `-- --update` to re-record the goldens in `tests/screenshots/`. Runs without a window, so it works in CI; a screen
+//! without a golden fails like one that differs.
+
+use interference_factory::screenshot_tests::{headless_app, ScreenshotTestPlugin};
+
+fn main() {
+    // A fresh, throwaway save directory, so captures don't depend on (or overwrite) anyone's progress or settings.
+    let data_dir = std::env::temp_dir().join("interference-factory-screenshots");
+    let _ = std::fs::remove_dir_all(&data_dir);
+    std::env::set_var(interference_factory::DATA_DIR_VAR, &data_dir);
+
+    let mut app = headless_app();
+    app.add_plugins(ScreenshotTestPlugin::from_args());
+    app.run();
+}
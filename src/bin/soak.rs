@@ -0,0 +1,12 @@
+//! Long-running stress test: `cargo run --release --bin soak -- --minutes 240 --seed 42`.
+//!
+//! Exits 1 if the main menu keeps gaining entities from one visit to the next; that's the memory check, with resident
+//! memory logged alongside. The seed covers the inputs and every board played, so a failing run can be repeated.
+
+use interference_factory::soak::SoakPlugin;
+
+fn main() {
+    let mut app = interference_factory::app();
+    app.add_plugins(SoakPlugin::from_args());
+    app.run();
+}
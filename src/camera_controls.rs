@@ -0,0 +1,140 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::{board_center, AppState, GRID_SPACING, MAX_X, MAX_Y};
+use crate::focus::MoveCamera;
+
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 2.;
+/// How much one notch of the wheel zooms by.
+const ZOOM_STEP: f32 = 1.1;
+/// Trackpads scroll in pixels; about this many make a notch.
+const PIXELS_PER_NOTCH: f32 = 40.;
+/// How close to the edge of the window the cursor has to be to pan, in logical pixels.
+const EDGE_MARGIN: f32 = 8.;
+/// In world units per second.
+const EDGE_PAN_SPEED: f32 = 500.;
+
+pub struct CameraControlsPlugin;
+
+impl Plugin for CameraControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(CameraView::default())
+            .add_systems(OnEnter(AppState::MainMenu), reset_view)
+            .add_systems(Update, (zoom_with_wheel, pan_with_drag, pan_at_edges, reset_view_on_key)
+                .before(MoveCamera).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// The player's own adjustments to the camera, on top of wherever it would be anyway.
+#[derive(Resource)]
+pub struct CameraView {
+    pan: Vec2,
+    /// Multiplies the projection's scale; above 1 is zoomed out.
+    pub zoom: f32,
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        CameraView {pan: Vec2::ZERO, zoom: 1.}
+    }
+}
+
+impl CameraView {
+    /// Where the camera centers when it would otherwise be at `target`, kept over the board.
+    pub fn frame(&self, target: Vec2) -> Vec2 {
+        let (min, max) = level_bounds();
+        (target + self.pan).clamp(min, max)
+    }
+
+    // No point panning further than the camera can go.
+    fn pan_by(&mut self, offset: Vec2) {
+        let home = board_center();
+        self.pan = self.frame(home + offset) - home;
+    }
+}
+
+/// The corners of the board, out to the outside edges of its cells.
+fn level_bounds() -> (Vec2, Vec2) {
+    let half_cell = Vec2::splat(GRID_SPACING as f32 / 2.);
+    let far_corner = (IVec2::new(MAX_X - 1, MAX_Y - 1) * GRID_SPACING).as_vec2();
+    (-half_cell, far_corner + half_cell)
+}
+
+fn reset_view(mut view: ResMut<CameraView>) {
+    *view = CameraView::default();
+}
+
+fn reset_view_on_key(keyboard_input: Res<Input<KeyCode>>, mut view: ResMut<CameraView>) {
+    if keyboard_input.just_pressed(KeyCode::Home) {
+        *view = CameraView::default();
+    }
+}
+
+fn zoom_with_wheel(mut wheel: EventReader<MouseWheel>, mut view: ResMut<CameraView>) {
+    let notches: f32 = wheel.iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_NOTCH,
+        })
+        .sum();
+    if notches != 0. {
+        // Scrolling up zooms in.
+        view.zoom = (view.zoom * ZOOM_STEP.powf(-notches)).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
+/// How much of the world one logical pixel of the window covers.
+fn world_per_pixel(window: &Window, projection: &OrthographicProjection) -> f32 {
+    projection.area.width() / window.width()
+}
+
+// The board follows the cursor while the middle button is held.
+fn pan_with_drag(
+    mouse_input: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<&OrthographicProjection, With<Camera2d>>,
+    mut view: ResMut<CameraView>,
+    mut last_cursor: Local<Option<Vec2>>,
+) {
+    let (Ok(window), Some(projection)) = (windows.get_single(), cameras.iter().next()) else {
+        return;
+    };
+    let cursor = window.cursor_position().filter(|_| mouse_input.pressed(MouseButton::Middle));
+    if let (Some(cursor), Some(last)) = (cursor, *last_cursor) {
+        // Screen y runs down, the world's runs up.
+        let moved = (cursor - last) * Vec2::new(1., -1.);
+        view.pan_by(-moved * world_per_pixel(window, projection));
+    }
+    *last_cursor = cursor;
+}
+
+fn pan_at_edges(
+    time: Res<Time>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut view: ResMut<CameraView>,
+) {
+    let Some((window, cursor)) = windows.get_single().ok()
+        .filter(|window| window.focused)
+        .and_then(|window| window.cursor_position().map(|cursor| (window, cursor))) else {
+        return;
+    };
+    let mut direction = Vec2::ZERO;
+    if cursor.x < EDGE_MARGIN {
+        direction.x -= 1.;
+    }
+    if cursor.x > window.width() - EDGE_MARGIN {
+        direction.x += 1.;
+    }
+    if cursor.y < EDGE_MARGIN {
+        direction.y += 1.;
+    }
+    if cursor.y > window.height() - EDGE_MARGIN {
+        direction.y -= 1.;
+    }
+    if direction != Vec2::ZERO {
+        view.pan_by(direction.normalize() * EDGE_PAN_SPEED * time.delta_seconds());
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{validate_move, AppState, MoveRejected, Player};
+use crate::focus::MoveCamera;
+use crate::settings::Settings;
+
+/// How far the camera is thrown at full trauma, in world units.
+const MAX_SHAKE_OFFSET: f32 = 14.;
+/// Trauma lost per second; a rejected move's shake is over in about a third of a second.
+const TRAUMA_DECAY: f32 = 3.;
+const REJECTED_MOVE_TRAUMA: f32 = 0.6;
+
+pub struct CameraEffectsPlugin;
+
+impl Plugin for CameraEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(CameraShake::default())
+            .add_systems(Update, (play_rejected_move_sound, shake_on_rejected_move)
+                .after(validate_move).run_if(in_state(AppState::Playing)))
+            // Shake sits on top of wherever the camera is headed, and comes off again before it's moved there.
+            .add_systems(Update, (
+                remove_shake.before(MoveCamera),
+                apply_shake.after(MoveCamera),
+            ));
+    }
+}
+
+/// Trauma builds up from jolts and drains away; the camera shakes harder the more there is.
+#[derive(Resource, Default)]
+pub struct CameraShake {
+    trauma: f32,
+    /// What's currently been added onto the cameras' positions.
+    offset: Vec2,
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.);
+    }
+}
+
+// Only the player's own moves; a past self bumping into something isn't news.
+fn shake_on_rejected_move(
+    mut rejections: EventReader<MoveRejected>,
+    mut shake: ResMut<CameraShake>,
+    player: Query<(), With<Player>>,
+) {
+    if rejections.iter().any(|rejection| player.contains(rejection.mover)) {
+        shake.add_trauma(REJECTED_MOVE_TRAUMA);
+    }
+}
+
+fn play_rejected_move_sound(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut rejections: EventReader<MoveRejected>,
+    player: Query<(), With<Player>>,
+) {
+    for _ in rejections.iter().filter(|rejection| player.contains(rejection.mover)) {
+        commands.spawn(AudioBundle {
+            source: asset_server.load("bonk.wav"),
+            settings: PlaybackSettings {
+                volume: Volume::new_relative(0.5),
+                ..PlaybackSettings::DESPAWN
+            },
+        });
+    }
+}
+
+fn remove_shake(shake: Res<CameraShake>, mut cameras: Query<&mut Transform, With<Camera2d>>) {
+    for mut transform in cameras.iter_mut() {
+        transform.translation -= shake.offset.extend(0.);
+    }
+}
+
+fn apply_shake(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut shake: ResMut<CameraShake>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+) {
+    shake.trauma = (shake.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.);
+    // Squared, so small jolts stay small and the shake tails off smoothly.
+    let strength = shake.trauma * shake.trauma;
+    shake.offset = if settings.reduce_motion || strength == 0. {
+        Vec2::ZERO
+    } else {
+        let mut rng = rand::thread_rng();
+        Vec2::new(rng.gen_range(-1. ..1.), rng.gen_range(-1. ..1.)) * MAX_SHAKE_OFFSET * strength
+    };
+    for mut transform in cameras.iter_mut() {
+        transform.translation += shake.offset.extend(0.);
+    }
+}
@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use crate::{AppState, LoopCounter};
+use crate::profile::Profile;
+use crate::rules::GameRules;
+use crate::shop::Upgrades;
+use crate::spawn_level::LevelSource;
+use crate::ui::spawn_button;
+
+/// The campaign, in order. Each level unlocks once the one before it is completed.
+const CAMPAIGN: [CampaignLevel; 4] = [
+    CampaignLevel {name: "First steps", file: "first-steps.ron"},
+    CampaignLevel {name: "Walled garden", file: "walled-garden.ron"},
+    CampaignLevel {name: "Relay", file: "relay.ron"},
+    CampaignLevel {name: "Count up", file: "count-up.ron"},
+];
+const LEVELS_DIR: &str = "assets/levels";
+
+pub struct CampaignPlugin;
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(CurrentCampaignLevel(None))
+            .add_systems(OnEnter(AppState::LevelSelect), spawn_level_select)
+            .add_systems(Update, press_level_select_buttons.run_if(in_state(AppState::LevelSelect)))
+            .add_systems(OnExit(AppState::LevelSelect), despawn_level_select)
+            .add_systems(OnEnter(AppState::GameOver), complete_level);
+    }
+}
+
+struct CampaignLevel {
+    name: &'static str,
+    file: &'static str,
+}
+
+impl CampaignLevel {
+    fn path(&self) -> PathBuf {
+        PathBuf::from(LEVELS_DIR).join(self.file)
+    }
+}
+
+/// Which campaign level is being played, if any. Cleared whenever something else is picked.
+#[derive(Resource)]
+pub struct CurrentCampaignLevel(pub Option<usize>);
+
+impl Profile {
+    fn is_level_unlocked(&self, index: usize) -> bool {
+        index == 0 || self.completed_levels.contains(CAMPAIGN[index - 1].file)
+    }
+}
+
+#[derive(Component)]
+struct LevelSelect;
+
+#[derive(Component)]
+enum LevelSelectButton {
+    Level(usize),
+    Back,
+}
+
+fn spawn_level_select(mut commands: Commands, profile: Res<Profile>) {
+    commands.spawn((
+        LevelSelect,
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+            background_color: Color::rgb(0.1, 0.08, 0.12).into(),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("Campaign", TextStyle {font_size: 60., ..default()}));
+        for (index, level) in CAMPAIGN.iter().enumerate() {
+            if !profile.is_level_unlocked(index) {
+                parent.spawn(TextBundle::from_section(
+                    format!("{}. Locked", index + 1),
+                    TextStyle {font_size: 30., color: Color::GRAY, ..default()}));
+                continue;
+            }
+            let completed = if profile.completed_levels.contains(level.file) { " (done)" } else { "" };
+            spawn_button(parent, format!("{}. {}{}", index + 1, level.name, completed), LevelSelectButton::Level(index));
+        }
+        spawn_button(parent, "Back", LevelSelectButton::Back);
+    });
+}
+
+fn despawn_level_select(mut commands: Commands, query: Query<Entity, With<LevelSelect>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn press_level_select_buttons(
+    interactions: Query<(&Interaction, &LevelSelectButton), Changed<Interaction>>,
+    mut level_source: ResMut<LevelSource>,
+    mut current_level: ResMut<CurrentCampaignLevel>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, button) in interactions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            LevelSelectButton::Level(index) => {
+                *level_source = LevelSource::File(CAMPAIGN[*index].path());
+                current_level.0 = Some(*index);
+                next_state.set(AppState::Playing);
+            },
+            LevelSelectButton::Back => next_state.set(AppState::MainMenu),
+        }
+    }
+}
+
+fn complete_level(
+    current_level: Res<CurrentCampaignLevel>,
+    loop_counter: Res<LoopCounter>,
+    rules: Res<GameRules>,
+    upgrades: Res<Upgrades>,
+    mut profile: ResMut<Profile>,
+) {
+    let Some(index) = current_level.0 else {
+        return;
+    };
+    if loop_counter.is_final_loop(&rules, &upgrades) && !profile.completed_levels.contains(CAMPAIGN[index].file) {
+        info!("Completed campaign level {}", CAMPAIGN[index].name);
+        profile.completed_levels.insert(CAMPAIGN[index].file.to_string());
+    }
+}
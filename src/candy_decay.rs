@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+
+use crate::{next_turn, AppState, SootSprite};
+use crate::grid::{GridCell, GridLocation};
+use crate::inventory::{CandyRemaining, Item};
+use crate::palette::Palette;
+use crate::rules::GameRules;
+use crate::spawn_level::{item_bundle, wall_bundle};
+
+/// How many rounds ahead candy starts to show cracks.
+const CRACK_ROUNDS: i32 = 3;
+/// Most the candy shakes, in radians, on the round before it hardens.
+const MAX_WOBBLE: f32 = 0.35;
+
+pub struct CandyDecayPlugin;
+
+impl Plugin for CandyDecayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            (decay_candy, restore_decayed_candy),
+            animate_cracking,
+        ).chain().after(next_turn).run_if(decay_enabled).run_if(in_state(AppState::Playing)));
+    }
+}
+
+fn decay_enabled(rules: Res<GameRules>) -> bool {
+    rules.candy_decay.is_some()
+}
+
+/// A wall that was candy until it went uncollected for too long. Undoing back past that puts the candy back.
+#[derive(Component, Clone, Copy)]
+struct DecayedCandy(Item);
+
+/// Candy that's about to harden, shaking harder and darkening as the round comes.
+#[derive(Component)]
+struct Cracking {
+    rounds_left: i32,
+    color: Color,
+}
+
+/// Soots take turns one at a time, so a round is over once the furthest-along soot has had its turn. Counting rounds
+/// rather than turns keeps a past self's board decaying on the same schedule it did while it was recorded.
+pub fn current_round(soots: &Query<(&SootSprite, &GridLocation)>) -> i32 {
+    soots.iter().map(|(soot, _)| soot.turn_number).max().unwrap_or(0)
+}
+
+// A soot standing on candy it can't take (out of order, or from the other side) holds the candy off until it leaves,
+// rather than ending up inside a wall. A past self whose recorded route runs into new wall just loses those turns.
+fn decay_candy(
+    mut commands: Commands,
+    rules: Res<GameRules>,
+    palette: Res<Palette>,
+    soots: Query<(&SootSprite, &GridLocation)>,
+    mut items: Query<(Entity, &Item, &Parent, &mut Sprite, &mut Transform, &Visibility, Option<&Cracking>)>,
+    cell_locations: Query<&GridLocation, With<GridCell>>,
+    mut candy_remaining: ResMut<CandyRemaining>,
+) {
+    let Some(lifetime) = rules.candy_decay else {
+        return;
+    };
+    let rounds_left = lifetime - current_round(&soots);
+
+    for (entity, item, parent, mut sprite, mut transform, visibility, cracking) in items.iter_mut() {
+        if !item.is_candy() || *visibility == Visibility::Hidden {
+            continue;
+        }
+        if rounds_left > CRACK_ROUNDS {
+            // Undone back to before the cracks showed.
+            if let Some(cracking) = cracking {
+                sprite.color = cracking.color;
+                transform.rotation = Quat::IDENTITY;
+                commands.entity(entity).remove::<Cracking>();
+            }
+            continue;
+        }
+        if rounds_left > 0 {
+            if cracking.is_none_or(|cracking| cracking.rounds_left != rounds_left) {
+                let color = cracking.map_or(sprite.color, |cracking| cracking.color);
+                commands.entity(entity).insert(Cracking {rounds_left, color});
+            }
+            continue;
+        }
+
+        let Ok(&location) = cell_locations.get(parent.get()) else {
+            continue;
+        };
+        if soots.iter().any(|(_, soot_location)| *soot_location == location) {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        commands.entity(parent.get()).with_children(|parent| {
+            parent.spawn((DecayedCandy(*item), wall_bundle(&palette)));
+        });
+        candy_remaining.0 -= 1;
+    }
+}
+
+// Only undo can turn the round back, and it puts the candy count back along with it.
+fn restore_decayed_candy(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+    rules: Res<GameRules>,
+    soots: Query<(&SootSprite, &GridLocation)>,
+    decayed: Query<(Entity, &DecayedCandy, &Parent)>,
+) {
+    let Some(lifetime) = rules.candy_decay else {
+        return;
+    };
+    if current_round(&soots) >= lifetime {
+        return;
+    }
+
+    for (entity, &DecayedCandy(item), parent) in decayed.iter() {
+        commands.entity(entity).despawn_recursive();
+        commands.entity(parent.get()).with_children(|parent| {
+            parent.spawn(item_bundle(item, &asset_server, &palette));
+        });
+    }
+}
+
+fn animate_cracking(time: Res<Time>, mut candies: Query<(&Cracking, &mut Transform, &mut Sprite)>) {
+    for (cracking, mut transform, mut sprite) in candies.iter_mut() {
+        // Shakes harder and gets darker every round, up to all the way on the last one.
+        let progress = (CRACK_ROUNDS - cracking.rounds_left + 1) as f32 / CRACK_ROUNDS as f32;
+        let wobble = MAX_WOBBLE * progress * (time.elapsed_seconds() * 30.).sin();
+        transform.rotation = Quat::from_rotation_z(wobble);
+
+        let [r, g, b, a] = cracking.color.as_rgba_f32();
+        let shade = 1. - 0.5 * progress;
+        sprite.color = Color::rgba(r * shade, g * shade, b * shade, a);
+    }
+}
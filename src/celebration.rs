@@ -0,0 +1,127 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::{AppState, StartLoop, DespawnOnExitGameOver, LoopCounter, SootSprite, GRID_SPACING};
+use crate::grid::{ApplyGridMovement, GridLocation, MovementComplete};
+use crate::level_spec::LevelSpec;
+use crate::particles::{ParticleEffect, ParticleEmitter};
+use crate::settings::Settings;
+use crate::spawn_level::SpawnLevel;
+
+pub struct CelebrationPlugin;
+
+impl Plugin for CelebrationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Playing), spawn_exit_flag.after(SpawnLevel).in_set(StartLoop))
+            .add_systems(Update, celebrate_arrivals.after(ApplyGridMovement).run_if(in_state(AppState::Playing)))
+            // The last arrival ends the loop, so let the effects keep playing behind the game over screen.
+            .add_systems(Update, (hop, wave_flag));
+    }
+}
+
+const HOP_SECS: f32 = 0.35;
+const HOP_HEIGHT: f32 = 30.;
+const FLAG_WAVE_SECS: f32 = 1.5;
+
+#[derive(Component)]
+struct ExitFlag;
+
+#[derive(Component)]
+struct Hop {
+    base: Vec3,
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct FlagWave(Timer);
+
+// Marks the player's exit; past selves may be headed elsewhere on levels where the exit moves.
+fn spawn_exit_flag(mut commands: Commands, level_spec: Res<LevelSpec>, loop_counter: Res<LoopCounter>) {
+    let corner = (level_spec.end_for_loop(loop_counter.0) * GRID_SPACING).as_vec2() + Vec2::new(GRID_SPACING as f32 / 4., GRID_SPACING as f32 / 6.);
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::DARK_GRAY,
+                custom_size: Some(Vec2::new(4., 56.)),
+                ..default()
+            },
+            transform: Transform::from_translation(corner.extend(0.1)),
+            ..default()
+        },
+        DespawnOnExitGameOver,
+    )).with_children(|parent| {
+        // The cloth hangs off the top of the pole, anchored at its left edge so it swings around the pole.
+        parent.spawn((
+            ExitFlag,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::ORANGE_RED,
+                    custom_size: Some(Vec2::new(28., 18.)),
+                    anchor: bevy::sprite::Anchor::CenterLeft,
+                    ..default()
+                },
+                transform: Transform::from_xyz(2., 19., 0.),
+                ..default()
+            },
+        ));
+    });
+}
+
+fn celebrate_arrivals(
+    mut commands: Commands,
+    mut movement_events: EventReader<MovementComplete>,
+    soots: Query<(&GridLocation, &Transform, &SootSprite)>,
+    flags: Query<Entity, With<ExitFlag>>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    settings: Res<Settings>,
+) {
+    for &MovementComplete{entity} in movement_events.iter() {
+        let Ok((location, transform, soot)) = soots.get(entity) else {
+            continue;
+        };
+        if location.0 != level_spec.end_for_loop(loop_counter.0 - soot.id.loop_number()) {
+            continue;
+        }
+
+        commands.entity(entity).insert(Hop {
+            base: transform.translation,
+            timer: Timer::from_seconds(HOP_SECS, TimerMode::Once),
+        });
+        // The hop says who arrived; the rest is decoration.
+        if settings.low_spec {
+            continue;
+        }
+        for flag in flags.iter() {
+            commands.entity(flag).insert(FlagWave(Timer::from_seconds(FLAG_WAVE_SECS, TimerMode::Once)));
+        }
+        commands.spawn(ParticleEmitter::bundle(ParticleEffect::Confetti, transform.translation.truncate()));
+    }
+}
+
+fn hop(mut commands: Commands, time: Res<Time>, mut soots: Query<(Entity, &mut Transform, &mut Hop)>) {
+    for (entity, mut transform, mut hop) in soots.iter_mut() {
+        hop.timer.tick(time.delta());
+        let height = (hop.timer.percent() * PI).sin() * HOP_HEIGHT;
+        transform.translation = hop.base + Vec3::Y * height;
+        if hop.timer.finished() {
+            transform.translation = hop.base;
+            commands.entity(entity).remove::<Hop>();
+        }
+    }
+}
+
+fn wave_flag(mut commands: Commands, time: Res<Time>, mut flags: Query<(Entity, &mut Transform, &mut FlagWave)>) {
+    for (entity, mut transform, mut wave) in flags.iter_mut() {
+        let timer = &mut wave.0;
+        timer.tick(time.delta());
+        let amplitude = 0.4 * timer.percent_left();
+        transform.rotation = Quat::from_rotation_z((timer.elapsed_secs() * 4. * PI).sin() * amplitude);
+        if timer.finished() {
+            transform.rotation = Quat::IDENTITY;
+            commands.entity(entity).remove::<FlagWave>();
+        }
+    }
+}
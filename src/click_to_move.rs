@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::{buffer_move_intents, debuffer_move_inputs, AppState, CurrentSoot, LoopCounter, MoveBuffer, Player, SootId, StartLoop};
+use crate::grid::{AnimateTranslation, GridCells, GridLocation};
+use crate::intents::MoveIntent;
+use crate::inventory::{Inventory, Item};
+use crate::level_spec::LevelSpec;
+use crate::shop::Upgrades;
+use crate::solver::find_path;
+use crate::toast::Toast;
+use crate::undo::Undone;
+
+pub struct ClickToMovePlugin;
+
+impl Plugin for ClickToMovePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(ClickDestination(None))
+            .add_systems(OnEnter(AppState::Playing), clear_destination.in_set(StartLoop))
+            .add_systems(Update, (
+                pick_destination,
+                walk_to_destination,
+            ).chain().after(buffer_move_intents).before(debuffer_move_inputs).run_if(in_state(AppState::Playing)))
+            .add_systems(Update, clear_destination.run_if(on_event::<Undone>()));
+    }
+}
+
+/// The cell the player clicked or tapped on and is walking toward, one turn at a time.
+#[derive(Resource)]
+struct ClickDestination(Option<IVec2>);
+
+fn clear_destination(mut destination: ResMut<ClickDestination>) {
+    destination.0 = None;
+}
+
+fn pick_destination(
+    mut intents: EventReader<MoveIntent>,
+    mut destination: ResMut<ClickDestination>,
+    mut move_buffer: ResMut<MoveBuffer>,
+) {
+    for intent in intents.iter() {
+        if let MoveIntent::GoTo(cell) = intent {
+            // A click replaces whatever was typed ahead.
+            move_buffer.clear();
+            destination.0 = Some(*cell);
+        }
+    }
+}
+
+/// Feeds the next step toward the destination into the move buffer whenever it's the player's turn.
+///
+/// The path is planned again from scratch every step, since past selves can take fuel or knock the player off course.
+fn walk_to_destination(
+    mut destination: ResMut<ClickDestination>,
+    mut move_buffer: ResMut<MoveBuffer>,
+    player: Query<(&GridLocation, &Inventory, &AnimateTranslation), With<Player>>,
+    current_soot: Res<CurrentSoot>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    upgrades: Res<Upgrades>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    items: Query<&Item>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let Some(target) = destination.0 else {
+        return;
+    };
+    // Any move typed in takes over from the click.
+    if !move_buffer.moves.is_empty() {
+        destination.0 = None;
+        return;
+    }
+    let Ok((location, inventory, animation)) = player.get_single() else {
+        return;
+    };
+    if current_soot.0 != SootId::Player || !animation.timer.finished() {
+        return;
+    }
+
+    let fuel_cells: Vec<(IVec2, i32)> = cells.0.keys()
+        .flat_map(|&cell| cells.contents(cell, &children).iter()
+            .filter_map(|&entity| match items.get(entity) {
+                Ok(&Item::Fuel(amount)) => Some((cell, amount)),
+                _ => None,
+            })
+            .collect::<Vec<_>>())
+        .collect();
+    match find_path(&level_spec.for_loop(loop_counter.0), &fuel_cells, location.0, inventory.fuel, target, &upgrades) {
+        Some(path) if !path.is_empty() => move_buffer.push(path[0]),
+        Some(_) => destination.0 = None,
+        None => {
+            destination.0 = None;
+            toasts.send(Toast::warning("Can't get there from here"));
+        },
+    }
+}
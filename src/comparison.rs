@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{AppState, DespawnOnExitGameOver, LoopCounter, Player, Recordings, MAX_X, MAX_Y};
+use crate::inventory::Inventory;
+use crate::level_spec::{CandyColor, LevelSpec};
+use crate::replay::route_cells;
+use crate::shop::Upgrades;
+use crate::solver::best_route;
+
+const CELL_SIZE: f32 = 28.;
+const ITEM_SIZE: f32 = 10.;
+const SOOT_SIZE: f32 = 20.;
+const STEP_TIME: Duration = Duration::from_millis(350);
+/// Steps to hold on the finished routes before both boards start over.
+const END_PAUSE_STEPS: usize = 4;
+
+pub struct ComparisonPlugin;
+
+impl Plugin for ComparisonPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<ToggleComparison>()
+            .insert_resource(ComparisonPlayback::default())
+            .add_systems(Update, (
+                toggle_comparison.run_if(on_event::<ToggleComparison>()),
+                play_comparison,
+            ).chain().run_if(in_state(AppState::GameOver)));
+    }
+}
+
+/// Shows or hides the player's loop next to the best route for it, played back side by side on two mini boards.
+#[derive(Event)]
+pub struct ToggleComparison;
+
+#[derive(Resource)]
+struct ComparisonPlayback {
+    timer: Timer,
+    step: usize,
+}
+
+impl Default for ComparisonPlayback {
+    fn default() -> Self {
+        Self {timer: Timer::new(STEP_TIME, TimerMode::Repeating), step: 0}
+    }
+}
+
+#[derive(Component)]
+struct Comparison;
+
+/// Every cell one route visits, in order.
+#[derive(Component)]
+struct MiniBoard(Vec<IVec2>);
+
+#[derive(Component)]
+struct MiniSoot;
+
+/// Something a soot picks up by walking over its cell.
+#[derive(Component)]
+struct MiniItem(IVec2);
+
+fn toggle_comparison(
+    mut commands: Commands,
+    mut events: EventReader<ToggleComparison>,
+    comparisons: Query<Entity, With<Comparison>>,
+    mut playback: ResMut<ComparisonPlayback>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    upgrades: Res<Upgrades>,
+    recordings: Res<Recordings>,
+    inventory: Query<&Inventory, With<Player>>,
+) {
+    events.clear();
+    if !comparisons.is_empty() {
+        for entity in comparisons.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let level_spec = level_spec.for_loop(loop_counter.0);
+    let start = level_spec.start;
+    let yours = route_cells(&level_spec, start, recordings.0[0].moves.iter().copied());
+    let (best_moves, best_points) = best_route(&level_spec, start, upgrades.starting_fuel, &upgrades);
+    let best = route_cells(&level_spec, start, best_moves);
+    let your_points = inventory.get_single().map_or(0, |inventory| inventory.points);
+    *playback = default();
+
+    commands.spawn((
+        Comparison,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(20.),
+                top: Val::Px(20.),
+                column_gap: Val::Px(20.),
+                padding: UiRect::all(Val::Px(10.)),
+                ..default()
+            },
+            background_color: Color::rgba(0.1, 0.08, 0.12, 0.9).into(),
+            ..default()
+        },
+        DespawnOnExitGameOver,
+    )).with_children(|parent| {
+        spawn_mini_board(parent, &level_spec, format!("Yours: {} points", your_points), yours);
+        spawn_mini_board(parent, &level_spec, format!("Best: {} points", best_points), best);
+    });
+}
+
+/// Where to put a square `size` across so it's centered on `cell`. Board cells are y-up, UI is y-down.
+fn cell_position(cell: IVec2, size: f32) -> (Val, Val) {
+    (
+        Val::Px(cell.x as f32 * CELL_SIZE + (CELL_SIZE - size) / 2.),
+        Val::Px((MAX_Y - 1 - cell.y) as f32 * CELL_SIZE + (CELL_SIZE - size) / 2.),
+    )
+}
+
+fn spawn_mini_board(parent: &mut ChildBuilder, level_spec: &LevelSpec, label: String, route: Vec<IVec2>) {
+    let square = |cell: IVec2, size: f32, color: Color| {
+        let (left, top) = cell_position(cell, size);
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left,
+                top,
+                width: Val::Px(size),
+                height: Val::Px(size),
+                ..default()
+            },
+            background_color: color.into(),
+            ..default()
+        }
+    };
+    let start = route[0];
+
+    parent.spawn(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(6.),
+            ..default()
+        },
+        ..default()
+    }).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(label, TextStyle {font_size: 20., ..default()}));
+        parent.spawn((
+            MiniBoard(route),
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(MAX_X as f32 * CELL_SIZE),
+                    height: Val::Px(MAX_Y as f32 * CELL_SIZE),
+                    ..default()
+                },
+                background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+                ..default()
+            },
+        )).with_children(|parent| {
+            parent.spawn(square(level_spec.end, CELL_SIZE, Color::rgb(0.3, 0.5, 0.3)));
+            for &wall in level_spec.walls.iter() {
+                parent.spawn(square(wall, CELL_SIZE, Color::rgb(0.1, 0.1, 0.1)));
+            }
+            for &(candy, color) in level_spec.candies.iter() {
+                let color = match color {
+                    CandyColor::Red => Color::RED,
+                    CandyColor::Green => Color::GREEN,
+                    CandyColor::Yellow => Color::YELLOW,
+                };
+                parent.spawn((MiniItem(candy), square(candy, ITEM_SIZE, color)));
+            }
+            for &candy in level_spec.numbered_candies.iter() {
+                parent.spawn((MiniItem(candy), square(candy, ITEM_SIZE, Color::WHITE)));
+            }
+            for &fuel in level_spec.fuel.iter().chain(level_spec.canisters.iter().map(|(fuel, _)| fuel)) {
+                parent.spawn((MiniItem(fuel), square(fuel, ITEM_SIZE, Color::ORANGE)));
+            }
+            parent.spawn((MiniSoot, square(start, SOOT_SIZE, Color::rgb(0.2, 0.2, 0.2))));
+        });
+    });
+}
+
+/// Steps both routes forward together, then holds on the end for a moment and starts over.
+fn play_comparison(
+    time: Res<Time>,
+    mut playback: ResMut<ComparisonPlayback>,
+    boards: Query<(&MiniBoard, &Children)>,
+    mut soots: Query<&mut Style, With<MiniSoot>>,
+    mut items: Query<(&MiniItem, &mut Visibility)>,
+) {
+    if boards.is_empty() {
+        return;
+    }
+    if playback.timer.tick(time.delta()).just_finished() {
+        let longest = boards.iter().map(|(board, _)| board.0.len()).max().unwrap_or(0);
+        playback.step = (playback.step + 1) % (longest + END_PAUSE_STEPS);
+    }
+
+    for (board, children) in boards.iter() {
+        let step = playback.step.min(board.0.len() - 1);
+        let visited = &board.0[..=step];
+        for &child in children.iter() {
+            if let Ok(mut style) = soots.get_mut(child) {
+                (style.left, style.top) = cell_position(board.0[step], SOOT_SIZE);
+            }
+            if let Ok((item, mut visibility)) = items.get_mut(child) {
+                *visibility = if visited.contains(&item.0) { Visibility::Hidden } else { Visibility::Inherited };
+            }
+        }
+    }
+}
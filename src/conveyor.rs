@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use crate::{soot_blocks, AppState, LoopCounter, Move, SootSprite, MAX_X, MAX_Y};
+use crate::grid::{AnimateTranslation, ApplyGridMovement, FollowUpMove, GridCells, GridLocation};
+use crate::inventory::PickUpItems;
+use crate::level_spec::LevelSpec;
+use crate::rules::GameRules;
+use crate::spawn_level::Wall;
+use crate::teleporter::{enter_teleporters, Teleporter};
+
+/// How far a soot being pushed leans back against the belt, in radians.
+const CONVEYED_TILT: f32 = 0.3;
+
+pub struct ConveyorPlugin;
+
+impl Plugin for ConveyorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            ride_conveyors.after(enter_teleporters).before(ApplyGridMovement),
+            animate_conveyed.after(ApplyGridMovement).before(PickUpItems),
+        ).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Pushes a soot that ends its turn here one cell in `direction`.
+#[derive(Component, Clone, Copy)]
+pub struct Conveyor {
+    pub direction: IVec2,
+}
+
+/// On a soot being carried along by a conveyor rather than moving under its own steam.
+#[derive(Component)]
+struct Conveyed;
+
+// The push is tacked onto the move that ended on the belt, waits included, so the turn only passes once the soot
+// has come to rest. Each soot is only pushed at the end of its own turns, so a recording replays the same no
+// matter how many soots are on the board. Where soots block each other, one in front of the belt holds it up.
+pub fn ride_conveyors(
+    mut commands: Commands,
+    mut moves: EventReader<Move>,
+    movers: Query<(Entity, &GridLocation, Option<&SootSprite>)>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    conveyors: Query<&Conveyor>,
+    teleporters: Query<(), With<Teleporter>>,
+    walls: Query<(), With<Wall>>,
+    rules: Res<GameRules>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+) {
+    for event in moves.iter() {
+        let Ok((_, location, soot)) = movers.get(event.mover) else {
+            continue;
+        };
+        let contents = cells.contents(location.0, &children);
+        // Teleporters take the soot away before the belt gets a chance.
+        if contents.iter().any(|&entity| teleporters.contains(entity)) {
+            continue;
+        }
+        let Some(conveyor) = contents.iter().find_map(|&entity| conveyors.get(entity).ok()) else {
+            continue;
+        };
+        let pushed = location.0 + conveyor.direction;
+        let soots = movers.iter().filter_map(|(entity, location, soot)| soot.map(|soot| (entity, location.0, soot.id)));
+        let open = (0..MAX_X).contains(&pushed.x) && (0..MAX_Y).contains(&pushed.y)
+            && !cells.contents(pushed, &children).iter().any(|&entity| walls.contains(entity))
+            && !(soot.is_some() && soot_blocks(pushed, event.mover, soots, &rules, &level_spec, &loop_counter));
+        if open {
+            commands.entity(event.mover).insert((FollowUpMove(pushed), Conveyed));
+        }
+    }
+}
+
+// Leans the soot back against the push, so being carried reads differently from walking.
+fn animate_conveyed(
+    mut commands: Commands,
+    mut soots: Query<(Entity, &mut Transform, &AnimateTranslation), (With<Conveyed>, Without<FollowUpMove>)>,
+) {
+    for (entity, mut transform, animation) in soots.iter_mut() {
+        if animation.timer.finished() {
+            transform.rotation = Quat::IDENTITY;
+            commands.entity(entity).remove::<Conveyed>();
+            continue;
+        }
+        let push = animation.end - animation.start;
+        let lean = -push.x.signum() * CONVEYED_TILT * (animation.timer.percent() * std::f32::consts::PI).sin();
+        transform.rotation = Quat::from_rotation_z(lean);
+    }
+}
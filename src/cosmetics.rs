@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::SootSprite;
+use crate::achievements::{Achievement, AchievementUnlocked};
+use crate::profile::Profile;
+use crate::toast::Toast;
+
+pub struct CosmeticsPlugin;
+
+impl Plugin for CosmeticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (unlock_cosmetics, dress_soots));
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum Cosmetic {
+    CandyHat,
+    FuelHat,
+    Ember,
+    Moss,
+    Frost,
+}
+
+enum Look {
+    /// Texture drawn on top of the soot.
+    Hat(&'static str),
+    /// Tint for the soot's body.
+    Color(Color),
+}
+
+struct CosmeticDef {
+    cosmetic: Cosmetic,
+    name: &'static str,
+    look: Look,
+    /// Cosmetics without an achievement are available from the start.
+    unlocked_by: Option<Achievement>,
+}
+
+const REGISTRY: [CosmeticDef; 5] = [
+    CosmeticDef {cosmetic: Cosmetic::CandyHat, name: "Candy hat", look: Look::Hat("red-candy.png"),
+        unlocked_by: Some(Achievement::FirstCandy)},
+    CosmeticDef {cosmetic: Cosmetic::FuelHat, name: "Fuel can", look: Look::Hat("fuel.png"),
+        unlocked_by: Some(Achievement::CleanSweep)},
+    CosmeticDef {cosmetic: Cosmetic::Ember, name: "Ember", look: Look::Color(Color::rgb(1., 0.6, 0.5)),
+        unlocked_by: None},
+    CosmeticDef {cosmetic: Cosmetic::Moss, name: "Moss", look: Look::Color(Color::rgb(0.6, 1., 0.6)),
+        unlocked_by: None},
+    CosmeticDef {cosmetic: Cosmetic::Frost, name: "Frost", look: Look::Color(Color::rgb(0.6, 0.8, 1.)),
+        unlocked_by: Some(Achievement::FullCircle)},
+];
+
+impl Cosmetic {
+    fn def(&self) -> &'static CosmeticDef {
+        REGISTRY.iter().find(|def| def.cosmetic == *self).unwrap()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.def().name
+    }
+
+    fn is_hat(&self) -> bool {
+        matches!(self.def().look, Look::Hat(_))
+    }
+}
+
+impl Profile {
+    fn is_unlocked(&self, cosmetic: Cosmetic) -> bool {
+        cosmetic.def().unlocked_by.is_none() || self.unlocked_cosmetics.contains(&cosmetic)
+    }
+
+    /// Steps through the unlocked hats, then back to no hat.
+    pub fn cycle_hat(&mut self) {
+        self.hat = self.next_unlocked(self.hat, true);
+    }
+
+    /// Steps through the unlocked colors, then back to the plain soot.
+    pub fn cycle_color(&mut self) {
+        self.color = self.next_unlocked(self.color, false);
+    }
+
+    fn next_unlocked(&self, current: Option<Cosmetic>, hats: bool) -> Option<Cosmetic> {
+        let mut options = REGISTRY.iter()
+            .map(|def| def.cosmetic)
+            .filter(|cosmetic| cosmetic.is_hat() == hats && self.is_unlocked(*cosmetic));
+        match current {
+            None => options.next(),
+            Some(current) => options.skip_while(|cosmetic| *cosmetic != current).nth(1),
+        }
+    }
+}
+
+fn unlock_cosmetics(
+    mut profile: ResMut<Profile>,
+    mut achievements: EventReader<AchievementUnlocked>,
+    mut toasts: EventWriter<Toast>,
+) {
+    for AchievementUnlocked(achievement) in achievements.iter() {
+        for def in REGISTRY.iter().filter(|def| def.unlocked_by == Some(*achievement)) {
+            if !profile.unlocked_cosmetics.contains(&def.cosmetic) {
+                info!("Unlocked cosmetic: {}", def.name);
+                toasts.send(Toast::success(format!("New look unlocked: {}", def.name)));
+                profile.unlocked_cosmetics.insert(def.cosmetic);
+            }
+        }
+    }
+}
+
+// Past selves are the same soot, so they wear the same look on top of their faded tint.
+fn dress_soots(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    profile: Res<Profile>,
+    mut soots: Query<(Entity, &mut TextureAtlasSprite), Added<SootSprite>>,
+) {
+    for (entity, mut sprite) in soots.iter_mut() {
+        if let Some(Look::Color(color)) = profile.color.map(|cosmetic| &cosmetic.def().look) {
+            let [r, g, b, _] = color.as_rgba_f32();
+            let faded = sprite.color;
+            sprite.color = Color::rgba(faded.r() * r, faded.g() * g, faded.b() * b, faded.a());
+        }
+
+        if let Some(Look::Hat(texture)) = profile.hat.map(|cosmetic| &cosmetic.def().look) {
+            commands.entity(entity).with_children(|parent| {
+                parent.spawn(SpriteBundle {
+                    texture: asset_server.load(*texture),
+                    sprite: Sprite {
+                        color: Color::rgba(1., 1., 1., sprite.color.a()),
+                        custom_size: Some(Vec2::splat(48.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., 50., 0.01),
+                    ..default()
+                });
+            });
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::AppState;
+use crate::inventory::Item;
+use crate::particles::Particle;
+
+/// How many visits to the same state in a row a count has to grow over before it's reported as a leak.
+const LEAK_WINDOW: usize = 4;
+const OVERLAY_Z_INDEX: i32 = 100;
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(EntityCensus::default())
+            .add_systems(Startup, spawn_debug_overlay)
+            .add_systems(Update, (
+                take_census.run_if(state_changed::<AppState>()),
+                toggle_debug_overlay,
+                update_debug_overlay,
+            ).chain());
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counts {
+    entities: usize,
+    items: usize,
+    audio: usize,
+    particles: usize,
+    ui_nodes: usize,
+}
+
+impl Counts {
+    const LABELS: [&'static str; 5] = ["Entities", "Items", "Audio", "Particles", "UI nodes"];
+
+    fn values(&self) -> [usize; 5] {
+        [self.entities, self.items, self.audio, self.particles, self.ui_nodes]
+    }
+}
+
+/// Entity counts sampled on entering each state. Coming back to the same state should find the same world, so a
+/// count that keeps going up across visits means something isn't being despawned.
+#[derive(Resource, Default)]
+struct EntityCensus {
+    history: HashMap<AppState, VecDeque<Counts>>,
+    /// Per category, whether it's currently growing; only the start of a streak gets logged.
+    growing: [bool; 5],
+}
+
+#[derive(Component)]
+struct DebugOverlay;
+
+fn count(
+    entities: &Query<Entity>,
+    items: &Query<(), With<Item>>,
+    audio: &Query<(), With<Handle<AudioSource>>>,
+    particles: &Query<(), With<Particle>>,
+    ui_nodes: &Query<(), With<Node>>,
+) -> Counts {
+    Counts {
+        entities: entities.iter().count(),
+        items: items.iter().count(),
+        audio: audio.iter().count(),
+        particles: particles.iter().count(),
+        ui_nodes: ui_nodes.iter().count(),
+    }
+}
+
+fn take_census(
+    mut census: ResMut<EntityCensus>,
+    app_state: Res<State<AppState>>,
+    entities: Query<Entity>,
+    items: Query<(), With<Item>>,
+    audio: Query<(), With<Handle<AudioSource>>>,
+    particles: Query<(), With<Particle>>,
+    ui_nodes: Query<(), With<Node>>,
+) {
+    let counts = count(&entities, &items, &audio, &particles, &ui_nodes);
+    let history = census.history.entry(*app_state.get()).or_default();
+    history.push_back(counts);
+    if history.len() > LEAK_WINDOW {
+        history.pop_front();
+    }
+    if history.len() < LEAK_WINDOW {
+        return;
+    }
+
+    let samples: Vec<[usize; 5]> = history.iter().map(Counts::values).collect();
+    for (category, label) in Counts::LABELS.iter().enumerate() {
+        let growing = samples.windows(2).all(|pair| pair[1][category] > pair[0][category]);
+        if growing && !census.growing[category] {
+            warn!("{} count grew on each of the last {} entries into {:?}: {:?}",
+                label, LEAK_WINDOW, app_state.get(), samples.iter().map(|sample| sample[category]).collect::<Vec<_>>());
+        }
+        census.growing[category] = growing;
+    }
+}
+
+fn spawn_debug_overlay(mut commands: Commands) {
+    commands.spawn((
+        DebugOverlay,
+        TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(60.),
+                right: Val::Px(10.),
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.6).into(),
+            z_index: ZIndex::Global(OVERLAY_Z_INDEX),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+}
+
+// F12 shows and hides the overlay.
+fn toggle_debug_overlay(keyboard_input: Res<Input<KeyCode>>, mut overlay: Query<&mut Visibility, With<DebugOverlay>>) {
+    if !keyboard_input.just_pressed(KeyCode::F12) {
+        return;
+    }
+    for mut visibility in overlay.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_debug_overlay(
+    census: Res<EntityCensus>,
+    mut overlay: Query<(&mut Text, &Visibility), With<DebugOverlay>>,
+    entities: Query<Entity>,
+    items: Query<(), With<Item>>,
+    audio: Query<(), With<Handle<AudioSource>>>,
+    particles: Query<(), With<Particle>>,
+    ui_nodes: Query<(), With<Node>>,
+) {
+    let Ok((mut text, visibility)) = overlay.get_single_mut() else {
+        return;
+    };
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+
+    let counts = count(&entities, &items, &audio, &particles, &ui_nodes).values();
+    text.sections = Counts::LABELS.iter().enumerate().map(|(category, label)| {
+        let (note, color) = if census.growing[category] { (" (growing)", Color::RED) } else { ("", Color::WHITE) };
+        TextSection::new(format!("{}: {}{}\n", label, counts[category], note), TextStyle {font_size: 20., color, ..default()})
+    }).collect();
+}
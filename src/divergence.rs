@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+
+use crate::{next_turn, AppState, DespawnOnExitGameOver, LoopCounter, SootId, SootSprite, Recordings, GRID_SPACING, SOOT_Z};
+use crate::grid::{AnimateTranslation, GridLocation};
+use crate::level_spec::LevelSpec;
+
+const GHOST_COLOR: Color = Color::rgba(1., 1., 1., 0.35);
+
+pub struct DivergencePlugin;
+
+impl Plugin for DivergencePlugin {
+    fn build(&self, app: &mut App) {
+        // After next_turn, so a move that just landed has also bumped its soot's turn number.
+        app.add_systems(Update, show_divergence_ghosts.after(next_turn).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Marks where a past self's recording says it should be, when it isn't there.
+#[derive(Component)]
+struct DivergenceGhost {
+    soot: Entity,
+}
+
+fn show_divergence_ghosts(
+    mut commands: Commands,
+    recordings: Res<Recordings>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    soots: Query<(Entity, &SootSprite, &GridLocation, &AnimateTranslation, &Handle<TextureAtlas>)>,
+    mut ghosts: Query<(Entity, &DivergenceGhost, &mut Transform)>,
+) {
+    for (soot_entity, soot, location, animation, atlas) in soots.iter() {
+        if soot.id == SootId::Player {
+            continue;
+        }
+        // Location updates before the turn counter does, so only compare once the move has landed.
+        if !animation.timer.finished() {
+            continue;
+        }
+
+        let moves = recordings.for_soot(soot.id).map(|recording| recording.moves.as_slice()).unwrap_or_default();
+        let replayed = moves.iter().take(soot.turn_number as usize);
+        let level_spec = level_spec.for_loop(loop_counter.0 - soot.id.loop_number());
+        let expected = replayed.fold(level_spec.start, |position, offset| level_spec.landing_cell(position, *offset));
+        let ghost = ghosts.iter_mut().find(|(_, ghost, _)| ghost.soot == soot_entity);
+
+        match (expected == location.0, ghost) {
+            (true, Some((ghost_entity, _, _))) => {
+                commands.entity(ghost_entity).despawn();
+            },
+            (false, Some((_, _, mut transform))) => {
+                transform.translation = (expected * GRID_SPACING).as_vec2().extend(SOOT_Z - 0.1);
+            },
+            (false, None) => {
+                commands.spawn((
+                    DivergenceGhost{soot: soot_entity},
+                    SpriteSheetBundle {
+                        texture_atlas: atlas.clone(),
+                        sprite: TextureAtlasSprite {
+                            color: GHOST_COLOR,
+                            ..default()
+                        },
+                        transform: Transform::from_translation((expected * GRID_SPACING).as_vec2().extend(SOOT_Z - 0.1)),
+                        ..default()
+                    },
+                    DespawnOnExitGameOver,
+                ));
+            },
+            (true, None) => {},
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+use crate::{move_soot_on_grid, AppState, Move, WAIT};
+use crate::grid::{GridCells, GridLocation};
+use crate::ice::slide_on_ice;
+use crate::inventory::Inventory;
+use crate::palette::Palette;
+use crate::spawn_level::Wall;
+
+const DOOR_Z: f32 = 0.1;
+
+pub struct DoorsPlugin;
+
+impl Plugin for DoorsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<DoorUnlocked>()
+            .add_systems(Update, (
+                unlock_doors.after(move_soot_on_grid).before(slide_on_ice),
+                play_unlock_sound,
+            ).chain().run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// A wall that a soot holding a key can walk into, using the key up to open it for good. Slides and pushes stop at
+/// it like at any other wall; only a step of the soot's own opens it.
+#[derive(Component, Clone, Copy)]
+pub struct Door;
+
+/// Sent when a door is opened, with the cell it was on.
+#[derive(Event)]
+pub struct DoorUnlocked(pub IVec2);
+
+/// A locked door as it sits in its cell. Also puts opened doors back on undo.
+pub fn door_bundle(palette: &Palette) -> (Wall, Door, SpriteBundle) {
+    (
+        Wall,
+        Door,
+        SpriteBundle {
+            sprite: Sprite {
+                color: palette.door,
+                custom_size: Some(Vec2::splat(128.)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., DOOR_Z),
+            ..default()
+        },
+    )
+}
+
+// validate_move only lets a soot into a door's cell if it has a key to spend.
+fn unlock_doors(
+    mut commands: Commands,
+    mut moves: EventReader<Move>,
+    mut soots: Query<(&GridLocation, &mut Inventory)>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    doors: Query<(), With<Door>>,
+    mut unlocked: EventWriter<DoorUnlocked>,
+) {
+    for event in moves.iter().filter(|event| event.offset != WAIT) {
+        let Ok((location, mut inventory)) = soots.get_mut(event.mover) else {
+            continue;
+        };
+        for &entity in cells.contents(location.0, &children).iter().filter(|&&entity| doors.contains(entity)) {
+            commands.entity(entity).despawn_recursive();
+            inventory.keys -= 1;
+            unlocked.send(DoorUnlocked(location.0));
+        }
+    }
+}
+
+fn play_unlock_sound(mut commands: Commands, asset_server: Res<AssetServer>, mut unlocked: EventReader<DoorUnlocked>) {
+    for _ in unlocked.iter() {
+        commands.spawn(AudioBundle {
+            source: asset_server.load("unlock.wav"),
+            settings: PlaybackSettings::DESPAWN,
+        });
+    }
+}
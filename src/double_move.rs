@@ -0,0 +1,21 @@
+use bevy::prelude::*;
+
+use crate::{next_turn, AppState, SootSprite};
+use crate::inventory::{Item, ItemGet, PickUpItems};
+
+pub struct DoubleMovePlugin;
+
+impl Plugin for DoubleMovePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, save_up_moves.after(PickUpItems).before(next_turn).run_if(in_state(AppState::Playing)));
+    }
+}
+
+// Picked up at the end of a move, so the extra move waits for the soot's next turn rather than tacking onto this one.
+fn save_up_moves(mut pickups: EventReader<ItemGet>, mut soots: Query<&mut SootSprite>) {
+    for pickup in pickups.iter().filter(|pickup| matches!(pickup.item, Item::DoubleMove)) {
+        if let Ok(mut soot) = soots.get_mut(pickup.soot) {
+            soot.bonus_moves += 1;
+        }
+    }
+}
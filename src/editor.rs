@@ -0,0 +1,406 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::{despawn_after_game_over, reset_run, AppState, GRID_SPACING, MAX_X, MAX_Y};
+use crate::campaign::CurrentCampaignLevel;
+use crate::grid::cursor_grid_location;
+use crate::level_spec::{CandyColor, LevelSpec};
+use crate::palette::Palette;
+use crate::rules::GameRules;
+use crate::shop::Upgrades;
+use crate::solver::unreachable_candies;
+use crate::spawn_level::LevelSource;
+use crate::toast::Toast;
+
+const EDITOR_LEVEL_PATH: &str = "assets/levels/custom.ron";
+const TILE_COLOR: Color = Color::PURPLE;
+const WALL_COLOR: Color = Color::rgb(0.2, 0.15, 0.25);
+const NETHER_CANDY_COLOR: Color = Color::rgba(0.7, 0.4, 1., 0.8);
+const ICE_COLOR: Color = Color::rgba(0.75, 0.9, 1., 0.7);
+const CONVEYOR_COLOR: Color = Color::rgb(0.3, 0.3, 0.35);
+const TIME_CRYSTAL_COLOR: Color = Color::rgb(0.6, 1., 0.95);
+const HAZARD_COLOR: Color = Color::rgba(1., 0.35, 0.05, 0.7);
+const PLATE_COLOR: Color = Color::rgba(0.9, 0.6, 0.1, 0.6);
+const GATE_COLOR: Color = Color::rgb(0.9, 0.6, 0.1);
+const SWITCH_COLOR: Color = Color::rgb(0.2, 0.6, 1.);
+const TELEPORTER_COLOR: Color = Color::rgba(0.2, 0.9, 0.9, 0.6);
+const PATROL_COLOR: Color = Color::rgba(0.55, 0.1, 0.15, 0.5);
+const CHASER_COLOR: Color = Color::rgb(0.85, 0.15, 0.1);
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(EditorLevel::default())
+            .insert_resource(EditorTool::Candy)
+            .add_systems(Update, open_editor.run_if(in_state(AppState::Playing)))
+            .add_systems(OnEnter(AppState::Editor), (despawn_after_game_over, reset_run, start_editing, spawn_editor_ui))
+            .add_systems(Update, (
+                select_tool,
+                apply_tool,
+                save_or_playtest,
+                leave_editor,
+                render_level.run_if(resource_changed::<EditorLevel>()),
+                update_editor_ui.run_if(resource_changed::<EditorTool>()),
+            ).chain().run_if(in_state(AppState::Editor)))
+            .add_systems(OnExit(AppState::Editor), despawn_editor);
+    }
+}
+
+/// The level being edited; a working copy that only reaches the game when saved and playtested.
+#[derive(Resource, Default)]
+struct EditorLevel(LevelSpec);
+
+#[derive(Resource, Debug, Clone, Copy, Eq, PartialEq)]
+enum EditorTool {
+    Candy,
+    Fuel,
+    NetherCandy,
+    NumberedCandy,
+    Wall,
+    Ice,
+    Key,
+    Door,
+    Start,
+    End,
+    Erase,
+}
+
+#[derive(Component)]
+struct EditorEntity;
+
+#[derive(Component)]
+struct EditorCell;
+
+#[derive(Component)]
+struct ToolDisplay;
+
+fn open_editor(keyboard_input: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<AppState>>) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        next_state.set(AppState::Editor);
+    }
+}
+
+fn start_editing(mut editor_level: ResMut<EditorLevel>, level_spec: Res<LevelSpec>) {
+    editor_level.0 = level_spec.clone();
+}
+
+fn despawn_editor(mut commands: Commands, query: Query<Entity, With<EditorEntity>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_editor_ui(mut commands: Commands) {
+    commands.spawn((
+        EditorEntity,
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn((
+            ToolDisplay,
+            TextBundle::from_section("", TextStyle {font_size: 40., ..default()}),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "1 candy  2 fuel  3 nether candy  4 numbered candy  5 wall  6 start  7 end  8 erase  9 ice  K key  D door  |  S save  P playtest  Esc menu",
+            TextStyle {font_size: 20., ..default()}));
+    });
+}
+
+fn update_editor_ui(tool: Res<EditorTool>, mut display: Query<&mut Text, With<ToolDisplay>>) {
+    for mut text in display.iter_mut() {
+        text.sections[0].value = format!("Level editor - {:?}", *tool);
+    }
+}
+
+fn select_tool(keyboard_input: Res<Input<KeyCode>>, mut tool: ResMut<EditorTool>) {
+    let tools = [
+        (KeyCode::Key1, EditorTool::Candy),
+        (KeyCode::Key2, EditorTool::Fuel),
+        (KeyCode::Key3, EditorTool::NetherCandy),
+        (KeyCode::Key4, EditorTool::NumberedCandy),
+        (KeyCode::Key5, EditorTool::Wall),
+        (KeyCode::Key6, EditorTool::Start),
+        (KeyCode::Key7, EditorTool::End),
+        (KeyCode::Key8, EditorTool::Erase),
+        (KeyCode::Key9, EditorTool::Ice),
+        (KeyCode::K, EditorTool::Key),
+        (KeyCode::D, EditorTool::Door),
+    ];
+    for (key, selected) in tools {
+        if keyboard_input.just_pressed(key) {
+            *tool = selected;
+        }
+    }
+}
+
+fn apply_tool(
+    mouse: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    tool: Res<EditorTool>,
+    mut editor_level: ResMut<EditorLevel>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let (camera, camera_transform) = cameras.single();
+    let Some(cell) = cursor_grid_location(windows.single(), camera, camera_transform) else {
+        return;
+    };
+    let cell = cell.0;
+    let level = &mut editor_level.0;
+
+    match *tool {
+        // Clicking a candy again cycles its color, then removes it.
+        EditorTool::Candy => match level.candies.iter().position(|(location, _)| *location == cell) {
+            Some(index) => match level.candies[index].1 {
+                CandyColor::Red => level.candies[index].1 = CandyColor::Green,
+                CandyColor::Green => level.candies[index].1 = CandyColor::Yellow,
+                CandyColor::Yellow => {
+                    level.candies.remove(index);
+                },
+            },
+            None => {
+                level.walls.retain(|location| *location != cell);
+                level.candies.push((cell, CandyColor::Red));
+            },
+        },
+        EditorTool::Fuel => toggle(&mut level.fuel, &mut level.walls, cell),
+        EditorTool::NetherCandy => toggle(&mut level.nether_candies, &mut level.walls, cell),
+        // New numbered candy goes to the end of the order; removing one renumbers those after it.
+        EditorTool::NumberedCandy => toggle(&mut level.numbered_candies, &mut level.walls, cell),
+        EditorTool::Wall => {
+            if level.walls.contains(&cell) {
+                level.walls.retain(|location| *location != cell);
+            } else if cell != level.start && cell != level.end {
+                clear_cell(level, cell);
+                level.walls.push(cell);
+            }
+        },
+        EditorTool::Ice => toggle(&mut level.ice, &mut level.walls, cell),
+        EditorTool::Key => toggle(&mut level.keys, &mut level.walls, cell),
+        EditorTool::Door => {
+            if level.doors.contains(&cell) {
+                level.doors.retain(|location| *location != cell);
+            } else if cell != level.start && cell != level.end {
+                clear_cell(level, cell);
+                level.doors.push(cell);
+            }
+        },
+        EditorTool::Start => {
+            level.walls.retain(|location| *location != cell);
+            level.start = cell;
+        },
+        EditorTool::End => {
+            level.walls.retain(|location| *location != cell);
+            level.end = cell;
+        },
+        EditorTool::Erase => clear_cell(level, cell),
+    }
+}
+
+fn toggle(cells: &mut Vec<IVec2>, walls: &mut Vec<IVec2>, cell: IVec2) {
+    if cells.contains(&cell) {
+        cells.retain(|location| *location != cell);
+    } else {
+        walls.retain(|location| *location != cell);
+        cells.push(cell);
+    }
+}
+
+fn clear_cell(level: &mut LevelSpec, cell: IVec2) {
+    level.candies.retain(|(location, _)| *location != cell);
+    level.timed_candies.retain(|(location, _, _)| *location != cell);
+    level.fuel.retain(|location| *location != cell);
+    level.canisters.retain(|(location, _)| *location != cell);
+    level.nether_candies.retain(|location| *location != cell);
+    level.numbered_candies.retain(|location| *location != cell);
+    level.walls.retain(|location| *location != cell);
+    level.keys.retain(|location| *location != cell);
+    level.bombs.retain(|location| *location != cell);
+    level.magnets.retain(|location| *location != cell);
+    level.time_crystals.retain(|location| *location != cell);
+    level.double_moves.retain(|location| *location != cell);
+    level.doors.retain(|location| *location != cell);
+    level.plates.retain(|(plate, gate)| *plate != cell && *gate != cell);
+    level.switches.retain(|(location, _)| *location != cell);
+    level.switch_gates.retain(|(location, _, _)| *location != cell);
+    level.teleporters.retain(|(a, b)| *a != cell && *b != cell);
+    level.ice.retain(|location| *location != cell);
+    level.conveyors.retain(|(location, _)| *location != cell);
+    level.hazards.retain(|(location, _)| *location != cell);
+    level.patrols.retain(|(route, _)| !route.contains(&cell));
+    level.chasers.retain(|(location, _)| *location != cell);
+}
+
+fn leave_editor(keyboard_input: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<AppState>>) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::MainMenu);
+    }
+}
+
+fn save_or_playtest(
+    keyboard_input: Res<Input<KeyCode>>,
+    editor_level: Res<EditorLevel>,
+    mut level_source: ResMut<LevelSource>,
+    mut current_level: ResMut<CurrentCampaignLevel>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut toasts: EventWriter<Toast>,
+    rules: Res<GameRules>,
+    upgrades: Res<Upgrades>,
+) {
+    let playtest = keyboard_input.just_pressed(KeyCode::P);
+    if !playtest && !keyboard_input.just_pressed(KeyCode::S) {
+        return;
+    }
+
+    let path = PathBuf::from(EDITOR_LEVEL_PATH);
+    if let Err(err) = editor_level.0.save(&path) {
+        warn!("Couldn't save level: {}", err);
+        toasts.send(Toast::warning(format!("Couldn't save level: {}", err)));
+        return;
+    }
+    info!("Saved level to {}", path.display());
+    toasts.send(Toast::info(format!("Saved level to {}", path.display())));
+    // However many loops the level gets once it's played, with its own overrides on top.
+    let mut level_rules = rules.clone();
+    level_rules.apply_overrides(&editor_level.0.rules);
+    let unreachable = unreachable_candies(&editor_level.0, level_rules.num_loops(&upgrades));
+    for candy in unreachable.iter() {
+        warn!("Candy at {} can't be collected", candy);
+    }
+    if !unreachable.is_empty() {
+        toasts.send(Toast::warning(format!("{} candy can't be collected", unreachable.len())));
+    }
+
+    if playtest {
+        *level_source = LevelSource::File(path);
+        current_level.0 = None;
+        next_state.set(AppState::Playing);
+    }
+}
+
+fn render_level(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+    editor_level: Res<EditorLevel>,
+    cells: Query<Entity, With<EditorCell>>,
+) {
+    for entity in cells.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let level = &editor_level.0;
+    let center = |cell: IVec2, z: f32| Transform::from_translation((cell * GRID_SPACING).as_vec2().extend(z));
+    let mut spawn_square = |cell: IVec2, color: Color, size: f32, z: f32| {
+        commands.spawn((EditorEntity, EditorCell, SpriteBundle {
+            sprite: Sprite {color, custom_size: Some(Vec2::splat(size)), ..default()},
+            transform: center(cell, z),
+            ..default()
+        }));
+    };
+
+    for x in 0..MAX_X {
+        for y in 0..MAX_Y {
+            spawn_square(IVec2::new(x, y), TILE_COLOR, 128., 0.);
+        }
+    }
+    for &wall in level.walls.iter() {
+        spawn_square(wall, WALL_COLOR, 128., 0.1);
+    }
+    for &door in level.doors.iter() {
+        spawn_square(door, palette.door, 128., 0.1);
+    }
+    for &ice in level.ice.iter() {
+        spawn_square(ice, ICE_COLOR, 128., 0.05);
+    }
+    for &(hazard, _) in level.hazards.iter() {
+        spawn_square(hazard, HAZARD_COLOR, 110., 0.05);
+    }
+    for &route_cell in level.patrols.iter().flat_map(|(route, _)| route.iter()) {
+        spawn_square(route_cell, PATROL_COLOR, 60., 0.06);
+    }
+    for &(chaser, _) in level.chasers.iter() {
+        spawn_square(chaser, CHASER_COLOR, 80., 0.06);
+    }
+    for &(conveyor, _) in level.conveyors.iter() {
+        spawn_square(conveyor, CONVEYOR_COLOR, 128., 0.05);
+    }
+    for &(plate, gate) in level.plates.iter() {
+        spawn_square(plate, PLATE_COLOR, 80., 0.05);
+        spawn_square(gate, GATE_COLOR, 128., 0.1);
+    }
+    for &(switch, _) in level.switches.iter() {
+        spawn_square(switch, SWITCH_COLOR, 50., 0.05);
+    }
+    for &(gate, _, _) in level.switch_gates.iter() {
+        spawn_square(gate, SWITCH_COLOR, 128., 0.1);
+    }
+    for &(a, b) in level.teleporters.iter() {
+        spawn_square(a, TELEPORTER_COLOR, 100., 0.05);
+        spawn_square(b, TELEPORTER_COLOR, 100., 0.05);
+    }
+    spawn_square(level.start, Color::rgba(0.3, 1., 0.3, 0.4), 128., 0.1);
+    spawn_square(level.end, Color::rgba(1., 0.3, 0.2, 0.4), 128., 0.1);
+
+    let mut spawn_item = |cell: IVec2, texture: &'static str, color: Color, offset: Vec2| {
+        let mut transform = center(cell, 0.2);
+        transform.translation += offset.extend(0.);
+        commands.spawn((EditorEntity, EditorCell, SpriteBundle {
+            texture: asset_server.load(texture),
+            sprite: Sprite {color, custom_size: Some(Vec2::splat(48.)), ..default()},
+            transform,
+            ..default()
+        })).id()
+    };
+    // Offset each kind within the cell so stacked items stay readable.
+    let timed_candies = level.timed_candies.iter().map(|&(cell, color, _)| (cell, color));
+    for (cell, color) in level.candies.iter().copied().chain(timed_candies) {
+        spawn_item(cell, color.texture(), Color::WHITE, Vec2::new(-30., 30.));
+    }
+    for &cell in level.fuel.iter().chain(level.canisters.iter().map(|(cell, _)| cell)) {
+        spawn_item(cell, "fuel.png", Color::WHITE, Vec2::new(30., 30.));
+    }
+    for &cell in level.keys.iter() {
+        spawn_item(cell, "key.png", palette.key, Vec2::ZERO);
+    }
+    for &cell in level.bombs.iter() {
+        spawn_item(cell, "bomb.png", Color::WHITE, Vec2::ZERO);
+    }
+    for &cell in level.magnets.iter() {
+        spawn_item(cell, "magnet.png", Color::WHITE, Vec2::ZERO);
+    }
+    for &cell in level.time_crystals.iter() {
+        spawn_item(cell, "blue-candy.png", TIME_CRYSTAL_COLOR, Vec2::ZERO);
+    }
+    for &cell in level.double_moves.iter() {
+        spawn_item(cell, "double-move.png", Color::WHITE, Vec2::ZERO);
+    }
+    for &cell in level.nether_candies.iter() {
+        spawn_item(cell, "blue-candy.png", NETHER_CANDY_COLOR, Vec2::new(-30., -30.));
+    }
+    let numbered: Vec<Entity> = level.numbered_candies.iter()
+        .map(|&cell| spawn_item(cell, "blue-candy.png", Color::WHITE, Vec2::new(30., -30.)))
+        .collect();
+    for (index, entity) in numbered.into_iter().enumerate() {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn(Text2dBundle {
+                text: Text::from_section((index + 1).to_string(), TextStyle {font_size: 30., color: Color::BLACK, ..default()}),
+                transform: Transform::from_xyz(0., 0., 0.01),
+                ..default()
+            });
+        });
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::{detect_game_over, next_turn, validate_move, AppState, CurrentSoot, DespawnOnExitGameOver, LoopCounter, Move, MoveAttempt, RoundCounter, SootId, SootSprite, StartLoop, GRID_SPACING, MAX_X, MAX_Y, SOOT_Z, WAIT};
+use crate::grid::{AnimateTranslation, GridCells, GridLocation, MovementComplete, SnapToGrid};
+use crate::hazard::{strike, HazardHit};
+use crate::inventory::{Inventory, PickUpItems};
+use crate::level_spec::{Hazard, LevelSpec};
+use crate::settings::Settings;
+use crate::spawn_level::{SpawnLevel, Wall};
+
+const PATROL_COLOR: Color = Color::rgb(0.55, 0.1, 0.15);
+const CHASER_COLOR: Color = Color::rgb(0.85, 0.15, 0.1);
+/// Just under the soots, so one that's been caught is still seen.
+const ENEMY_Z: f32 = SOOT_Z - 0.05;
+
+pub struct EnemiesPlugin;
+
+impl Plugin for EnemiesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Approaching::default())
+            .insert_resource(EnemyTurns::default())
+            .add_systems(OnEnter(AppState::Playing), (
+                reset_enemy_turns,
+                (spawn_patrols, spawn_chasers).after(SpawnLevel),
+            ).in_set(StartLoop))
+            .add_systems(Update, (
+                note_approaches,
+                (walk_into_enemies, finish_chase).after(PickUpItems).before(next_turn),
+                (advance_patrols, queue_chasers).after(next_turn).before(detect_game_over),
+                animate_patrols,
+            ).chain().run_if(in_state(AppState::Playing)))
+            .add_systems(Update, chase.before(validate_move).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Does what its hazard would to any soot it runs into or that runs into it.
+#[derive(Component)]
+pub struct Enemy(Hazard);
+
+/// Walks its route one cell per round, out to the end and back again.
+#[derive(Component)]
+struct Patrol {
+    route: Vec<IVec2>,
+    /// The round it's standing where it is for.
+    round: i32,
+}
+
+impl Patrol {
+    /// Where it is during `round`. Only the round matters, so a past self meets it in the same place it did when it
+    /// was recorded, and undo puts it back by putting the round back.
+    fn cell_for_round(&self, round: i32) -> IVec2 {
+        let last = self.route.len() as i32 - 1;
+        if last == 0 {
+            return self.route[0];
+        }
+        let step = round.rem_euclid(2 * last);
+        self.route[(if step > last { 2 * last - step } else { step }) as usize]
+    }
+}
+
+/// Steps toward whichever soot's turn is next. Unlike a patrol it moves like a soot does, through `MoveAttempt`, so
+/// walls, ice and the rest treat it the same.
+#[derive(Component)]
+pub struct Chaser;
+
+/// Where a patrol is being drawn between. Patrols don't use `AnimateTranslation`, since they all step at once and
+/// only one mover at a time gets to say a move is over.
+#[derive(Component)]
+struct PatrolStep {
+    from: Vec2,
+    to: Vec2,
+    timer: Timer,
+}
+
+/// The enemies' turn, in between rounds. Chasers go one at a time, and soots wait for the last of them.
+#[derive(Resource, Default)]
+pub struct EnemyTurns {
+    round: i32,
+    waiting: VecDeque<Entity>,
+    moving: Option<Entity>,
+}
+
+/// Whether soots can take their turns, with every chaser done moving.
+pub fn enemies_done(turns: Res<EnemyTurns>) -> bool {
+    turns.waiting.is_empty() && turns.moving.is_none()
+}
+
+/// Soots partway through a move. Only walking into an enemy sets it off; it already had its go at anyone it walked
+/// into.
+#[derive(Resource, Default)]
+struct Approaching(HashSet<Entity>);
+
+fn reset_enemy_turns(mut turns: ResMut<EnemyTurns>, mut approaching: ResMut<Approaching>) {
+    *turns = default();
+    approaching.0.clear();
+}
+
+fn spawn_patrols(mut commands: Commands, asset_server: Res<AssetServer>, level_spec: Res<LevelSpec>, settings: Res<Settings>) {
+    for (route, hazard) in level_spec.patrols.iter() {
+        if route.is_empty() {
+            continue;
+        }
+        let patrol = Patrol {route: route.clone(), round: 0};
+        let location = patrol.cell_for_round(0);
+        let position = (location * GRID_SPACING).as_vec2();
+        let mut timer = Timer::new(settings.animation_speed.duration(), TimerMode::Once);
+        timer.tick(timer.duration());
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load("soot-sprite.png"),
+                sprite: Sprite {
+                    color: PATROL_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(ENEMY_Z)),
+                ..default()
+            },
+            Enemy(*hazard),
+            patrol,
+            GridLocation(location),
+            PatrolStep {from: position, to: position, timer},
+            DespawnOnExitGameOver,
+        ));
+    }
+}
+
+fn spawn_chasers(mut commands: Commands, asset_server: Res<AssetServer>, level_spec: Res<LevelSpec>, settings: Res<Settings>) {
+    for &(location, hazard) in level_spec.chasers.iter() {
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load("soot-sprite.png"),
+                sprite: Sprite {
+                    color: CHASER_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., 0., ENEMY_Z),
+                ..default()
+            },
+            Enemy(hazard),
+            Chaser,
+            GridLocation(location),
+            SnapToGrid,
+            settings.animation_speed.animate_translation(),
+            DespawnOnExitGameOver,
+        ));
+    }
+}
+
+fn note_approaches(mut moves: EventReader<Move>, mut approaching: ResMut<Approaching>) {
+    for event in moves.iter().filter(|event| event.offset != WAIT) {
+        approaching.0.insert(event.mover);
+    }
+}
+
+type SootParts<'a> = (
+    &'a SootSprite,
+    &'a mut GridLocation,
+    &'a mut Inventory,
+    &'a mut Transform,
+    &'a mut AnimateTranslation,
+);
+
+/// An enemy has just arrived on `cell`: anyone there, short of their exit, gets caught.
+fn catch_soots_at(
+    cell: IVec2,
+    contact: Hazard,
+    current_soot: SootId,
+    exit_for: impl Fn(SootId) -> IVec2,
+    soots: &mut Query<SootParts, Without<Enemy>>,
+    hits: &mut EventWriter<HazardHit>,
+) {
+    for (soot, mut location, mut inventory, mut transform, mut animation) in soots.iter_mut() {
+        let exit = exit_for(soot.id);
+        if location.0 != cell || location.0 == exit {
+            continue;
+        }
+        hits.send(HazardHit(contact));
+        if contact == Hazard::Knockout && soot.id == current_soot {
+            // The turn's already been handed to this soot, so being carried off to the exit has to be its move: the
+            // soot finishing getting there is what passes the turn on.
+            location.bypass_change_detection().0 = exit;
+            animation.start = transform.translation.truncate();
+            animation.end = (exit * GRID_SPACING).as_vec2();
+            animation.timer.reset();
+            continue;
+        }
+        strike(contact, exit, location, &mut inventory, &mut transform, &mut animation);
+    }
+}
+
+// Like a hazard: once the whole move is done, and before next_turn, so a soot sent to the exit is already done when
+// turns are handed out.
+fn walk_into_enemies(
+    mut completions: EventReader<MovementComplete>,
+    mut approaching: ResMut<Approaching>,
+    mut soots: Query<SootParts, Without<Enemy>>,
+    enemies: Query<(&Enemy, &GridLocation)>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    mut hits: EventWriter<HazardHit>,
+) {
+    for &MovementComplete{entity} in completions.iter() {
+        if !approaching.0.remove(&entity) {
+            continue;
+        }
+        let Ok((soot, location, mut inventory, mut transform, mut animation)) = soots.get_mut(entity) else {
+            continue;
+        };
+        let exit = level_spec.end_for_loop(loop_counter.0 - soot.id.loop_number());
+        if location.0 == exit {
+            continue;
+        }
+        let Some((&Enemy(contact), _)) = enemies.iter().find(|(_, enemy_location)| enemy_location.0 == location.0) else {
+            continue;
+        };
+        strike(contact, exit, location, &mut inventory, &mut transform, &mut animation);
+        hits.send(HazardHit(contact));
+    }
+}
+
+// Enemies take their turn between rounds, once every soot still going has had one.
+fn advance_patrols(
+    round_counter: Res<RoundCounter>,
+    current_soot: Res<CurrentSoot>,
+    mut patrols: Query<(&Enemy, &mut Patrol, &mut GridLocation, &Transform, &mut PatrolStep), Without<SootSprite>>,
+    mut soots: Query<SootParts, Without<Enemy>>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    mut hits: EventWriter<HazardHit>,
+) {
+    for (&Enemy(contact), mut patrol, mut location, transform, mut step) in patrols.iter_mut() {
+        if patrol.round == round_counter.0 {
+            continue;
+        }
+        // Only undo turns the round back, and it puts back whatever the patrol did along with it.
+        let advanced = round_counter.0 > patrol.round;
+        patrol.round = round_counter.0;
+        location.0 = patrol.cell_for_round(patrol.round);
+        step.from = transform.translation.truncate();
+        step.to = (location.0 * GRID_SPACING).as_vec2();
+        step.timer.reset();
+        if advanced {
+            let exit_for = |soot: SootId| level_spec.end_for_loop(loop_counter.0 - soot.loop_number());
+            catch_soots_at(location.0, contact, current_soot.0, exit_for, &mut soots, &mut hits);
+        }
+    }
+}
+
+fn animate_patrols(time: Res<Time>, mut patrols: Query<(&mut PatrolStep, &mut Transform)>) {
+    for (mut step, mut transform) in patrols.iter_mut() {
+        if step.timer.finished() {
+            continue;
+        }
+        step.timer.tick(time.delta());
+        let z = transform.translation.z;
+        transform.translation = step.from.lerp(step.to, step.timer.percent()).extend(z);
+    }
+}
+
+fn queue_chasers(
+    round_counter: Res<RoundCounter>,
+    mut turns: ResMut<EnemyTurns>,
+    chasers: Query<Entity, With<Chaser>>,
+) {
+    if round_counter.0 > turns.round {
+        turns.waiting.extend(chasers.iter());
+    }
+    turns.round = round_counter.0;
+}
+
+fn chase(
+    mut turns: ResMut<EnemyTurns>,
+    current_soot: Res<CurrentSoot>,
+    chasers: Query<&GridLocation, With<Chaser>>,
+    soots: Query<(&SootSprite, &GridLocation)>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    walls: Query<(), With<Wall>>,
+    mut attempts: EventWriter<MoveAttempt>,
+) {
+    if turns.moving.is_some() {
+        return;
+    }
+    let Some(chaser) = turns.waiting.pop_front() else {
+        return;
+    };
+    let Ok(from) = chasers.get(chaser) else {
+        return;
+    };
+    let Some((_, target)) = soots.iter().find(|(soot, _)| soot.id == current_soot.0) else {
+        return;
+    };
+
+    let is_open = |cell: IVec2| (0..MAX_X).contains(&cell.x) && (0..MAX_Y).contains(&cell.y)
+        && !cells.contents(cell, &children).iter().any(|&entity| walls.contains(entity));
+    turns.moving = Some(chaser);
+    attempts.send(MoveAttempt {mover: chaser, offset: step_towards(from.0, target.0, is_open)});
+}
+
+/// The first step of a shortest path from `from` to `to` through open cells, or a wait if there's no way there.
+fn step_towards(from: IVec2, to: IVec2, is_open: impl Fn(IVec2) -> bool) -> IVec2 {
+    const OFFSETS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+    // Walking distances back from the target, so the way to go is just whichever neighbour is nearest.
+    let mut distances = HashMap::from([(to, 0)]);
+    let mut frontier = VecDeque::from([to]);
+    while let Some(cell) = frontier.pop_front() {
+        if cell == from {
+            break;
+        }
+        let distance = distances[&cell];
+        for offset in OFFSETS {
+            let next = cell + offset;
+            if is_open(next) && !distances.contains_key(&next) {
+                distances.insert(next, distance + 1);
+                frontier.push_back(next);
+            }
+        }
+    }
+
+    let Some(&distance) = distances.get(&from) else {
+        return WAIT;
+    };
+    OFFSETS.into_iter()
+        .find(|&offset| distances.get(&(from + offset)).is_some_and(|&next| next < distance))
+        .unwrap_or(WAIT)
+}
+
+// A chaser's move is over once it lands, or straight away if it was blocked.
+fn finish_chase(
+    mut completions: EventReader<MovementComplete>,
+    mut turns: ResMut<EnemyTurns>,
+    current_soot: Res<CurrentSoot>,
+    chasers: Query<(&Enemy, &GridLocation), With<Chaser>>,
+    mut soots: Query<SootParts, Without<Enemy>>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    mut hits: EventWriter<HazardHit>,
+) {
+    for &MovementComplete{entity} in completions.iter() {
+        if turns.moving != Some(entity) {
+            continue;
+        }
+        turns.moving = None;
+        let Ok((&Enemy(contact), location)) = chasers.get(entity) else {
+            continue;
+        };
+        let exit_for = |soot: SootId| level_spec.end_for_loop(loop_counter.0 - soot.loop_number());
+        catch_soots_at(location.0, contact, current_soot.0, exit_for, &mut soots, &mut hits);
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+
+use crate::{board_center, AppState, CurrentSoot, SootSprite};
+use crate::camera_controls::CameraView;
+use crate::settings::Settings;
+
+/// How far the camera leans from the board center toward the soot whose turn it is.
+const FOCUS_LEAN: f32 = 0.3;
+/// Projection scale while focused; below 1 is zoomed in.
+const FOCUS_SCALE: f32 = 0.85;
+/// How long the camera backs off to the whole board when the turn passes, in seconds.
+const PULL_BACK_SECS: f32 = 0.25;
+/// Rate the camera closes the distance to where it's headed; higher is snappier.
+const FOLLOW_RATE: f32 = 6.;
+
+/// Where the camera is steered each frame.
+#[derive(SystemSet, Hash, Debug, Clone, Eq, PartialEq)]
+pub struct MoveCamera;
+
+pub struct FocusPlugin;
+
+impl Plugin for FocusPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(PullBack(Timer::from_seconds(PULL_BACK_SECS, TimerMode::Once)))
+            .add_systems(Update, (
+                toggle_focus_mode,
+                pull_back_on_turn_change.run_if(resource_changed::<CurrentSoot>()),
+                follow_current_soot.in_set(MoveCamera),
+            ).chain());
+    }
+}
+
+/// Runs between turns; the camera shows the whole board until it finishes.
+#[derive(Resource)]
+struct PullBack(Timer);
+
+fn toggle_focus_mode(keyboard_input: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keyboard_input.just_pressed(KeyCode::F6) {
+        settings.focus_mode = !settings.focus_mode;
+        info!("Focus mode: {}", settings.focus_mode);
+    }
+}
+
+fn pull_back_on_turn_change(mut pull_back: ResMut<PullBack>) {
+    pull_back.0.reset();
+}
+
+fn follow_current_soot(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    state: Res<State<AppState>>,
+    current_soot: Res<CurrentSoot>,
+    view: Res<CameraView>,
+    mut pull_back: ResMut<PullBack>,
+    soots: Query<(&SootSprite, &Transform), Without<Camera2d>>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+) {
+    pull_back.0.tick(time.delta());
+    let home = board_center();
+    let focus = soots.iter()
+        .find(|(soot, _)| soot.id == current_soot.0)
+        .map(|(_, transform)| transform.translation.truncate());
+
+    let (target, scale) = match focus {
+        Some(focus) if settings.focus_mode && *state.get() == AppState::Playing && pull_back.0.finished() =>
+            (home.lerp(focus, FOCUS_LEAN), FOCUS_SCALE),
+        _ => (home, 1.),
+    };
+    let (target, scale) = (view.frame(target), scale * view.zoom);
+
+    for (mut transform, mut projection) in cameras.iter_mut() {
+        // No easing with reduce motion; the camera jumps straight to the full board view, as the player has it.
+        if settings.reduce_motion {
+            transform.translation = view.frame(home).extend(transform.translation.z);
+            projection.scale = view.zoom;
+            continue;
+        }
+        let step = 1. - (-FOLLOW_RATE * time.delta_seconds()).exp();
+        let position = transform.translation.truncate().lerp(target, step);
+        transform.translation = position.extend(transform.translation.z);
+        projection.scale += (scale - projection.scale) * step;
+    }
+}
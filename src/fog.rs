@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use crate::{AppState, SootSprite};
+use crate::grid::{GridCell, GridLocation};
+use crate::rules::GameRules;
+
+/// Over everything drawn on a cell, markers, previews and hints included, but under the soots.
+const FOG_Z: f32 = 0.9;
+pub const FOG_COLOR: Color = Color::rgba(0.05, 0.05, 0.08, 0.92);
+
+pub struct FogPlugin;
+
+impl Plugin for FogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (cover_new_cells, apply_deferred, update_fog)
+            .chain().run_if(fog_enabled).run_if(in_state(AppState::Playing)));
+    }
+}
+
+fn fog_enabled(rules: Res<GameRules>) -> bool {
+    rules.fog_radius.is_some()
+}
+
+/// Whether `cell` is out of sight of every soot, standing at `soots`. Never, unless the fog rule is on.
+pub fn fogged(rules: &GameRules, cell: IVec2, mut soots: impl Iterator<Item = IVec2>) -> bool {
+    rules.fog_radius.is_some_and(|radius| !soots.any(|soot| {
+        let steps = (soot - cell).abs();
+        steps.x + steps.y <= radius
+    }))
+}
+
+/// Hides everything on its cell while no soot is close enough to see it.
+#[derive(Component)]
+struct FogCover;
+
+// Cells are spawned fresh every loop, so each new one gets its own cover.
+fn cover_new_cells(mut commands: Commands, cells: Query<Entity, Added<GridCell>>) {
+    for cell in cells.iter() {
+        commands.entity(cell).with_children(|parent| {
+            parent.spawn((
+                FogCover,
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: FOG_COLOR,
+                        custom_size: Some(Vec2::splat(128.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., 0., FOG_Z),
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+// Checked every frame rather than on Changed<GridLocation>, since knockouts move soots without tripping change
+// detection. Soots' locations change as their moves start, so the fog lifts ahead of them rather than once they land.
+fn update_fog(
+    rules: Res<GameRules>,
+    soots: Query<&GridLocation, With<SootSprite>>,
+    cells: Query<&GridLocation, With<GridCell>>,
+    mut covers: Query<(&Parent, &mut Visibility), With<FogCover>>,
+) {
+    for (parent, mut visibility) in covers.iter_mut() {
+        let Ok(cell) = cells.get(parent.get()) else {
+            continue;
+        };
+        let fogged = fogged(&rules, cell.0, soots.iter().map(|soot| soot.0));
+        let wanted = if fogged { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
@@ -1,24 +1,46 @@
 use bevy::prelude::*;
 
-use crate::{AppState, DespawnOnExitGameOver, Player};
+use crate::{AppState, DespawnOnExitGameOver, LoopCounter, Player};
 
-use crate::inventory::Inventory;
+use crate::comparison::ToggleComparison;
+use crate::high_scores::{record_high_score, FinishedRun};
+use crate::inventory::{CandyByLoop, Inventory};
+use crate::painting::PaintedCells;
+use crate::replay::ExportReplay;
+use crate::rules::GameRules;
+use crate::scoring::Score;
+use crate::shop::Upgrades;
+use crate::ui::spawn_button;
 
 pub struct GameOverScreenPlugin;
 
 impl Plugin for GameOverScreenPlugin {
     fn build (&self, app: &mut App) {
-        app.add_systems(OnEnter(AppState::GameOver), spawn_game_over_screen)
+        app.add_systems(OnEnter(AppState::GameOver), spawn_game_over_screen.after(record_high_score))
            .add_systems(Update, update_game_over_screen.run_if(in_state(AppState::GameOver)));
     }
 }
 
-const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
-const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
-const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
+#[derive(Component)]
+enum GameOverButton {
+    Restart,
+    Shop,
+    SaveReplay,
+    Compare,
+    MainMenu,
+}
 
-fn spawn_game_over_screen(mut commands: Commands, inventory: Query<&Inventory, With<Player>>) {
-    let inventory = inventory.single();
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    player: Query<(&Inventory, &Score), With<Player>>,
+    rules: Res<GameRules>,
+    painted: Res<PaintedCells>,
+    loop_counter: Res<LoopCounter>,
+    upgrades: Res<Upgrades>,
+    finished_run: Res<FinishedRun>,
+    candy_by_loop: Res<CandyByLoop>,
+) {
+    let (inventory, score) = player.single();
     commands.spawn((
         NodeBundle {
             style: Style {
@@ -27,6 +49,7 @@ fn spawn_game_over_screen(mut commands: Commands, inventory: Query<&Inventory, W
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
                 flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
                 ..default()
             },
             background_color: Color::rgba(0., 0., 0., 0.7).into(),
@@ -35,41 +58,53 @@ fn spawn_game_over_screen(mut commands: Commands, inventory: Query<&Inventory, W
         DespawnOnExitGameOver,
     )).with_children(|parent| {
         parent.spawn(TextBundle::from_section(
-            format!("Game over! Score: {}", inventory.candies),
+            format!("Game over! Score: {}", score.total(inventory)),
             TextStyle {font_size: 50., ..default()}));
-        parent.spawn(ButtonBundle{
-            style: Style {
-                width: Val::Px(150.),
-                height: Val::Px(65.),
-                // horizontally center child text
-                justify_content: JustifyContent::Center,
-                // vertically center child text
-                align_items: AlignItems::Center,
-                ..default()
-            },
-            background_color: NORMAL_BUTTON.into(),
-            ..default()}).with_children(|parent| {
-                parent.spawn(TextBundle::from_section("Restart", TextStyle::default()));
-        });
+        // Only worth breaking down once there are past selves to share the credit with.
+        if loop_counter.0 > 0 && !candy_by_loop.0.is_empty() {
+            parent.spawn(TextBundle::from_section(candy_by_loop.describe(), TextStyle {font_size: 30., ..default()}));
+        }
+        if let Some(run) = &finished_run.0 {
+            parent.spawn(TextBundle::from_section(
+                format!("Run total: {}  Best: {}", run.total, run.best),
+                TextStyle {font_size: 40., ..default()}));
+            if run.new_record {
+                parent.spawn(TextBundle::from_section(
+                    "New record!",
+                    TextStyle {font_size: 50., color: Color::GOLD, ..default()}));
+            }
+        }
+        if rules.painting && painted.is_board_painted() {
+            parent.spawn(TextBundle::from_section("The whole board is painted!", TextStyle {font_size: 40., ..default()}));
+        }
+        // The shop sits between runs, so only the last loop leads there.
+        if loop_counter.is_final_loop(&rules, &upgrades) {
+            spawn_button(parent, "Shop", GameOverButton::Shop);
+            spawn_button(parent, "Save replay", GameOverButton::SaveReplay);
+        } else {
+            spawn_button(parent, "Restart", GameOverButton::Restart);
+        }
+        spawn_button(parent, "Compare with best route", GameOverButton::Compare);
+        spawn_button(parent, "Main menu", GameOverButton::MainMenu);
     });
 }
 
 fn update_game_over_screen(
     mut next_state: ResMut<NextState<AppState>>,
-    mut interaction_query: Query<(&Interaction, &mut BackgroundColor), With<Button>>
+    interaction_query: Query<(&Interaction, &GameOverButton), Changed<Interaction>>,
+    mut export_replay: EventWriter<ExportReplay>,
+    mut toggle_comparison: EventWriter<ToggleComparison>,
 ) {
-    for (interaction, mut color) in interaction_query.iter_mut() {
-        match *interaction {
-            Interaction::Pressed => {
-                next_state.set(AppState::Playing);
-                *color = PRESSED_BUTTON.into();
-            }
-            Interaction::Hovered => {
-                *color = HOVERED_BUTTON.into();
-            },
-            Interaction::None => {
-                *color = NORMAL_BUTTON.into();
-            },
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            GameOverButton::Restart => next_state.set(AppState::Playing),
+            GameOverButton::Shop => next_state.set(AppState::Shop),
+            GameOverButton::SaveReplay => export_replay.send(ExportReplay),
+            GameOverButton::Compare => toggle_comparison.send(ToggleComparison),
+            GameOverButton::MainMenu => next_state.set(AppState::MainMenu),
         }
     }
 }
@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::{debuffer_move_inputs, next_turn, replay_move_attempts, validate_move, AppState, Move, MoveAttempt, SootSprite, StartLoop, WAIT};
+use crate::grid::{GridCells, GridLocation, MovementComplete};
+use crate::inventory::PickUpItems;
+use crate::spawn_level::Wall;
+
+/// How see-through a gate is while it's held open, so it's still clear there's a gate there.
+const OPEN_GATE_ALPHA: f32 = 0.2;
+const SWITCH_OFF_ALPHA: f32 = 0.4;
+
+pub struct GatesPlugin;
+
+impl Plugin for GatesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(SwitchStates::default())
+            .insert_resource(Switching::default())
+            .add_systems(OnEnter(AppState::Playing), reset_switches.in_set(StartLoop))
+            .add_systems(Update, (
+                note_switchers,
+                flip_switches.after(PickUpItems).before(next_turn),
+            ).chain().run_if(in_state(AppState::Playing)))
+            // After this frame's move attempts are made, so a soot setting off from a plate is already off it, and with
+            // the walls in place before the move is checked.
+            .add_systems(Update, (update_gates, update_switches, apply_deferred).chain()
+                .after(debuffer_move_inputs).after(replay_move_attempts).before(validate_move)
+                .run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Stand on it to hold its gates open.
+#[derive(Component, Clone, Copy)]
+pub struct PressurePlate;
+
+/// Step onto it to flip every gate with the same id.
+#[derive(Component, Clone, Copy)]
+pub struct Switch(pub u8);
+
+/// Shut like a wall unless whatever controls it has it open.
+#[derive(Component, Clone)]
+pub struct Gate {
+    pub control: GateControl,
+    pub open: bool,
+}
+
+#[derive(Clone)]
+pub enum GateControl {
+    /// Open while some soot is standing on one of these cells. Nobody can hold a plate down and walk through its
+    /// gate at once, so getting through takes a second soot: usually a past self that was routed onto the plate.
+    Plates(Vec<IVec2>),
+    /// Open or shut at the start of the loop, and the other way every time its switch is flipped.
+    Switch {id: u8, open_at_start: bool},
+}
+
+/// Switches flipped an odd number of times this loop. Undo puts this back along with the rest of the board.
+#[derive(Resource, Default, Clone)]
+pub struct SwitchStates(HashMap<u8, bool>);
+
+impl SwitchStates {
+    fn is_flipped(&self, id: u8) -> bool {
+        self.0.get(&id).copied().unwrap_or(false)
+    }
+}
+
+/// Soots partway through a move. Only moving onto a switch flips it; waiting on one doesn't, and neither do enemies.
+#[derive(Resource, Default)]
+struct Switching(HashSet<Entity>);
+
+fn reset_switches(mut states: ResMut<SwitchStates>, mut switching: ResMut<Switching>) {
+    states.0.clear();
+    switching.0.clear();
+}
+
+fn note_switchers(mut moves: EventReader<Move>, mut switching: ResMut<Switching>) {
+    for event in moves.iter().filter(|event| event.offset != WAIT) {
+        switching.0.insert(event.mover);
+    }
+}
+
+// Once the whole move is done, so a soot that slides or is carried across a switch only flips the one it stops on.
+fn flip_switches(
+    mut completions: EventReader<MovementComplete>,
+    mut switching: ResMut<Switching>,
+    soots: Query<&GridLocation, With<SootSprite>>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    switches: Query<&Switch>,
+    mut states: ResMut<SwitchStates>,
+) {
+    for &MovementComplete{entity} in completions.iter() {
+        if !switching.0.remove(&entity) {
+            continue;
+        }
+        let Ok(location) = soots.get(entity) else {
+            continue;
+        };
+        for &cell_entity in cells.contents(location.0, &children) {
+            if let Ok(&Switch(id)) = switches.get(cell_entity) {
+                let flipped = states.is_flipped(id);
+                states.0.insert(id, !flipped);
+            }
+        }
+    }
+}
+
+// Plates go by where soots are on the grid rather than where they're drawn, so a gate opens as soon as a soot sets
+// off for its plate and shuts as soon as one sets off from it: a soot about to move off a plate no longer holds it,
+// or it could step from the plate straight through a gate next to it. A gate never shuts on a soot standing in it.
+fn update_gates(
+    mut commands: Commands,
+    mut attempts: EventReader<MoveAttempt>,
+    soots: Query<(Entity, &GridLocation), With<SootSprite>>,
+    cells: Res<GridCells>,
+    states: Res<SwitchStates>,
+    mut gates: Query<(Entity, &mut Gate, &mut Sprite, &Parent)>,
+) {
+    let leaving: HashSet<Entity> = attempts.iter()
+        .filter(|attempt| attempt.offset != WAIT)
+        .map(|attempt| attempt.mover)
+        .collect();
+    let occupied: HashSet<Entity> = soots.iter().filter_map(|(_, location)| cells.get(location.0)).collect();
+    for (entity, mut gate, mut sprite, cell) in gates.iter_mut() {
+        let open = match &gate.control {
+            GateControl::Plates(plates) => plates.iter().any(|&plate| soots.iter()
+                .any(|(soot, location)| location.0 == plate && !leaving.contains(&soot))),
+            &GateControl::Switch {id, open_at_start} => open_at_start != states.is_flipped(id),
+        } || gate.open && occupied.contains(&cell.get());
+        if open == gate.open {
+            continue;
+        }
+        gate.open = open;
+        if open {
+            commands.entity(entity).remove::<Wall>();
+        } else {
+            commands.entity(entity).insert(Wall);
+        }
+        sprite.color.set_a(if open { OPEN_GATE_ALPHA } else { 1. });
+    }
+}
+
+fn update_switches(states: Res<SwitchStates>, mut switches: Query<(&Switch, &mut Sprite)>) {
+    if !states.is_changed() {
+        return;
+    }
+    for (&Switch(id), mut sprite) in switches.iter_mut() {
+        sprite.color.set_a(if states.is_flipped(id) { 1. } else { SWITCH_OFF_ALPHA });
+    }
+}
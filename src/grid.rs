@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use bevy::prelude::*;
 
-use crate::{AppState, GRID_SPACING};
+use crate::{AppState, GRID_SPACING, MAX_X, MAX_Y};
 
 #[derive(SystemSet, Hash, Debug, Clone, Eq, PartialEq)]
 pub struct ApplyGridMovement;
@@ -11,19 +13,42 @@ impl Plugin for GridPlugin {
     fn build (&self, app: &mut App) {
         app
         .add_event::<MovementComplete>()
+        .insert_resource(GridCells::default())
         .add_systems(Update, (
             snap_to_grid,
             animate_translation,
-        ).in_set(ApplyGridMovement).chain().run_if(in_state(AppState::Playing)));
+        // The board behind the main menu moves about too.
+        ).in_set(ApplyGridMovement).chain().run_if(in_state(AppState::Playing).or_else(in_state(AppState::MainMenu))));
     }
 }
 
 #[derive(Component, PartialEq, Eq, Hash, Copy, Clone, Debug, Deref, DerefMut)]
 pub struct GridLocation(pub IVec2);
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy)]
 pub struct SnapToGrid;
 
+/// A board cell. Whatever sits on the cell (items, walls) is parented to it.
+#[derive(Component)]
+pub struct GridCell;
+
+/// The cell entity at each board position, so what's on a cell can be looked up instead of scanned for.
+#[derive(Resource, Default)]
+pub struct GridCells(pub HashMap<IVec2, Entity>);
+
+impl GridCells {
+    pub fn get(&self, location: IVec2) -> Option<Entity> {
+        self.0.get(&location).copied()
+    }
+
+    /// Everything parented to the cell at `location`.
+    pub fn contents<'a>(&self, location: IVec2, children: &'a Query<&Children>) -> &'a [Entity] {
+        self.get(location)
+            .and_then(|cell| children.get(cell).ok())
+            .map_or(&[], |children| &**children)
+    }
+}
+
 #[derive(Event)]
 pub struct MovementComplete {
     pub entity: Entity,
@@ -33,6 +58,21 @@ fn center_of(grid_location: &GridLocation) -> Vec2 {
     Vec2::new((grid_location.x * GRID_SPACING) as f32, (grid_location.y * GRID_SPACING) as f32)
 }
 
+/// The in-bounds grid cell under the cursor, if any.
+pub fn cursor_grid_location(window: &Window, camera: &Camera, camera_transform: &GlobalTransform) -> Option<GridLocation> {
+    screen_grid_location(window.cursor_position()?, camera, camera_transform)
+}
+
+/// The in-bounds grid cell under a point on screen, such as a touch, if any.
+pub fn screen_grid_location(position: Vec2, camera: &Camera, camera_transform: &GlobalTransform) -> Option<GridLocation> {
+    let world = camera.viewport_to_world_2d(camera_transform, position)?;
+    let cell = (world / GRID_SPACING as f32).round().as_ivec2();
+    if cell.x < 0 || cell.x >= MAX_X || cell.y < 0 || cell.y >= MAX_Y {
+        return None;
+    }
+    Some(GridLocation(cell))
+}
+
 fn snap_to_grid(
     mut query: Query<(&mut Transform, Option<&mut AnimateTranslation>, Ref<GridLocation>),
     (With<SnapToGrid>, Changed<GridLocation>)>
@@ -41,7 +81,7 @@ fn snap_to_grid(
         let destination = center_of(&grid_location);
         // Insta-snap newly added components.
         if grid_location.is_added() {
-            transform.translation = destination.extend(0.);
+            transform.translation = destination.extend(transform.translation.z);
             continue;
         }
 
@@ -52,7 +92,7 @@ fn snap_to_grid(
                 animate_transform.timer.reset();
             },
             None => {
-                transform.translation = destination.extend(0.);
+                transform.translation = destination.extend(transform.translation.z);
             },
         }
     }
@@ -66,23 +106,39 @@ pub struct AnimateTranslation {
     pub ease: CubicSegment<Vec2>
 }
 
+/// Another cell to go to as soon as the current move lands, as part of the same turn. The second leg is animated
+/// on its own, and the move only counts as complete once it lands too.
+#[derive(Component)]
+pub struct FollowUpMove(pub IVec2);
+
 fn animate_translation(
+    mut commands: Commands,
     time: Res<Time>,
     mut event_writer: EventWriter<MovementComplete>,
-    mut query: Query<(Entity, &mut Transform, &mut AnimateTranslation)>
+    mut query: Query<(Entity, &mut Transform, &mut AnimateTranslation, Option<&FollowUpMove>, Option<&mut GridLocation>)>
 ) {
-    for (entity, mut transform, mut animate_translation) in query.iter_mut() {
+    for (entity, mut transform, mut animate_translation, follow_up, grid_location) in query.iter_mut() {
         if animate_translation.timer.finished() {
             continue;
         }
 
         if animate_translation.timer.tick(time.delta()).just_finished() {
-            transform.translation = animate_translation.end.extend(0.);
+            transform.translation = animate_translation.end.extend(transform.translation.z);
+            if let (Some(&FollowUpMove(next)), Some(mut grid_location)) = (follow_up, grid_location) {
+                // Restart right away rather than waiting on snap_to_grid, so nothing sees the soot as landed.
+                grid_location.0 = next;
+                animate_translation.start = animate_translation.end;
+                animate_translation.end = center_of(&grid_location);
+                animate_translation.timer.reset();
+                commands.entity(entity).remove::<FollowUpMove>();
+                continue;
+            }
             event_writer.send(MovementComplete{entity});
         } else {
             let progress = animate_translation.timer.percent();
             let lerp = animate_translation.ease.ease(progress);
-            transform.translation = animate_translation.start.lerp(animate_translation.end, lerp).extend(0.);
+            let z = transform.translation.z;
+            transform.translation = animate_translation.start.lerp(animate_translation.end, lerp).extend(z);
         }
     }
 }
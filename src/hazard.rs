@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{next_turn, AppState, LoopCounter, Move, SootSprite, StartLoop, GRID_SPACING, WAIT};
+use crate::grid::{AnimateTranslation, GridCells, GridLocation, MovementComplete};
+use crate::inventory::{Inventory, PickUpItems};
+use crate::level_spec::{Hazard, LevelSpec};
+use crate::palette::Palette;
+
+const FLASH_SECS: f32 = 0.4;
+const FLASH_OPACITY: f32 = 0.4;
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<HazardHit>()
+            .insert_resource(Entering::default())
+            .add_systems(OnEnter(AppState::Playing), clear_entering.in_set(StartLoop))
+            .add_systems(Update, (
+                note_entries,
+                trigger_hazards.after(PickUpItems).before(next_turn),
+                (play_hazard_sound, flash_screen),
+                fade_flash,
+            ).chain().run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Marks a hazard cell; see `Hazard` for what each kind does.
+#[derive(Component, Clone, Copy)]
+pub struct HazardTile(pub Hazard);
+
+/// Sent when a soot comes to rest on a hazard it moved onto.
+#[derive(Event)]
+pub struct HazardHit(pub Hazard);
+
+/// Soots partway through a move. Only moving onto a hazard sets it off; waiting on one, or losing a turn while
+/// standing on one, doesn't.
+#[derive(Resource, Default)]
+struct Entering(HashSet<Entity>);
+
+#[derive(Component)]
+struct HazardFlash {
+    timer: Timer,
+    color: Color,
+}
+
+fn clear_entering(mut entering: ResMut<Entering>) {
+    entering.0.clear();
+}
+
+fn note_entries(mut moves: EventReader<Move>, mut entering: ResMut<Entering>) {
+    for event in moves.iter().filter(|event| event.offset != WAIT) {
+        entering.0.insert(event.mover);
+    }
+}
+
+// Runs once the whole move is done, slides and pushes included, and after pickups, so candy grabbed on the hazard
+// cell can burn too. Before next_turn, so a soot knocked to the exit is already done when turns are handed out.
+fn trigger_hazards(
+    mut completions: EventReader<MovementComplete>,
+    mut entering: ResMut<Entering>,
+    mut soots: Query<(&SootSprite, &mut GridLocation, &mut Inventory, &mut Transform, &mut AnimateTranslation)>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    hazards: Query<&HazardTile>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    mut hits: EventWriter<HazardHit>,
+) {
+    for &MovementComplete{entity} in completions.iter() {
+        if !entering.0.remove(&entity) {
+            continue;
+        }
+        let Ok((soot, location, mut inventory, mut transform, mut animation)) = soots.get_mut(entity) else {
+            continue;
+        };
+        let Some(&HazardTile(hazard)) = cells.contents(location.0, &children).iter()
+            .find_map(|&cell_entity| hazards.get(cell_entity).ok()) else {
+            continue;
+        };
+
+        let exit = level_spec.end_for_loop(loop_counter.0 - soot.id.loop_number());
+        strike(hazard, exit, location, &mut inventory, &mut transform, &mut animation);
+        hits.send(HazardHit(hazard));
+    }
+}
+
+/// Does to a soot what `hazard` does, given the exit it's headed for. Anything else that hurts soots the same ways,
+/// like an enemy's touch, goes through here too.
+pub fn strike(
+    hazard: Hazard,
+    exit: IVec2,
+    mut location: Mut<GridLocation>,
+    inventory: &mut Inventory,
+    transform: &mut Transform,
+    animation: &mut AnimateTranslation,
+) {
+    match hazard {
+        Hazard::Burn(amount) => inventory.points = (inventory.points - amount).max(0),
+        Hazard::Knockout => {
+            // Straight there, without another move to animate or count as a turn.
+            location.bypass_change_detection().0 = exit;
+            let position = (exit * GRID_SPACING).as_vec2();
+            transform.translation = position.extend(transform.translation.z);
+            animation.end = position;
+        },
+    }
+}
+
+fn play_hazard_sound(mut commands: Commands, asset_server: Res<AssetServer>, mut hits: EventReader<HazardHit>) {
+    for _ in hits.iter() {
+        commands.spawn(AudioBundle {
+            source: asset_server.load("hazard.wav"),
+            settings: PlaybackSettings::DESPAWN,
+        });
+    }
+}
+
+fn flash_screen(mut commands: Commands, palette: Res<Palette>, mut hits: EventReader<HazardHit>) {
+    let Some(hit) = hits.iter().last() else {
+        return;
+    };
+    let color = match hit.0 {
+        Hazard::Burn(_) => palette.blocked_move,
+        Hazard::Knockout => palette.warning,
+    };
+    commands.spawn((
+        HazardFlash {timer: Timer::from_seconds(FLASH_SECS, TimerMode::Once), color},
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            background_color: color.with_a(FLASH_OPACITY).into(),
+            ..default()
+        },
+    ));
+}
+
+fn fade_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flashes: Query<(Entity, &mut HazardFlash, &mut BackgroundColor)>,
+) {
+    for (entity, mut flash, mut background) in flashes.iter_mut() {
+        if flash.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        background.0 = flash.color.with_a(FLASH_OPACITY * flash.timer.percent_left());
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{AppState, LoopCounter};
+use crate::inventory::Inventory;
+use crate::rules::GameRules;
+use crate::scoring::{FinishedLoopsScore, Score};
+use crate::shop::Upgrades;
+use crate::spawn_level::{LevelSeed, LevelSource};
+use crate::toast::Toast;
+
+pub struct HighScoresPlugin;
+
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(FinishedRun(None))
+            .add_systems(OnEnter(AppState::GameOver), record_high_score);
+    }
+}
+
+/// Best run total for each level, kept between sessions by the persistence plugin.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    /// Keyed by level file for handcrafted levels, or by seed for generated ones.
+    pub best: BTreeMap<String, i32>,
+}
+
+/// How the run that just ended stacks up. Only set on the last loop of a run.
+#[derive(Resource)]
+pub struct FinishedRun(pub Option<RunScore>);
+
+pub struct RunScore {
+    /// Points scored by every soot over every loop of the run, bonuses included.
+    pub total: i32,
+    /// The best total for this level, counting this run.
+    pub best: i32,
+    pub new_record: bool,
+}
+
+fn level_key(level_source: &LevelSource, level_seed: &LevelSeed) -> String {
+    match level_source {
+        LevelSource::Generated => format!("seed {}", level_seed.seed),
+        LevelSource::File(path) => path.display().to_string(),
+    }
+}
+
+pub fn record_high_score(
+    mut high_scores: ResMut<HighScores>,
+    mut finished_run: ResMut<FinishedRun>,
+    loop_counter: Res<LoopCounter>,
+    rules: Res<GameRules>,
+    upgrades: Res<Upgrades>,
+    level_source: Res<LevelSource>,
+    level_seed: Res<LevelSeed>,
+    soots: Query<(&Inventory, &Score)>,
+    finished_loops: Res<FinishedLoopsScore>,
+    mut toasts: EventWriter<Toast>,
+) {
+    if !loop_counter.is_final_loop(&rules, &upgrades) {
+        finished_run.0 = None;
+        return;
+    }
+
+    let total = finished_loops.run_total(soots.iter());
+    let best = high_scores.best.entry(level_key(&level_source, &level_seed)).or_insert(i32::MIN);
+    let new_record = total > *best;
+    if new_record {
+        // The first run on a level always sets its record; only beating an earlier one is worth announcing.
+        if *best != i32::MIN {
+            toasts.send(Toast::success(format!("New personal best: {}", total)));
+        }
+        *best = total;
+    }
+    finished_run.0 = Some(RunScore {total, best: *best, new_record});
+}
@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+
+use crate::{AppState, DespawnOnExitPlaying, LoopCounter, Player, StartLoop};
+use crate::grid::GridLocation;
+use crate::inventory::Inventory;
+use crate::level_spec::LevelSpec;
+use crate::move_preview::spawn_arrow;
+use crate::shop::Upgrades;
+use crate::solver::best_route;
+use crate::toast::Toast;
+
+const HINT_Z: f32 = 0.65;
+const HINT_COLOR: Color = Color::rgba(0.5, 0.7, 1., 0.9);
+
+pub struct HintsPlugin;
+
+impl Plugin for HintsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(HintsLeft(0))
+            .add_systems(OnEnter(AppState::Playing), reset_hints.in_set(StartLoop))
+            .add_systems(Update, (show_hint, clear_stale_hint).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Hints the player can still ask for this loop; bought in the shop.
+#[derive(Resource)]
+struct HintsLeft(i32);
+
+/// Points the player one step along the best route from where they stand.
+#[derive(Component)]
+struct HintArrow;
+
+fn reset_hints(mut hints_left: ResMut<HintsLeft>, upgrades: Res<Upgrades>) {
+    hints_left.0 = upgrades.hints;
+}
+
+fn show_hint(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut hints_left: ResMut<HintsLeft>,
+    player: Query<(&GridLocation, &Inventory), With<Player>>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    upgrades: Res<Upgrades>,
+    arrows: Query<(), With<HintArrow>>,
+    mut toasts: EventWriter<Toast>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::H) || !arrows.is_empty() || upgrades.hints == 0 {
+        return;
+    }
+    if hints_left.0 <= 0 {
+        toasts.send(Toast::warning("No hints left this loop"));
+        return;
+    }
+    let Ok((location, inventory)) = player.get_single() else {
+        return;
+    };
+
+    let (route, _) = best_route(&level_spec.for_loop(loop_counter.0), location.0, inventory.fuel, &upgrades);
+    let Some(&offset) = route.first() else {
+        toasts.send(Toast::warning("No route to the exit from here"));
+        return;
+    };
+    hints_left.0 -= 1;
+    let arrow = spawn_arrow(&mut commands, location.0, offset, HINT_COLOR, HINT_Z);
+    commands.entity(arrow).insert((HintArrow, DespawnOnExitPlaying));
+}
+
+// A hint is only good for the cell it was asked from.
+fn clear_stale_hint(
+    mut commands: Commands,
+    player: Query<Ref<GridLocation>, With<Player>>,
+    arrows: Query<Entity, With<HintArrow>>,
+) {
+    if !player.get_single().is_ok_and(|location| location.is_changed()) {
+        return;
+    }
+    for entity in arrows.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+use crate::{move_soot_on_grid, soot_blocks, AppState, LoopCounter, Move, SootSprite, MAX_X, MAX_Y, WAIT};
+use crate::grid::{GridCells, GridLocation};
+use crate::level_spec::LevelSpec;
+use crate::rules::GameRules;
+use crate::spawn_level::Wall;
+use crate::teleporter::enter_teleporters;
+
+pub struct IcePlugin;
+
+impl Plugin for IcePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, slide_on_ice
+            .after(move_soot_on_grid)
+            .before(enter_teleporters)
+            .run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// A soot that moves onto ice keeps going the same way until the cell ahead is blocked or it's off the ice.
+#[derive(Component, Clone, Copy)]
+pub struct Ice;
+
+// The whole slide is one move: the soot animates straight to where it stops and only picks up what's there. Where
+// soots block each other, one in the way stops the slide like a wall.
+pub fn slide_on_ice(
+    mut moves: EventReader<Move>,
+    mut movers: Query<(Entity, &mut GridLocation, Option<&SootSprite>)>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    ice: Query<(), With<Ice>>,
+    walls: Query<(), With<Wall>>,
+    rules: Res<GameRules>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+) {
+    for event in moves.iter().filter(|event| event.offset != WAIT) {
+        let soots: Vec<_> = movers.iter()
+            .filter_map(|(entity, location, soot)| soot.map(|soot| (entity, location.0, soot.id)))
+            .collect();
+        let Ok((_, mut location, soot)) = movers.get_mut(event.mover) else {
+            continue;
+        };
+        let is_open = |cell: IVec2| (0..MAX_X).contains(&cell.x) && (0..MAX_Y).contains(&cell.y)
+            && !cells.contents(cell, &children).iter().any(|&entity| walls.contains(entity))
+            && !(soot.is_some() && soot_blocks(cell, event.mover, soots.iter().copied(), &rules, &level_spec, &loop_counter));
+        let on_ice = |cell: IVec2| cells.contents(cell, &children).iter().any(|&entity| ice.contains(entity));
+        while on_ice(location.0) && is_open(location.0 + event.offset) {
+            location.0 += event.offset;
+        }
+    }
+}
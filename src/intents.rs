@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use bevy::input::touch::TouchPhase;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::AppState;
+use crate::grid::{cursor_grid_location, screen_grid_location};
+use crate::settings::{MoveAction, Settings};
+
+/// Shortest drag, in logical pixels, that counts as a swipe rather than a tap.
+const SWIPE_MIN_DISTANCE: f32 = 40.;
+
+#[derive(SystemSet, Hash, Debug, Clone, Eq, PartialEq)]
+pub struct ReadMoveIntents;
+
+pub struct IntentsPlugin;
+
+impl Plugin for IntentsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<MoveIntent>()
+            .add_systems(Update, (
+                keyboard_intents,
+                mouse_intents,
+                touch_intents,
+            ).in_set(ReadMoveIntents).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// What the player wants to do next, whichever of keyboard, mouse or touch they asked with.
+#[derive(Event, Clone, Copy)]
+pub enum MoveIntent {
+    /// One cell in this direction.
+    Step(IVec2),
+    /// Walk to this cell, however many turns it takes.
+    GoTo(IVec2),
+}
+
+fn keyboard_intents(
+    keyboard_input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    mut intents: EventWriter<MoveIntent>,
+) {
+    if settings.confirm_on_release {
+        // Letting go commits the move, unless another direction is still held: rolling onto it changes the plan.
+        for action in MoveAction::ALL {
+            let others_held = MoveAction::ALL.iter().any(|&other| other != action && settings.key_bindings.pressed(other, &keyboard_input));
+            if settings.key_bindings.just_released(action, &keyboard_input) && !others_held {
+                intents.send(MoveIntent::Step(action.offset()));
+            }
+        }
+        return;
+    }
+
+    let mut offset = IVec2::ZERO;
+    for action in MoveAction::ALL {
+        if settings.key_bindings.just_pressed(action, &keyboard_input) {
+            offset += action.offset();
+        }
+    }
+
+    if offset.length_squared() == 1 {
+        intents.send(MoveIntent::Step(offset));
+    }
+}
+
+/// Clicks and taps meant for a button aren't moves.
+fn over_ui(interactions: &Query<&Interaction>) -> bool {
+    interactions.iter().any(|interaction| *interaction != Interaction::None)
+}
+
+fn mouse_intents(
+    mouse: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    interactions: Query<&Interaction>,
+    mut intents: EventWriter<MoveIntent>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) || over_ui(&interactions) {
+        return;
+    }
+
+    let (camera, camera_transform) = cameras.single();
+    if let Some(cell) = cursor_grid_location(windows.single(), camera, camera_transform) {
+        intents.send(MoveIntent::GoTo(cell.0));
+    }
+}
+
+/// A swipe steps one cell in the direction it mostly went; a tap walks to the tapped cell.
+fn touch_intents(
+    mut touches: EventReader<TouchInput>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    interactions: Query<&Interaction>,
+    mut touch_starts: Local<HashMap<u64, Vec2>>,
+    mut intents: EventWriter<MoveIntent>,
+) {
+    for touch in touches.iter() {
+        match touch.phase {
+            TouchPhase::Started => {
+                touch_starts.insert(touch.id, touch.position);
+            },
+            TouchPhase::Moved => {},
+            TouchPhase::Canceled => {
+                touch_starts.remove(&touch.id);
+            },
+            TouchPhase::Ended => {
+                let Some(start) = touch_starts.remove(&touch.id) else {
+                    continue;
+                };
+                // Screen space is y-down, the board is y-up.
+                let drag = touch.position - start;
+                if drag.length() >= SWIPE_MIN_DISTANCE {
+                    let offset = if drag.x.abs() > drag.y.abs() {
+                        IVec2::new(drag.x.signum() as i32, 0)
+                    } else {
+                        IVec2::new(0, -drag.y.signum() as i32)
+                    };
+                    intents.send(MoveIntent::Step(offset));
+                    continue;
+                }
+                if over_ui(&interactions) {
+                    continue;
+                }
+                let (camera, camera_transform) = cameras.single();
+                if let Some(cell) = screen_grid_location(touch.position, camera, camera_transform) {
+                    intents.send(MoveIntent::GoTo(cell.0));
+                }
+            },
+        }
+    }
+}
@@ -1,7 +1,15 @@
+use std::collections::{BTreeMap, HashSet};
+
 use bevy::prelude::*;
 
-use crate::{AppState, SootSprite};
-use crate::grid::{AnimateTranslation, GridLocation};
+use crate::{AppState, LoopCounter, SootId, SootSprite, StartLoop};
+use crate::grid::{AnimateTranslation, GridCells, GridLocation};
+use crate::level_spec::CandyColor;
+use crate::magnet::Magnetized;
+use crate::rules::GameRules;
+
+/// Cells around a soot that a magnet pulls candy in from.
+const MAGNET_REACH: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
 
 #[derive(SystemSet, Hash, Debug, Clone, Eq, PartialEq)]
 pub struct PickUpItems;
@@ -12,54 +20,236 @@ impl Plugin for InventoryPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, (
             pick_up_item,
-            add_item_to_inventory,
+            (add_item_to_inventory, count_candy_remaining, tally_candy_by_loop),
         ).in_set(PickUpItems).chain().run_if(in_state(AppState::Playing)))
-        .add_event::<ItemGet>();
+        .add_systems(OnEnter(AppState::Playing), (clear_candy_by_loop, clear_candy_collected).in_set(StartLoop))
+        .add_event::<ItemGet>()
+        .insert_resource(CandyRemaining(0))
+        .insert_resource(CandyCollected(0))
+        .insert_resource(CandyByLoop::default());
     }
 }
 
-#[derive(Component, Clone, Copy)]
+#[derive(Component, Debug, Clone, Copy)]
 pub enum Item {
-    Candy,
-    Fuel,
+    Candy(CandyColor),
+    /// A canister holding this much fuel.
+    Fuel(i32),
+    /// Only shows up from the second loop on, and only past selves can pick it up.
+    NetherCandy,
+    /// Numbered from 1; can only be picked up once every lower-numbered candy is gone.
+    NumberedCandy(u8),
+    /// Opens one door, see `Door`.
+    Key,
+    /// Carried until used, then blows up the walls around its holder.
+    Bomb,
+    /// Takes effect straight away instead of being carried, see `Magnetized`.
+    Magnet,
+    /// Adds a loop to the run.
+    TimeCrystal,
+    /// Gives the holder two moves on their next turn.
+    DoubleMove,
+    /// Candy that's only there for this many rounds of each loop, see `TimedCandyPlugin`.
+    TimedCandy(CandyColor, i32),
+}
+
+impl Item {
+    pub fn is_candy(&self) -> bool {
+        matches!(self, Item::Candy(_) | Item::NetherCandy | Item::NumberedCandy(_) | Item::TimedCandy(..))
+    }
+
+    /// What it adds to the score of whoever picks it up; nothing for anything that isn't candy.
+    pub fn points(&self) -> i32 {
+        match self {
+            Item::Candy(color) | Item::TimedCandy(color, _) => color.points(),
+            // Worth the trip to the other side.
+            Item::NetherCandy => 2,
+            Item::NumberedCandy(_) => 1,
+            Item::Fuel(_) | Item::Key | Item::Bomb | Item::Magnet | Item::TimeCrystal | Item::DoubleMove => 0,
+        }
+    }
+
+    /// As the player would say it, e.g. "green candy".
+    pub fn name(&self) -> String {
+        match self {
+            Item::Candy(color) => format!("{} candy", format!("{:?}", color).to_lowercase()),
+            Item::TimedCandy(color, _) => format!("timed {} candy", format!("{:?}", color).to_lowercase()),
+            Item::Fuel(1) => "fuel".to_string(),
+            Item::Fuel(amount) => format!("{} fuel", amount),
+            Item::NetherCandy => "nether candy".to_string(),
+            Item::NumberedCandy(number) => format!("candy #{}", number),
+            Item::Key => "key".to_string(),
+            Item::Bomb => "bomb".to_string(),
+            Item::Magnet => "magnet".to_string(),
+            Item::TimeCrystal => "time crystal".to_string(),
+            Item::DoubleMove => "double move".to_string(),
+        }
+    }
+
+    /// Each kind of pickup gets its own take on the pickup sounds, so what was grabbed can be heard.
+    pub fn pickup_sound(&self) -> PickupSound {
+        // Pitch steps in semitones, as playback speed.
+        let semitones = |steps: f32| 2_f32.powf(steps / 12.);
+        match self {
+            // A major chord across the three colors. Golden candy is the fifth, with a chime over it of its own.
+            Item::Candy(color) | Item::TimedCandy(color, _) => match color {
+                CandyColor::Red => PickupSound {file: "candy-pickup.wav", speed: 1., volume: 1.},
+                CandyColor::Green => PickupSound {file: "candy-pickup.wav", speed: semitones(4.), volume: 1.},
+                CandyColor::Yellow => PickupSound {file: "golden-candy-pickup.wav", speed: 1., volume: 1.},
+            },
+            // Low and quiet, from the other side.
+            Item::NetherCandy => PickupSound {file: "candy-pickup.wav", speed: semitones(-7.), volume: 0.7},
+            // Climbs a whole-tone scale as the sequence goes on, topping out an octave up.
+            Item::NumberedCandy(number) => PickupSound {
+                file: "candy-pickup.wav",
+                speed: semitones(2. * number.saturating_sub(1).min(6) as f32),
+                volume: 1.,
+            },
+            // Bigger canisters sound deeper.
+            Item::Fuel(amount) => PickupSound {
+                file: "fuel-pickup.wav",
+                speed: semitones(-3. * (amount - 1).clamp(0, 4) as f32),
+                volume: 1.,
+            },
+            // An octave up from fuel, bright and jingly.
+            Item::Key => PickupSound {file: "fuel-pickup.wav", speed: semitones(12.), volume: 0.8},
+            // An octave down, heavy.
+            Item::Bomb => PickupSound {file: "fuel-pickup.wav", speed: semitones(-12.), volume: 1.},
+            Item::Magnet => PickupSound {file: "fuel-pickup.wav", speed: semitones(7.), volume: 0.9},
+            // Two octaves up from the candy, glassy.
+            Item::TimeCrystal => PickupSound {file: "candy-pickup.wav", speed: semitones(24.), volume: 1.},
+            Item::DoubleMove => PickupSound {file: "fuel-pickup.wav", speed: semitones(5.), volume: 0.9},
+        }
+    }
+}
+
+pub struct PickupSound {
+    pub file: &'static str,
+    /// Playback speed, which also shifts the pitch.
+    pub speed: f32,
+    pub volume: f32,
 }
 
+/// Most fuel a soot can carry, before upgrades.
+pub const BASE_FUEL_CAPACITY: i32 = 5;
+
 #[derive(Component, Clone, Copy)]
 pub struct Inventory {
-    pub candies: i32,
+    /// Score from candy picked up, by what each piece was worth.
+    pub points: i32,
     pub fuel: i32,
+    /// Fuel picked up past this is wasted.
+    pub max_fuel: i32,
+    pub keys: i32,
+    pub bombs: i32,
 }
 
 impl Inventory {
     fn add(&mut self, item: Item) {
         match item {
-            Item::Candy => self.candies += 1,
-            Item::Fuel => self.fuel += 1,
+            Item::Candy(_) | Item::NetherCandy | Item::NumberedCandy(_) | Item::TimedCandy(..) => self.points += item.points(),
+            Item::Fuel(amount) => self.add_fuel(amount),
+            Item::Key => self.keys += 1,
+            Item::Bomb => self.bombs += 1,
+            Item::Magnet | Item::TimeCrystal | Item::DoubleMove => {},
         }
     }
+
+    pub fn add_fuel(&mut self, amount: i32) {
+        self.fuel = (self.fuel + amount).min(self.max_fuel);
+    }
+}
+
+/// Candy still on the board this loop; set from the spawn count and decremented on every candy pickup.
+#[derive(Resource)]
+pub struct CandyRemaining(pub i32);
+
+/// Candy picked up this loop, by anyone.
+#[derive(Resource)]
+pub struct CandyCollected(pub i32);
+
+/// Points scored this loop, by the loop each soot was played in (counted from the start of the run), so the
+/// haul can be credited to the loop whose route earned it.
+#[derive(Resource, Default, Clone)]
+pub struct CandyByLoop(pub BTreeMap<i32, i32>);
+
+impl CandyByLoop {
+    /// e.g. "Loop 1: 6 points, loop 2: 4 points".
+    pub fn describe(&self) -> String {
+        self.0.iter()
+            .enumerate()
+            .map(|(index, (loop_number, points))| {
+                let label = if index == 0 { "Loop" } else { "loop" };
+                format!("{} {}: {} points", label, loop_number + 1, points)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
 #[derive(Event)]
 pub struct ItemGet {
     pub soot: Entity,
     pub item: Item,
+    /// Where the item was, which a magnet can make a cell away from the soot.
+    pub cell: IVec2,
+    /// The loop the soot picking it up was played in, counted from the start of the run.
+    pub loop_number: i32,
 }
 
 fn pick_up_item(
     mut commands: Commands,
-    soot_sprites: Query<(Entity, &GridLocation, &AnimateTranslation), (With<SootSprite>, With<Inventory>)>,
-    items: Query<(Entity, &GridLocation, &Item)>,
+    soot_sprites: Query<(Entity, &SootSprite, &GridLocation, &AnimateTranslation, Option<&Magnetized>), With<Inventory>>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    items: Query<&Item>,
+    loop_counter: Res<LoopCounter>,
+    rules: Res<GameRules>,
     mut event_writer: EventWriter<ItemGet>)
 {
-    for (soot, &soot_location, animation) in soot_sprites.iter() {
+    let mut next_numbered = items.iter()
+        .filter_map(|item| match item {
+            Item::NumberedCandy(number) => Some(*number),
+            _ => None,
+        })
+        .min();
+
+    // Two soots can reach the same candy in one frame, one of them by magnet; only the first gets it.
+    let mut claimed = HashSet::new();
+    for (soot, soot_sprite, &soot_location, animation, magnetized) in soot_sprites.iter() {
         if !animation.timer.finished() {
             continue;
         }
-        for (entity, item_location, item) in items.iter() {
-            if soot_location == *item_location {
-                commands.entity(entity).despawn();
-                event_writer.send(ItemGet{soot, item: *item});
+        // Without interference, past selves walk their route but leave everything for the present.
+        if !rules.interference && soot_sprite.id != SootId::Player {
+            continue;
+        }
+        // A magnet reaches into the cells around, but only for candy.
+        let pulled = magnetized.map_or(vec![], |_| MAGNET_REACH.map(|offset| soot_location.0 + offset).to_vec());
+        let reached = cells.contents(soot_location.0, &children).iter().map(|&entity| (entity, soot_location.0, false))
+            .chain(pulled.into_iter().flat_map(|cell| cells.contents(cell, &children).iter().map(move |&entity| (entity, cell, true))));
+        for (entity, cell, pulling) in reached {
+            let Ok(item) = items.get(entity) else {
+                continue;
+            };
+            if pulling && !item.is_candy() || claimed.contains(&entity) {
+                continue;
+            }
+            if let Item::NetherCandy = item {
+                if loop_counter.0 == 0 || soot_sprite.id == SootId::Player {
+                    continue;
+                }
+            }
+            if let Item::NumberedCandy(number) = item {
+                // Out of order: the candy just stays put.
+                if Some(*number) != next_numbered {
+                    continue;
+                }
+                next_numbered = Some(number + 1);
             }
+            claimed.insert(entity);
+            commands.entity(entity).despawn_recursive();
+            event_writer.send(ItemGet{soot, item: *item, cell, loop_number: loop_counter.0 - soot_sprite.id.loop_number()});
         }
     }
 }
@@ -73,3 +263,30 @@ fn add_item_to_inventory(
         inventory.add(event.item);
     }
 }
+
+fn clear_candy_by_loop(mut candy_by_loop: ResMut<CandyByLoop>) {
+    candy_by_loop.0.clear();
+}
+
+fn tally_candy_by_loop(mut candy_by_loop: ResMut<CandyByLoop>, mut event_reader: EventReader<ItemGet>) {
+    for event in event_reader.iter().filter(|event| event.item.is_candy()) {
+        *candy_by_loop.0.entry(event.loop_number).or_default() += event.item.points();
+    }
+}
+
+fn clear_candy_collected(mut candy_collected: ResMut<CandyCollected>) {
+    candy_collected.0 = 0;
+}
+
+fn count_candy_remaining(
+    mut candy_remaining: ResMut<CandyRemaining>,
+    mut candy_collected: ResMut<CandyCollected>,
+    mut event_reader: EventReader<ItemGet>,
+) {
+    for event in event_reader.iter() {
+        if event.item.is_candy() {
+            candy_remaining.0 -= 1;
+            candy_collected.0 += 1;
+        }
+    }
+}
@@ -0,0 +1,575 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{MAX_X, MAX_Y, WAIT};
+use crate::rules::RuleOverrides;
+use crate::solver::unreachable_candies;
+use crate::storage;
+
+// Where generated levels start and end; handcrafted levels can put them anywhere, and move them from loop to loop.
+const START_SPACE: IVec2 = IVec2 {x: 0, y: MAX_Y - 1};
+const END_SPACE: IVec2 = IVec2 {x: MAX_X - 1, y: 0};
+const NUM_CANDIES: usize = 10;
+const NUM_FUEL: usize = 2;
+const NUM_NETHER_CANDIES: usize = 3;
+const NUM_NUMBERED_CANDIES: usize = 3;
+/// Odds of a generated board getting a time crystal.
+const TIME_CRYSTAL_CHANCE: f64 = 0.1;
+/// Layouts to roll before settling for one with unreachable candy.
+const MAX_GENERATION_ATTEMPTS: usize = 100;
+/// There's no filesystem in the browser, so the levels shipped with the game are built in.
+#[cfg(target_arch = "wasm32")]
+const BUNDLED_LEVELS: [(&str, &str); 4] = [
+    ("assets/levels/count-up.ron", include_str!("../assets/levels/count-up.ron")),
+    ("assets/levels/first-steps.ron", include_str!("../assets/levels/first-steps.ron")),
+    ("assets/levels/relay.ron", include_str!("../assets/levels/relay.ron")),
+    ("assets/levels/walled-garden.ron", include_str!("../assets/levels/walled-garden.ron")),
+];
+
+/// Declarative description of a board: what goes where. Spawning turns this into entities.
+#[derive(Resource, Debug, Clone)]
+pub struct LevelSpec {
+    pub start: IVec2,
+    /// Overrides `start` for the loops listed, by loop number from the start of the run.
+    pub loop_starts: Vec<IVec2>,
+    pub end: IVec2,
+    /// Overrides `end` for the loops listed, like `loop_starts`. Each soot heads for the exit of the loop it was
+    /// recorded in.
+    pub loop_ends: Vec<IVec2>,
+    pub candies: Vec<(IVec2, CandyColor)>,
+    /// Candy that disappears if nobody picks it up within this many rounds of a loop.
+    pub timed_candies: Vec<(IVec2, CandyColor, i32)>,
+    pub fuel: Vec<IVec2>,
+    /// Fuel pickups holding more than one fuel, and how much.
+    pub canisters: Vec<(IVec2, i32)>,
+    pub nether_candies: Vec<IVec2>,
+    /// In collection order: the first cell holds candy number 1.
+    pub numbered_candies: Vec<IVec2>,
+    pub walls: Vec<IVec2>,
+    /// Each one opens one door.
+    pub keys: Vec<IVec2>,
+    /// Walls that a soot holding a key can walk through, using up the key.
+    pub doors: Vec<IVec2>,
+    /// Each pressure plate and the gate it holds open; several plates can hold the same gate.
+    pub plates: Vec<(IVec2, IVec2)>,
+    /// Switches, by id. Stepping onto one flips every gate with the same id.
+    pub switches: Vec<(IVec2, u8)>,
+    /// Gates worked by switches: the id of the switches that flip it, and whether it starts out open.
+    pub switch_gates: Vec<(IVec2, u8, bool)>,
+    /// Linked cells: stepping onto either end carries the soot to the other.
+    pub teleporters: Vec<(IVec2, IVec2)>,
+    /// Push whoever ends a turn on them one cell along.
+    pub conveyors: Vec<(IVec2, Direction)>,
+    /// Cells that hurt whoever walks onto them.
+    pub hazards: Vec<(IVec2, Hazard)>,
+    /// Enemies, each walking its route back and forth a cell per round and doing what the hazard would to any soot it
+    /// meets.
+    pub patrols: Vec<(Vec<IVec2>, Hazard)>,
+    /// Enemies that start here and head for whichever soot's turn is next, by the shortest way round the walls.
+    pub chasers: Vec<(IVec2, Hazard)>,
+    /// Each one can be picked up and set off later to clear the walls around whoever's holding it.
+    pub bombs: Vec<IVec2>,
+    /// Each one, once picked up, pulls in candy from the cells around whoever's holding it for a few turns.
+    pub magnets: Vec<IVec2>,
+    /// Each one adds a loop to the run for whoever picks it up.
+    pub time_crystals: Vec<IVec2>,
+    /// Each one gives whoever picks it up two moves on their next turn.
+    pub double_moves: Vec<IVec2>,
+    /// A soot that moves onto ice keeps sliding the same way until it reaches a cell that isn't.
+    pub ice: Vec<IVec2>,
+    /// Merged into the game rules when the level is loaded.
+    pub rules: RuleOverrides,
+}
+
+impl Default for LevelSpec {
+    fn default() -> Self {
+        Self {
+            start: START_SPACE,
+            loop_starts: vec![],
+            end: END_SPACE,
+            loop_ends: vec![],
+            candies: vec![],
+            timed_candies: vec![],
+            fuel: vec![],
+            canisters: vec![],
+            nether_candies: vec![],
+            numbered_candies: vec![],
+            walls: vec![],
+            keys: vec![],
+            doors: vec![],
+            plates: vec![],
+            switches: vec![],
+            switch_gates: vec![],
+            teleporters: vec![],
+            ice: vec![],
+            conveyors: vec![],
+            hazards: vec![],
+            patrols: vec![],
+            chasers: vec![],
+            bombs: vec![],
+            magnets: vec![],
+            time_crystals: vec![],
+            double_moves: vec![],
+            rules: default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CandyColor {
+    Red,
+    Green,
+    Yellow,
+}
+
+impl CandyColor {
+    /// Each is marked with a pip per point, so the tiers can be told apart without their colors.
+    pub fn texture(&self) -> &'static str {
+        match self {
+            CandyColor::Red => "red-candy.png",
+            CandyColor::Green => "green-candy.png",
+            CandyColor::Yellow => "yellow-candy.png",
+        }
+    }
+
+    /// What one is worth to whoever picks it up.
+    pub fn points(&self) -> i32 {
+        match self {
+            CandyColor::Red => 1,
+            CandyColor::Green => 2,
+            CandyColor::Yellow => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// What a hazard cell does to a soot that moves onto it.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Hazard {
+    /// Burns away this many points from the candy the soot is carrying.
+    Burn(i32),
+    /// Sends the soot straight to the exit, ending its loop.
+    Knockout,
+}
+
+impl Direction {
+    pub fn offset(&self) -> IVec2 {
+        match self {
+            Direction::Up => IVec2::Y,
+            Direction::Down => IVec2::NEG_Y,
+            Direction::Left => IVec2::NEG_X,
+            Direction::Right => IVec2::X,
+        }
+    }
+
+    fn from_offset(offset: IVec2) -> Self {
+        match (offset.x, offset.y) {
+            (0, 1) => Direction::Up,
+            (0, -1) => Direction::Down,
+            (-1, 0) => Direction::Left,
+            _ => Direction::Right,
+        }
+    }
+}
+
+impl LevelSpec {
+    /// Randomly lays out a board where every candy can be collected over `num_loops` loops. The same seed always
+    /// produces the same layout.
+    pub fn generate(seed: u64, num_loops: i32) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut spec = Self::random(&mut rng);
+        for _ in 1..MAX_GENERATION_ATTEMPTS {
+            if unreachable_candies(&spec, num_loops).is_empty() {
+                return spec;
+            }
+            spec = Self::random(&mut rng);
+        }
+        warn!("Gave up looking for a fully solvable layout for seed {}", seed);
+        spec
+    }
+
+    fn random(rng: &mut StdRng) -> Self {
+        // Items stay off both corners so every board transform below maps start and end onto empty cells.
+        let random_location = |rng: &mut StdRng| loop {
+            let location = IVec2 {x: rng.gen_range(0..MAX_X), y: rng.gen_range(0..MAX_Y)};
+            if location != START_SPACE && location != END_SPACE {
+                return location;
+            }
+        };
+
+        let candies = (0..NUM_CANDIES).map(|_| {
+            let color = match rng.gen_range(0..3) {
+                0 => CandyColor::Red,
+                1 => CandyColor::Green,
+                2 => CandyColor::Yellow,
+                _ => unreachable!(),
+            };
+            (random_location(rng), color)
+        }).collect();
+        let fuel = (0..NUM_FUEL).map(|_| random_location(rng)).collect();
+        let nether_candies = (0..NUM_NETHER_CANDIES).map(|_| random_location(rng)).collect();
+        let numbered_candies = (0..NUM_NUMBERED_CANDIES).map(|_| random_location(rng)).collect();
+        let time_crystals = rng.gen_bool(TIME_CRYSTAL_CHANCE).then(|| random_location(rng)).into_iter().collect();
+
+        Self {candies, fuel, nether_candies, numbered_candies, time_crystals, ..default()}
+    }
+
+    /// Reads a handcrafted level from a RON file; see `assets/levels/` for examples.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        #[cfg(target_arch = "wasm32")]
+        let bundled = BUNDLED_LEVELS.iter().find(|(bundled, _)| Path::new(bundled) == path);
+        #[cfg(target_arch = "wasm32")]
+        let contents = match bundled {
+            Some((_, contents)) => contents.to_string(),
+            None => storage::read(path)?,
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let contents = storage::read(path)?;
+        let file: LevelFile = ron::from_str(&contents).map_err(|err| format!("couldn't parse {}: {}", path.display(), err))?;
+        file.into_spec()
+    }
+
+    /// Where the soot played during the given loop (counted from the start of the run) spawns.
+    pub fn start_for_loop(&self, loop_number: i32) -> IVec2 {
+        self.loop_starts.get(loop_number as usize).copied().unwrap_or(self.start)
+    }
+
+    /// Where the soot played during the given loop (counted from the start of the run) is headed.
+    pub fn end_for_loop(&self, loop_number: i32) -> IVec2 {
+        self.loop_ends.get(loop_number as usize).copied().unwrap_or(self.end)
+    }
+
+    /// The board as the soot played during the given loop sees it: `start` and `end` are that loop's.
+    pub fn for_loop(&self, loop_number: i32) -> Self {
+        Self {
+            start: self.start_for_loop(loop_number),
+            end: self.end_for_loop(loop_number),
+            ..self.clone()
+        }
+    }
+
+    /// Whether a soot can move into `cell` at all. Doors and gates count as shut, since routes are planned without
+    /// keys or help from anyone else.
+    pub fn is_open(&self, cell: IVec2) -> bool {
+        (0..MAX_X).contains(&cell.x) && (0..MAX_Y).contains(&cell.y)
+            && !self.walls.contains(&cell) && !self.doors.contains(&cell)
+            && !self.plates.iter().any(|&(_, gate)| gate == cell)
+            && !self.switch_gates.iter().any(|&(gate, _, _)| gate == cell)
+    }
+
+    /// Where a soot moving `offset` from `from` comes to rest, given that the first step is open. It slides on over
+    /// ice until the cell ahead is blocked or it's off the ice, then takes any teleporter or else conveyor it stops
+    /// on. Ending up on a knockout hazard sends it to the exit.
+    pub fn landing_cell(&self, from: IVec2, offset: IVec2) -> IVec2 {
+        if offset == WAIT {
+            return self.conveyed(from);
+        }
+        let mut cell = from + offset;
+        while self.ice.contains(&cell) && self.is_open(cell + offset) {
+            cell += offset;
+        }
+        let partner = self.teleporters.iter().find_map(|&(a, b)| match cell {
+            _ if cell == a => Some(b),
+            _ if cell == b => Some(a),
+            _ => None,
+        });
+        let cell = partner.unwrap_or_else(|| self.conveyed(cell));
+        match self.hazards.iter().find(|(location, _)| *location == cell) {
+            Some((_, Hazard::Knockout)) => self.end,
+            _ => cell,
+        }
+    }
+
+    /// Where a soot that ends its turn on `cell` gets pushed to, if that's a conveyor and the way is clear.
+    fn conveyed(&self, cell: IVec2) -> IVec2 {
+        self.conveyors.iter()
+            .find(|(location, _)| *location == cell)
+            .map(|(_, direction)| cell + direction.offset())
+            .filter(|&pushed| self.is_open(pushed))
+            .unwrap_or(cell)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let cell = |location: &IVec2| (location.x, location.y);
+        let file = LevelFile {
+            size: (MAX_X, MAX_Y),
+            start: cell(&self.start),
+            loop_starts: self.loop_starts.iter().map(cell).collect(),
+            end: cell(&self.end),
+            loop_ends: self.loop_ends.iter().map(cell).collect(),
+            candies: self.candies.iter().map(|(location, color)| (location.x, location.y, *color)).collect(),
+            timed_candies: self.timed_candies.iter()
+                .map(|(location, color, rounds)| (location.x, location.y, *color, *rounds))
+                .collect(),
+            fuel: self.fuel.iter().map(cell).collect(),
+            canisters: self.canisters.iter().map(|(location, amount)| (location.x, location.y, *amount)).collect(),
+            nether_candies: self.nether_candies.iter().map(cell).collect(),
+            numbered_candies: self.numbered_candies.iter().map(cell).collect(),
+            walls: self.walls.iter().map(cell).collect(),
+            keys: self.keys.iter().map(cell).collect(),
+            bombs: self.bombs.iter().map(cell).collect(),
+            magnets: self.magnets.iter().map(cell).collect(),
+            time_crystals: self.time_crystals.iter().map(cell).collect(),
+            double_moves: self.double_moves.iter().map(cell).collect(),
+            doors: self.doors.iter().map(cell).collect(),
+            plates: self.plates.iter().map(|(plate, gate)| (cell(plate), cell(gate))).collect(),
+            switches: self.switches.iter().map(|(location, id)| (cell(location), *id)).collect(),
+            switch_gates: self.switch_gates.iter().map(|(location, id, open)| (cell(location), *id, *open)).collect(),
+            teleporters: self.teleporters.iter().map(|(a, b)| (cell(a), cell(b))).collect(),
+            ice: self.ice.iter().map(cell).collect(),
+            conveyors: self.conveyors.iter().map(|(location, direction)| (location.x, location.y, *direction)).collect(),
+            hazards: self.hazards.iter().map(|(location, hazard)| (location.x, location.y, *hazard)).collect(),
+            patrols: self.patrols.iter().map(|(route, hazard)| (route.iter().map(cell).collect(), *hazard)).collect(),
+            chasers: self.chasers.iter().map(|(location, hazard)| (cell(location), *hazard)).collect(),
+            rules: self.rules.clone(),
+        };
+        let contents = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+            .map_err(|err| format!("couldn't serialize level: {}", err))?;
+        storage::write(path, &contents)
+    }
+
+    pub fn transformed(mut self, transform: BoardTransform) -> Self {
+        for (location, _) in self.candies.iter_mut() {
+            *location = transform.apply(*location);
+        }
+        let cells = self.loop_starts.iter_mut()
+            .chain(self.loop_ends.iter_mut())
+            .chain(self.fuel.iter_mut())
+            .chain(self.nether_candies.iter_mut())
+            .chain(self.numbered_candies.iter_mut())
+            .chain(self.walls.iter_mut())
+            .chain(self.keys.iter_mut())
+            .chain(self.bombs.iter_mut())
+            .chain(self.magnets.iter_mut())
+            .chain(self.time_crystals.iter_mut())
+            .chain(self.double_moves.iter_mut())
+            .chain(self.doors.iter_mut())
+            .chain(self.ice.iter_mut());
+        for location in cells {
+            *location = transform.apply(*location);
+        }
+        for (a, b) in self.teleporters.iter_mut().chain(self.plates.iter_mut()) {
+            (*a, *b) = (transform.apply(*a), transform.apply(*b));
+        }
+        for (location, _) in self.hazards.iter_mut().chain(self.chasers.iter_mut()) {
+            *location = transform.apply(*location);
+        }
+        for (location, _) in self.canisters.iter_mut() {
+            *location = transform.apply(*location);
+        }
+        for (location, _, _) in self.timed_candies.iter_mut() {
+            *location = transform.apply(*location);
+        }
+        for location in self.patrols.iter_mut().flat_map(|(route, _)| route.iter_mut()) {
+            *location = transform.apply(*location);
+        }
+        for (location, _) in self.switches.iter_mut() {
+            *location = transform.apply(*location);
+        }
+        for (location, _, _) in self.switch_gates.iter_mut() {
+            *location = transform.apply(*location);
+        }
+        for (location, direction) in self.conveyors.iter_mut() {
+            // Both ends of the belt move with the board, so the direction follows along.
+            let ahead = transform.apply(*location + direction.offset());
+            *location = transform.apply(*location);
+            *direction = Direction::from_offset(ahead - *location);
+        }
+        (self.start, self.end) = (transform.apply(self.start), transform.apply(self.end));
+        // Rotating reverses the route; swap the ends back so it's still walked down and right.
+        if let BoardTransform::Rotate | BoardTransform::MirrorRotate = transform {
+            std::mem::swap(&mut self.start, &mut self.end);
+            std::mem::swap(&mut self.loop_starts, &mut self.loop_ends);
+        }
+        self
+    }
+}
+
+/// Symmetries of the board that keep the start-to-end diagonal in place, so one seed gives four distinct layouts
+/// that are all still played moving down and right. Transforms that would swap start and end have them swapped
+/// back, which is why only these four are offered. They assume a square board.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum BoardTransform {
+    #[default]
+    Identity,
+    /// Reflect across the start-end diagonal.
+    Mirror,
+    /// Rotate half a turn about the board center.
+    Rotate,
+    /// Both of the above; a reflection across the other diagonal.
+    MirrorRotate,
+}
+
+impl BoardTransform {
+    pub fn new(mirror: bool, rotate: bool) -> Self {
+        match (mirror, rotate) {
+            (false, false) => BoardTransform::Identity,
+            (true, false) => BoardTransform::Mirror,
+            (false, true) => BoardTransform::Rotate,
+            (true, true) => BoardTransform::MirrorRotate,
+        }
+    }
+
+    fn apply(&self, location: IVec2) -> IVec2 {
+        let max = IVec2::new(MAX_X - 1, MAX_Y - 1);
+        match self {
+            BoardTransform::Identity => location,
+            BoardTransform::Mirror => IVec2::new(max.y - location.y, max.x - location.x),
+            BoardTransform::Rotate => max - location,
+            BoardTransform::MirrorRotate => IVec2::new(location.y, location.x),
+        }
+    }
+}
+
+type Cell = (i32, i32);
+
+/// On-disk level format. Cells are `(x, y)` with `(0, 0)` in the bottom-left corner.
+#[derive(Serialize, Deserialize)]
+struct LevelFile {
+    size: Cell,
+    start: Cell,
+    /// Spawn cell for each loop in turn; loops past the end of the list use `start`.
+    #[serde(default)]
+    loop_starts: Vec<Cell>,
+    end: Cell,
+    /// Exit for each loop in turn; loops past the end of the list use `end`.
+    #[serde(default)]
+    loop_ends: Vec<Cell>,
+    #[serde(default)]
+    candies: Vec<(i32, i32, CandyColor)>,
+    /// Each candy and the rounds it lasts, e.g. `timed_candies: [(2, 3, Yellow, 6)]`.
+    #[serde(default)]
+    timed_candies: Vec<(i32, i32, CandyColor, i32)>,
+    #[serde(default)]
+    fuel: Vec<Cell>,
+    /// Bigger fuel pickups and how much each holds, e.g. `canisters: [(2, 3, 3)]`.
+    #[serde(default)]
+    canisters: Vec<(i32, i32, i32)>,
+    #[serde(default)]
+    nether_candies: Vec<Cell>,
+    /// Collected in the order listed.
+    #[serde(default)]
+    numbered_candies: Vec<Cell>,
+    #[serde(default)]
+    walls: Vec<Cell>,
+    #[serde(default)]
+    keys: Vec<Cell>,
+    #[serde(default)]
+    bombs: Vec<Cell>,
+    #[serde(default)]
+    magnets: Vec<Cell>,
+    #[serde(default)]
+    time_crystals: Vec<Cell>,
+    #[serde(default)]
+    double_moves: Vec<Cell>,
+    #[serde(default)]
+    doors: Vec<Cell>,
+    /// Each plate and the gate it opens, e.g. `plates: [((1, 3), (2, 2))]`.
+    #[serde(default)]
+    plates: Vec<(Cell, Cell)>,
+    /// Each switch and its id, e.g. `switches: [((0, 2), 1)]`.
+    #[serde(default)]
+    switches: Vec<(Cell, u8)>,
+    /// Each switch gate, the id of its switches and whether it starts open, e.g. `switch_gates: [((3, 3), 1, false)]`.
+    #[serde(default)]
+    switch_gates: Vec<(Cell, u8, bool)>,
+    /// Pairs of linked cells, e.g. `teleporters: [((1, 3), (3, 1))]`.
+    #[serde(default)]
+    teleporters: Vec<(Cell, Cell)>,
+    #[serde(default)]
+    ice: Vec<Cell>,
+    /// e.g. `conveyors: [(2, 3, Right)]`.
+    #[serde(default)]
+    conveyors: Vec<(i32, i32, Direction)>,
+    /// e.g. `hazards: [(1, 1, Burn(2)), (3, 2, Knockout)]`.
+    #[serde(default)]
+    hazards: Vec<(i32, i32, Hazard)>,
+    /// Each enemy's route and what it does on contact, e.g. `patrols: [([(1, 2), (2, 2), (3, 2)], Burn(1))]`.
+    #[serde(default)]
+    patrols: Vec<(Vec<Cell>, Hazard)>,
+    /// Where each chasing enemy starts and what it does on contact, e.g. `chasers: [((4, 0), Knockout)]`.
+    #[serde(default)]
+    chasers: Vec<(Cell, Hazard)>,
+    /// Rule overrides for this level, e.g. `rules: (loops: Some(1), interference: Some(false))`.
+    #[serde(default)]
+    rules: RuleOverrides,
+}
+
+impl LevelFile {
+    fn into_spec(self) -> Result<LevelSpec, String> {
+        // The board size is still compiled in, so a file can only describe what goes on it.
+        if self.size != (MAX_X, MAX_Y) {
+            return Err(format!("only {}x{} boards are supported", MAX_X, MAX_Y));
+        }
+
+        let cell = |(x, y): Cell| {
+            if !(0..MAX_X).contains(&x) || !(0..MAX_Y).contains(&y) {
+                return Err(format!("cell ({}, {}) is off the board", x, y));
+            }
+            Ok(IVec2::new(x, y))
+        };
+
+        Ok(LevelSpec {
+            start: cell(self.start)?,
+            loop_starts: self.loop_starts.into_iter().map(cell).collect::<Result<_, _>>()?,
+            end: cell(self.end)?,
+            loop_ends: self.loop_ends.into_iter().map(cell).collect::<Result<_, _>>()?,
+            candies: self.candies.into_iter()
+                .map(|(x, y, color)| Ok((cell((x, y))?, color)))
+                .collect::<Result<_, String>>()?,
+            timed_candies: self.timed_candies.into_iter()
+                .map(|(x, y, color, rounds)| Ok((cell((x, y))?, color, rounds)))
+                .collect::<Result<_, String>>()?,
+            fuel: self.fuel.into_iter().map(cell).collect::<Result<_, _>>()?,
+            canisters: self.canisters.into_iter()
+                .map(|(x, y, amount)| Ok((cell((x, y))?, amount)))
+                .collect::<Result<_, String>>()?,
+            nether_candies: self.nether_candies.into_iter().map(cell).collect::<Result<_, _>>()?,
+            numbered_candies: self.numbered_candies.into_iter().map(cell).collect::<Result<_, _>>()?,
+            walls: self.walls.into_iter().map(cell).collect::<Result<_, _>>()?,
+            keys: self.keys.into_iter().map(cell).collect::<Result<_, _>>()?,
+            bombs: self.bombs.into_iter().map(cell).collect::<Result<_, _>>()?,
+            magnets: self.magnets.into_iter().map(cell).collect::<Result<_, _>>()?,
+            time_crystals: self.time_crystals.into_iter().map(cell).collect::<Result<_, _>>()?,
+            double_moves: self.double_moves.into_iter().map(cell).collect::<Result<_, _>>()?,
+            doors: self.doors.into_iter().map(cell).collect::<Result<_, _>>()?,
+            plates: self.plates.into_iter()
+                .map(|(plate, gate)| Ok((cell(plate)?, cell(gate)?)))
+                .collect::<Result<_, String>>()?,
+            switches: self.switches.into_iter()
+                .map(|(location, id)| Ok((cell(location)?, id)))
+                .collect::<Result<_, String>>()?,
+            switch_gates: self.switch_gates.into_iter()
+                .map(|(location, id, open)| Ok((cell(location)?, id, open)))
+                .collect::<Result<_, String>>()?,
+            teleporters: self.teleporters.into_iter()
+                .map(|(a, b)| Ok((cell(a)?, cell(b)?)))
+                .collect::<Result<_, String>>()?,
+            ice: self.ice.into_iter().map(cell).collect::<Result<_, _>>()?,
+            conveyors: self.conveyors.into_iter()
+                .map(|(x, y, direction)| Ok((cell((x, y))?, direction)))
+                .collect::<Result<_, String>>()?,
+            hazards: self.hazards.into_iter()
+                .map(|(x, y, hazard)| Ok((cell((x, y))?, hazard)))
+                .collect::<Result<_, String>>()?,
+            patrols: self.patrols.into_iter()
+                .map(|(route, hazard)| Ok((route.into_iter().map(cell).collect::<Result<_, _>>()?, hazard)))
+                .collect::<Result<_, String>>()?,
+            chasers: self.chasers.into_iter()
+                .map(|(location, hazard)| Ok((cell(location)?, hazard)))
+                .collect::<Result<_, String>>()?,
+            rules: self.rules,
+        })
+    }
+}
@@ -0,0 +1,991 @@
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
+use std::collections::VecDeque;
+
+use bevy::app::PluginGroupBuilder;
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+
+use achievements::AchievementsPlugin;
+use camera_controls::CameraControlsPlugin;
+use camera_effects::CameraEffectsPlugin;
+use campaign::CampaignPlugin;
+use candy_decay::CandyDecayPlugin;
+use celebration::CelebrationPlugin;
+use click_to_move::ClickToMovePlugin;
+use comparison::ComparisonPlugin;
+use cosmetics::CosmeticsPlugin;
+use debug_overlay::DebugOverlayPlugin;
+use divergence::DivergencePlugin;
+use doors::{Door, DoorsPlugin};
+use double_move::DoubleMovePlugin;
+use editor::EditorPlugin;
+use enemies::{enemies_done, EnemiesPlugin};
+use focus::FocusPlugin;
+use fog::FogPlugin;
+use game_over_screen::GameOverScreenPlugin;
+use gates::GatesPlugin;
+use high_scores::HighScoresPlugin;
+use grid::{GridPlugin, GridCells, GridLocation, ApplyGridMovement, AnimateTranslation, FollowUpMove, MovementComplete};
+use intents::{IntentsPlugin, MoveIntent, ReadMoveIntents};
+use inventory::{CandyCollected, CandyRemaining, Inventory, ItemGet, PickUpItems, InventoryPlugin};
+use level_spec::LevelSpec;
+use hazard::HazardPlugin;
+use hints::HintsPlugin;
+use ice::{slide_on_ice, IcePlugin};
+use conveyor::{ride_conveyors, ConveyorPlugin};
+use magnet::MagnetPlugin;
+use loop_summary::LoopSummaryPlugin;
+use main_menu::MainMenuPlugin;
+use markers::MarkersPlugin;
+use menu_board::MenuBoardPlugin;
+use minimap::MinimapPlugin;
+use recorded_paths::RecordedPathsPlugin;
+use replay::ReplayPlugin;
+use rewind::RewindPlugin;
+use run_log::RunLogPlugin;
+use rules::{Collisions, GameRules};
+use scoring::ScoringPlugin;
+use settings::{Settings, SettingsPlugin};
+use settings_menu::SettingsMenuPlugin;
+use shop::{ShopPlugin, Upgrades};
+use soot_panel::SootPanelPlugin;
+use move_preview::MovePreviewPlugin;
+use music::MusicPlugin;
+use painting::PaintingPlugin;
+use palette::PalettePlugin;
+use particles::ParticlesPlugin;
+use pause_menu::PauseMenuPlugin;
+use persistence::PersistencePlugin;
+use pickup_flight::PickupFlightPlugin;
+use spawn_level::{SpawnLevelPlugin, Wall};
+use sprite_animation::{Facing, SpriteAnimationPlugin};
+use telemetry::TelemetryPlugin;
+use turn_indicator::TurnIndicatorPlugin;
+use turn_timer::TurnTimerPlugin;
+use teleporter::TeleporterPlugin;
+use text_input::TextInputPlugin;
+use time_crystal::TimeCrystalPlugin;
+use timed_candy::TimedCandyPlugin;
+use toast::ToastPlugin;
+use trading::{Trade, TradingPlugin};
+use watchdog::WatchdogPlugin;
+use wiggle::WigglePlugin;
+use ui::{UiPlugin, UpdateUi};
+use undo::UndoPlugin;
+use usable_items::{ItemUse, UsableItemsPlugin};
+
+mod achievements;
+mod camera_controls;
+mod camera_effects;
+mod campaign;
+mod candy_decay;
+mod celebration;
+mod click_to_move;
+mod comparison;
+mod conveyor;
+mod cosmetics;
+mod debug_overlay;
+mod divergence;
+mod doors;
+mod double_move;
+mod editor;
+mod enemies;
+mod focus;
+mod fog;
+mod game_over_screen;
+mod gates;
+mod grid;
+mod hazard;
+mod high_scores;
+mod ice;
+mod hints;
+mod intents;
+mod inventory;
+mod level_spec;
+mod loop_summary;
+mod magnet;
+mod main_menu;
+mod markers;
+mod menu_board;
+mod minimap;
+mod painting;
+mod palette;
+mod particles;
+mod pause_menu;
+mod persistence;
+mod pickup_flight;
+mod profile;
+mod recorded_paths;
+mod replay;
+mod rewind;
+mod rules;
+mod run_log;
+mod scoring;
+mod settings;
+mod settings_menu;
+mod shop;
+pub mod soak;
+mod soot_panel;
+#[cfg(feature = "screenshot-tests")]
+pub mod screenshot_tests;
+mod solver;
+mod move_preview;
+mod music;
+#[cfg(feature = "platform")]
+mod platform;
+mod ui;
+mod undo;
+mod usable_items;
+mod spawn_level;
+mod sprite_animation;
+mod storage;
+pub use storage::DATA_DIR_VAR;
+mod telemetry;
+mod turn_indicator;
+mod turn_timer;
+mod teleporter;
+mod text_input;
+mod time_crystal;
+mod timed_candy;
+mod toast;
+mod trading;
+mod watchdog;
+mod wiggle;
+#[cfg(feature = "stream-overlay")]
+mod stream_overlay;
+
+// Current gameplay:
+// - move down and right on a grid, optimize your path to get the most candy
+// - grab fuel to be able to go up or left, adds a nice bit of complexity to the optimization problem.
+
+// Immediate next steps:
+// - Add sound effects !! (done)
+// - Fix the double-despawn bug (kinda fixed, still latent)
+// - Fix fuel and candy spawning on first/last cell (done)
+// - Generalize to n time loops (go until all candy is collected)
+// - UI / appstate changes for time loops
+// - Iterate further on plugin structure
+
+// More gameplay:
+// - Add a between-levels upgrade system of some kind; spend candy, get upgrades. (done)
+
+// Tech debt:
+// - No hierarchical entity relationships; everything is just flat right now. That is fine for now but not forever.
+// (Items and walls are now children of their grid cell.)
+// - Pull out more plugins. main.rs is a dumping ground right now lol. Next: looping? movement? application state?
+// - Event-based control flow can easily lead to hard bugs like deadlocks. Is there a better way then events for
+// signaling between systems when I need to guarantee some invariant? Maybe a State, so we can more directly model this
+// with a state machine?
+
+// Bugs:
+// - Fuel can spawn on the last cell, and if it does you won't get to use it as the game will end first.
+// - Some kind of double-despawn bug seems to be happening.
+// Figured out some of why this was happening - pickups on the last spot were despawning twice, once when they were
+// picked up and once when the level despawned. I fixed this by not spawning pickups on the last spot.
+// However this is a broader issue with system ordering when transitioning between states.
+
+// Polish:
+// - Show the actual candies/fuel collected in the score display instead of a number
+// - Animate candy/fuel collection - have it fly up to the score/fuel display? (done)
+// - Add a background to the level (done)
+// - Style the score/fuel display
+// - Control the window dimensions ?
+// - Use a sprite for the grid cells instead of a solid color (done)
+// - Sound effects for picking up candies
+// - Transparency for the candy sprite
+// - Queue inputs so they aren't skipped if the player is moving
+// - Animate a wiggle when the player tries to move off the grid (done)
+// - Show the recorded moves on the grid (maybe a path in a different color and offset for each soot?)
+
+// Time loop todo:
+// - Make score and fuel into components on the player (done)
+// - Review those changes, they felt a little awkward (done)
+// - Record the player's moves (done)
+// - Add state to track whether we're in the first or second loop (done)
+// - Add a system to replay the player's moves from the first loop (done)
+// - Add a second player entity to represent the past self, only present during the second loop (done)
+// - Have the past self replay the moves from the first loop instead of the player (done)
+// - Add state to track whose turn it is (player or past self should alternate) (done)
+// - Make player/past self only move when it's their turn (done)
+// - When one player has no more moves, skip their turn (done)
+// - Keep the same map for both loops (done)
+// - One recording per loop, not one recording for the whole game (done)
+// - Differentiate between next loop and next game (next loop - quick transition, no UI; next game - slow transition, show UI?)
+// - Any number of loops - keep going until all candy is collected
+// - Show the total collected candy across all soots in UI and at end of game
+
+/// The whole game, ready to run. Shared by the game binary and the soak test.
+pub fn app() -> App {
+    app_with(DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            // Only matters in the browser: the canvas follows the size of the page instead of a fixed resolution.
+            fit_canvas_to_parent: true,
+            ..default()
+        }),
+        ..default()
+    }))
+}
+
+/// The game on top of whichever of Bevy's plugins it's given, so the screenshot tests can run it without a window.
+fn app_with(default_plugins: PluginGroupBuilder) -> App {
+    let mut app = App::new();
+    app
+        .add_plugins(default_plugins)
+        .add_plugins(PersistencePlugin)
+        .add_plugins(SettingsPlugin)
+        .add_plugins(PalettePlugin)
+        .add_plugins(CosmeticsPlugin)
+        .add_plugins(ToastPlugin)
+        .add_plugins(TextInputPlugin)
+        .add_plugins(MainMenuPlugin)
+        .add_plugins(MenuBoardPlugin)
+        .add_plugins(SettingsMenuPlugin)
+        .add_plugins(CampaignPlugin)
+        .add_plugins(ShopPlugin)
+        .add_plugins(GridPlugin)
+        .add_plugins(InventoryPlugin)
+        .add_plugins(ScoringPlugin)
+        .add_plugins(MagnetPlugin)
+        .add_plugins(TimeCrystalPlugin)
+        .add_plugins(DoubleMovePlugin)
+        .add_plugins(TeleporterPlugin)
+        .add_plugins(IcePlugin)
+        .add_plugins(DoorsPlugin)
+        .add_plugins(GatesPlugin)
+        .add_plugins(ConveyorPlugin)
+        .add_plugins(HazardPlugin)
+        .add_plugins(EnemiesPlugin)
+        .add_plugins(CandyDecayPlugin)
+        .add_plugins(TimedCandyPlugin)
+        .add_plugins(UiPlugin)
+        .add_plugins(SootPanelPlugin)
+        .add_plugins(TurnIndicatorPlugin)
+        .add_plugins(MinimapPlugin)
+        .add_plugins(PickupFlightPlugin)
+        .add_plugins(ParticlesPlugin)
+        .add_plugins(FogPlugin)
+        .add_plugins(SpawnLevelPlugin)
+        .add_plugins(SpriteAnimationPlugin)
+        .add_plugins(GameOverScreenPlugin)
+        .add_plugins(LoopSummaryPlugin)
+        .add_plugins(HighScoresPlugin)
+        .add_plugins(AchievementsPlugin)
+        .add_plugins(TelemetryPlugin)
+        .add_plugins(WatchdogPlugin)
+        .add_plugins(CelebrationPlugin)
+        .add_plugins(MusicPlugin)
+        .add_plugins(MarkersPlugin)
+        .add_plugins(MovePreviewPlugin)
+        .add_plugins(HintsPlugin)
+        .add_plugins(DivergencePlugin)
+        .add_plugins(RecordedPathsPlugin)
+        .add_plugins(ReplayPlugin)
+        .add_plugins(RewindPlugin)
+        .add_plugins(ComparisonPlugin)
+        .add_plugins(TradingPlugin)
+        .add_plugins(UsableItemsPlugin)
+        .add_plugins(WigglePlugin)
+        .add_plugins(UndoPlugin)
+        .add_plugins(RunLogPlugin)
+        .add_plugins(IntentsPlugin)
+        .add_plugins(ClickToMovePlugin)
+        .add_plugins(TurnTimerPlugin)
+        .add_plugins(PaintingPlugin)
+        .add_plugins(EditorPlugin)
+        .add_plugins(PauseMenuPlugin)
+        .add_plugins(DebugOverlayPlugin)
+        .add_plugins(FocusPlugin)
+        .add_plugins(CameraControlsPlugin)
+        .add_plugins(CameraEffectsPlugin)
+        .add_state::<AppState>()
+        .add_systems(Startup, spawn_cam)
+        .add_systems(OnEnter(AppState::MainMenu), (despawn_after_playing, despawn_after_game_over, reset_run))
+        .insert_resource(Suspended(false))
+        .configure_set(OnEnter(AppState::Playing), StartLoop.run_if(not_suspended))
+        .add_systems(OnEnter(AppState::Playing), ((reset_move_buffer, reset_round_counter).in_set(StartLoop), resume.after(StartLoop)))
+        .configure_sets(Update, (ApplyGridMovement, PickUpItems, UpdateUi).chain())
+        .add_systems(Update,
+            (
+                (
+                    buffer_move_intents.after(ReadMoveIntents),
+                    (debuffer_move_inputs, replay_move_attempts).run_if(enemies_done),
+                    validate_move,
+                    (move_soot_on_grid, record_moves),
+                ).chain().before(ApplyGridMovement),
+                // Once ice, teleporters and conveyors have had their say about where the soot lands.
+                (apply_deferred, collide_soots).chain()
+                    .after(slide_on_ice).after(ride_conveyors).before(ApplyGridMovement),
+                (
+                    play_item_pickup_sound,
+                ).chain().after(PickUpItems),
+                next_turn,
+                detect_game_over,
+            ).chain().run_if(in_state(AppState::Playing)))
+        .insert_resource(MoveBuffer::default())
+        .add_event::<MoveAttempt>()
+        .add_event::<Move>()
+        .add_event::<MoveRejected>()
+        .insert_resource(Recordings::default())
+        .insert_resource(GameRules::from_args())
+        .insert_resource(LoopCounter(0))
+        .insert_resource(RoundCounter(0))
+        .insert_resource(CurrentSoot(SootId::Player))
+        .add_systems(OnExit(AppState::Playing), despawn_after_playing.run_if(not_suspended))
+        .add_systems(OnExit(AppState::LoopTransition), (despawn_after_game_over, swap_loop))
+        .add_systems(OnExit(AppState::GameOver), (despawn_after_game_over, swap_loop));
+
+    #[cfg(feature = "platform")]
+    app.add_plugins(platform::PlatformPlugin);
+
+    #[cfg(feature = "stream-overlay")]
+    app.add_plugins(stream_overlay::StreamOverlayPlugin);
+
+    app
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+enum AppState {
+    #[default]
+    MainMenu,
+    LevelSelect,
+    Playing,
+    /// The current loop is on hold behind the pause menu; its entities are left as they are.
+    Paused,
+    /// Between the loops of a run: a look back at the loop just played, before the next one starts.
+    LoopTransition,
+    GameOver,
+    /// Between runs: spend banked points on upgrades.
+    Shop,
+    Editor,
+    Settings,
+}
+
+/// OnEnter(AppState::Playing) setup for a fresh loop. Skipped when play resumes from the pause menu.
+#[derive(SystemSet, Hash, Debug, Clone, Eq, PartialEq)]
+struct StartLoop;
+
+/// Whether the loop in progress is paused. Entering and leaving Playing for the pause menu leaves the board alone.
+#[derive(Resource)]
+struct Suspended(bool);
+
+fn not_suspended(suspended: Res<Suspended>) -> bool {
+    !suspended.0
+}
+
+fn resume(mut suspended: ResMut<Suspended>) {
+    suspended.0 = false;
+}
+
+#[derive(Component, Clone, Copy)]
+struct DespawnOnExitPlaying;
+
+#[derive(Component, Clone, Copy)]
+struct DespawnOnExitGameOver;
+
+fn despawn_after_playing(mut commands: Commands, query: Query<Entity, With<DespawnOnExitPlaying>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn despawn_after_game_over(mut commands: Commands, query: Query<Entity, With<DespawnOnExitGameOver>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Where the camera sits to frame the whole board.
+fn board_center() -> Vec2 {
+    let max_grid_location = Vec2 {x: MAX_X as f32 - 1., y: MAX_Y as f32 - 1.};
+    let max_grid_pixel = max_grid_location * GRID_SPACING as f32;
+    max_grid_pixel/2.
+}
+
+fn spawn_cam(mut commands: Commands) {
+    commands.spawn(Camera2dBundle{
+        transform: Transform { translation: board_center().extend(0.), ..default() },
+        // Keeps at least the default window's worth of the world in view, so the board still fits in a small window
+        // or browser canvas.
+        projection: OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMin {min_width: VIEW_WIDTH, min_height: VIEW_HEIGHT},
+            near: -CAMERA_DEPTH,
+            far: CAMERA_DEPTH,
+            ..default()
+        },
+        ..default()
+    });
+}
+
+/// How far either side of z = 0 the cameras see. The background sits behind 0 and soots in front of it, so a camera
+/// at 0 has to look both ways or half the board gets culled.
+const CAMERA_DEPTH: f32 = 1000.;
+
+/// Matches the default window size, so the board looks the same as ever there.
+const VIEW_WIDTH: f32 = 1280.;
+const VIEW_HEIGHT: f32 = 720.;
+const MAX_X: i32 = 5;
+const MAX_Y: i32 = 5;
+const GRID_SPACING: i32 = 130;
+// Soots draw above the tiles, items and markers beneath them.
+const SOOT_Z: f32 = 1.;
+
+#[derive(Component)]
+struct Player;
+
+#[derive(Component, Clone, Copy)]
+struct SootSprite {
+    id: SootId,
+    /// Moves made so far this loop, which is what recordings are indexed by.
+    turn_number: i32,
+    /// Moves still to come in the current turn after this one, from a double move.
+    moves_left: i32,
+    /// Extra moves saved up for the soot's next turn.
+    bonus_moves: i32,
+}
+
+/// How many moves the player can type ahead of the animation, before upgrades. Presses past the limit are dropped.
+const MAX_BUFFERED_MOVES: usize = 4;
+
+#[derive(Resource)]
+struct MoveBuffer {
+    moves: VecDeque<IVec2>,
+    capacity: usize,
+}
+
+impl Default for MoveBuffer {
+    fn default() -> Self {
+        Self {moves: VecDeque::new(), capacity: MAX_BUFFERED_MOVES}
+    }
+}
+
+impl MoveBuffer {
+    /// Moves the player has entered but that haven't been attempted yet, oldest first.
+    fn pending_moves(&self) -> Vec<IVec2> {
+        self.moves.iter().copied().collect()
+    }
+
+    fn push(&mut self, offset: IVec2) {
+        if self.moves.len() < self.capacity {
+            self.moves.push_back(offset);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.moves.clear();
+    }
+}
+
+fn reset_move_buffer(mut move_buffer: ResMut<MoveBuffer>, upgrades: Res<Upgrades>) {
+    move_buffer.clear();
+    move_buffer.capacity = MAX_BUFFERED_MOVES + upgrades.extra_queued_moves as usize;
+}
+
+/// Queues up every single step asked for, from whichever input it came from.
+fn buffer_move_intents(mut intents: EventReader<MoveIntent>, mut move_buffer: ResMut<MoveBuffer>) {
+    for intent in intents.iter() {
+        if let MoveIntent::Step(offset) = intent {
+            move_buffer.push(*offset);
+        }
+    }
+}
+
+/// A move that stays put and just spends the turn.
+const WAIT: IVec2 = IVec2::ZERO;
+
+#[derive(Event)]
+struct MoveAttempt {
+    mover: Entity,
+    offset: IVec2,
+}
+
+fn debuffer_move_inputs(
+    player: Query<(Entity, &AnimateTranslation, &SootSprite), With<Player>>,
+    mut move_buffer: ResMut<MoveBuffer>,
+    mut event_writer: EventWriter<MoveAttempt>,
+    current_soot: Res<CurrentSoot>,
+    rules: Res<GameRules>,
+) {
+    if current_soot.0 != SootId::Player {
+        return;
+    }
+
+    let (player, animation, soot) = player.single();
+    if !animation.timer.finished() {
+        return;
+    }
+    if rules.out_of_turns(soot.turn_number) {
+        move_buffer.clear();
+        return;
+    }
+
+    let Some(offset) = move_buffer.moves.pop_front() else {
+        return;
+    };
+    event_writer.send(MoveAttempt{mover: player, offset});
+}
+
+fn replay_move_attempts(
+    soot_sprites: Query<(Entity, &AnimateTranslation, &SootSprite)>,
+    recordings: Res<Recordings>,
+    mut event_writer: EventWriter<MoveAttempt>,
+    mut skip_turn: EventWriter<MovementComplete>,
+    current_soot: Res<CurrentSoot>,
+) {
+    if current_soot.0 == SootId::Player {
+        return;
+    }
+
+    for (soot_entity, animation, soot) in soot_sprites.iter() {
+        if soot.id != current_soot.0 {
+            continue;
+        }
+
+        if !animation.timer.finished() {
+            continue;
+        }
+
+        if recordings.is_exhausted(soot) {
+            skip_turn.send(MovementComplete{entity: soot_entity});
+            continue;
+        }
+
+        if let Some(recording) = recordings.for_soot(soot.id) {
+            if let Some(&offset) = recording.moves.get(soot.turn_number as usize){
+                event_writer.send(MoveAttempt{mover:soot_entity, offset});
+            }
+        }
+    }
+}
+
+/// Sent when validate_move turns down a move attempt, whether it's off the board, into a wall, or more than the
+/// mover has fuel for.
+#[derive(Event)]
+struct MoveRejected {
+    mover: Entity,
+    offset: IVec2,
+}
+
+#[derive(Event)]
+struct Move {
+    mover: Entity,
+    /// Where the mover set off from; ice, teleporters and conveyors can take it further than one `offset` away.
+    from: IVec2,
+    offset: IVec2,
+    fuel_cost: i32,
+}
+
+/// Moving up or left burns one fuel per axis.
+fn fuel_cost(offset: IVec2, upgrades: &Upgrades) -> i32 {
+    let mut fuel_cost = 0;
+    if offset.x < 0 && !upgrades.free_leftward {
+        fuel_cost += 1;
+    }
+    if offset.y > 0 && !upgrades.free_uphill {
+        fuel_cost += 1;
+    }
+    fuel_cost
+}
+
+// Enemies go through here too. They carry nothing, so they have no fuel to spend and no keys for doors.
+fn validate_move(
+    movers: Query<(Entity, &GridLocation, Option<&Inventory>, Option<&SootSprite>)>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    rules: Res<GameRules>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    walls: Query<(), With<Wall>>,
+    doors: Query<(), With<Door>>,
+    upgrades: Res<Upgrades>,
+    mut move_buffer: ResMut<MoveBuffer>,
+    mut attempts: EventReader<MoveAttempt>,
+    mut moves: EventWriter<Move>,
+    mut rejections: EventWriter<MoveRejected>,
+    mut skip_turn: EventWriter<MovementComplete>,
+) {
+    if attempts.is_empty() {
+        return;
+    }
+
+    if attempts.len() > 1 {
+        panic!("Multiple move attempts in one frame!");
+    }
+
+    let &MoveAttempt{mover, offset} = attempts.iter().next().unwrap();
+    let (_, grid_location, inventory, soot) = movers.get(mover).unwrap();
+
+    let fuel_cost = inventory.map_or(0, |_| fuel_cost(offset, &upgrades));
+    let (fuel, keys) = inventory.map_or((0, 0), |inventory| (inventory.fuel, inventory.keys));
+    let next_pos = grid_location.0 + offset;
+    let soots = movers.iter().filter_map(|(entity, location, _, soot)| soot.map(|soot| (entity, location.0, soot.id)));
+    let blocked = fuel_cost > fuel
+        || next_pos.x < 0 || next_pos.x >= MAX_X || next_pos.y < 0 || next_pos.y >= MAX_Y
+        || cells.contents(next_pos, &children).iter()
+            .any(|&entity| walls.contains(entity) && !(doors.contains(entity) && keys > 0))
+        || soot.is_some() && offset != WAIT && soot_blocks(next_pos, mover, soots, &rules, &level_spec, &loop_counter);
+    if !blocked {
+        moves.send(Move{mover, from: grid_location.0, offset, fuel_cost});
+        return;
+    }
+    rejections.send(MoveRejected{mover, offset});
+
+    // A past self or an enemy just loses its turn. For the player, whatever was queued after this was planned from a
+    // cell they never reached, so drop it too.
+    if soot.is_some_and(|soot| soot.id == SootId::Player) {
+        move_buffer.clear();
+    } else {
+        skip_turn.send(MovementComplete{entity: mover});
+    }
+}
+
+/// The soot standing on `cell`, besides `mover`, that a soot moving there would run into. Soots that have reached
+/// their exit are done and out of the way.
+fn soot_in_the_way(
+    cell: IVec2,
+    mover: Entity,
+    soots: impl IntoIterator<Item = (Entity, IVec2, SootId)>,
+    level_spec: &LevelSpec,
+    loop_counter: &LoopCounter,
+) -> Option<Entity> {
+    soots.into_iter()
+        .find(|&(entity, location, soot)| entity != mover
+            && location == cell
+            && location != level_spec.end_for_loop(loop_counter.0 - soot.loop_number()))
+        .map(|(entity, _, _)| entity)
+}
+
+/// Whether a soot moving on to `cell` runs into another one there and has to stop short, under rules where soots block
+/// each other. validate_move asks about the first step; ice, teleporters and conveyors ask before carrying a soot on.
+fn soot_blocks(
+    cell: IVec2,
+    mover: Entity,
+    soots: impl IntoIterator<Item = (Entity, IVec2, SootId)>,
+    rules: &GameRules,
+    level_spec: &LevelSpec,
+    loop_counter: &LoopCounter,
+) -> bool {
+    rules.collisions == Collisions::Block && soot_in_the_way(cell, mover, soots, level_spec, loop_counter).is_some()
+}
+
+/// Settles a soot moving onto another one, for the rules that let it. Blocking is already taken care of by
+/// validate_move and whatever carried the soot on from there.
+fn collide_soots(
+    mut moves: EventReader<Move>,
+    rules: Res<GameRules>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    mut soots: Query<(Entity, &mut GridLocation, &mut Inventory, &SootSprite, &mut Transform, &mut AnimateTranslation)>,
+    follow_ups: Query<&FollowUpMove>,
+) {
+    for &Move{mover, from, ..} in moves.iter().filter(|event| event.offset != WAIT) {
+        let Ok((_, location, _, _, _, _)) = soots.get(mover) else {
+            continue;
+        };
+        // A teleporter or conveyor taking the soot on is where it really ends up.
+        let cell = follow_ups.get(mover).map_or(location.0, |follow_up| follow_up.0);
+        let locations = soots.iter().map(|(entity, location, _, soot, _, _)| (entity, location.0, soot.id));
+        let Some(other) = soot_in_the_way(cell, mover, locations, &level_spec, &loop_counter) else {
+            continue;
+        };
+        match rules.collisions {
+            Collisions::PassThrough | Collisions::Block => {},
+            Collisions::Swap => {
+                // Straight back to where the mover came from; animating it would count as the other soot's turn.
+                let (_, mut other_location, _, _, mut transform, mut animation) = soots.get_mut(other).unwrap();
+                other_location.bypass_change_detection().0 = from;
+                let position = (other_location.0 * GRID_SPACING).as_vec2();
+                transform.translation = position.extend(transform.translation.z);
+                animation.end = position;
+            },
+            Collisions::Paradox(penalty) => {
+                let (_, _, mut inventory, _, _, _) = soots.get_mut(mover).unwrap();
+                inventory.points = (inventory.points - penalty).max(0);
+            },
+        }
+    }
+}
+
+fn move_soot_on_grid(
+    mut movers: Query<(&mut GridLocation, Option<&mut Inventory>, Option<&mut Facing>)>,
+    mut events: EventReader<Move>,
+) {
+    if events.is_empty() {
+        return;
+    }
+
+    if events.len() > 1 {
+        panic!("Multiple moves in one frame!");
+    }
+
+    let &Move{mover, offset, fuel_cost, ..} = events.iter().next().unwrap();
+    let (mut grid_location, inventory, facing) = movers.get_mut(mover).unwrap();
+    grid_location.0 += offset;
+    if let Some(mut facing) = facing {
+        *facing = facing.after_move(offset);
+    }
+
+    if let Some(mut inventory) = inventory.filter(|_| fuel_cost > 0) {
+        inventory.fuel -= fuel_cost;
+    }
+}
+
+/// Everything one soot did during one loop. Replays index into it by turn number and never consume it, so a
+/// recording can be replayed again in every later loop.
+#[derive(Default, Debug, Clone)]
+struct Recording {
+    moves: Vec<IVec2>,
+    trades: Vec<Trade>,
+    item_uses: Vec<ItemUse>,
+}
+
+/// One recording per loop, indexed by loop number: index 0 is the loop the player is recording right now, and index
+/// n is what `SootId::Recording(n)` replays.
+#[derive(Resource)]
+struct Recordings(Vec<Recording>);
+
+impl Default for Recordings {
+    fn default() -> Self {
+        Self(vec![Recording::default()])
+    }
+}
+
+impl Recordings {
+    fn current_mut(&mut self) -> &mut Recording {
+        &mut self.0[0]
+    }
+
+    fn for_soot(&self, soot_id: SootId) -> Option<&Recording> {
+        self.0.get(soot_id.loop_number() as usize)
+    }
+
+    /// Shifts every recording back a loop and starts a fresh one for the player.
+    fn start_next_loop(&mut self) {
+        self.0.insert(0, Recording::default());
+    }
+
+    /// Whether a past self has already replayed every move it recorded. The player never runs out.
+    fn is_exhausted(&self, soot: &SootSprite) -> bool {
+        match soot.id {
+            SootId::Player => false,
+            SootId::Recording(_) => self.for_soot(soot.id)
+                .is_none_or(|recording| soot.turn_number as usize >= recording.moves.len()),
+        }
+    }
+}
+
+fn record_moves(
+    mut recordings: ResMut<Recordings>,
+    mut events: EventReader<Move>,
+    player: Query<(), With<Player>>,
+) {
+    for event in events.iter().filter(|event| player.contains(event.mover)) {
+        recordings.current_mut().moves.push(event.offset);
+    }
+}
+
+fn detect_game_over(
+    soots: Query<(&SootSprite, &GridLocation, &AnimateTranslation)>,
+    recordings: Res<Recordings>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    rules: Res<GameRules>,
+    upgrades: Res<Upgrades>,
+    candy_remaining: Res<CandyRemaining>,
+    candy_collected: Res<CandyCollected>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    let exits_open = rules.exits_open(candy_collected.0, candy_remaining.0);
+    for (soot, soot_location, animation) in soots.iter() {
+        if !animation.timer.finished() {
+            return;
+        }
+
+        // A past self knocked off its route can run out of moves short of the exit; it's done either way. So is
+        // anyone who has used up the level's turn limit. A locked exit is just another cell until the quota's met.
+        let done = (exits_open && soot_location.0 == level_spec.end_for_loop(loop_counter.0 - soot.id.loop_number()))
+            || recordings.is_exhausted(soot)
+            || rules.out_of_turns(soot.turn_number);
+        if !done {
+            return;
+        }
+    }
+
+    // Only the last loop ends the run; the ones before it just hand over to the next.
+    if loop_counter.is_final_loop(&rules, &upgrades) {
+        app_state.set(AppState::GameOver);
+    } else {
+        app_state.set(AppState::LoopTransition);
+    }
+}
+
+#[derive(Resource)]
+struct LoopCounter(i32);
+
+/// Rounds of turns taken this loop. A round ends each time the turn comes back around to the earliest soot still
+/// moving, so every soot makes its nth move in the nth round whichever loop it's replayed in. Anything on the board
+/// that moves by itself, like a patrolling enemy, goes in between rounds.
+#[derive(Resource)]
+struct RoundCounter(i32);
+
+fn reset_round_counter(mut round_counter: ResMut<RoundCounter>) {
+    round_counter.0 = 0;
+}
+
+#[derive(Resource)]
+struct CurrentSoot(SootId);
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum SootId {
+    Player,
+    Recording(i32),
+}
+impl SootId {
+    fn loop_number(&self) -> i32 {
+        match self {
+            SootId::Player => 0,
+            SootId::Recording(loop_number) => *loop_number,
+        }
+    }
+}
+impl From<i32> for SootId {
+    fn from(loop_number: i32) -> Self {
+        match loop_number {
+            0 => Self::Player,
+            loop_number => Self::Recording(loop_number),
+        }
+    }
+}
+
+const NUM_LOOPS: i32 = 3;
+
+/// Throws away a run left partway through, e.g. on going back to the main menu.
+fn reset_run(
+    mut loop_counter: ResMut<LoopCounter>,
+    mut recordings: ResMut<Recordings>,
+    mut current_soot: ResMut<CurrentSoot>,
+) {
+    loop_counter.0 = 0;
+    *recordings = Recordings::default();
+    current_soot.0 = SootId::Player;
+}
+
+fn swap_loop(
+    mut loop_counter: ResMut<LoopCounter>,
+    mut recordings: ResMut<Recordings>,
+    rules: Res<GameRules>,
+    upgrades: Res<Upgrades>,
+) {
+    debug!("Moves recorded: {:?}", recordings.0.iter().map(|recording| &recording.moves).collect::<Vec<_>>());
+    if loop_counter.is_final_loop(&rules, &upgrades) {
+        loop_counter.0 = 0;
+        *recordings = Recordings::default();
+    } else {
+        loop_counter.0 += 1;
+        recordings.start_next_loop();
+    }
+}
+
+fn next_turn(
+    mut current_soot: ResMut<CurrentSoot>,
+    mut round_counter: ResMut<RoundCounter>,
+    loop_counter: Res<LoopCounter>,
+    recordings: Res<Recordings>,
+    level_spec: Res<LevelSpec>,
+    settings: Res<Settings>,
+    rules: Res<GameRules>,
+    candy_remaining: Res<CandyRemaining>,
+    candy_collected: Res<CandyCollected>,
+    mut soots: Query<(&mut SootSprite, &GridLocation)>,
+    mut movement_events: EventReader<MovementComplete>,
+) {
+    // Enemies take their turns in between rounds, outside the soots' turn order.
+    let completions: Vec<Entity> = movement_events.iter()
+        .map(|event| event.entity)
+        .filter(|&entity| soots.contains(entity))
+        .collect();
+    let Some(&entity) = completions.first() else {
+        return;
+    };
+
+    if completions.len() > 1 {
+        panic!("Multiple movement events in one frame!");
+    }
+
+    // Validate that the correct entity just moved.
+    let (mut soot_sprite, _) = soots.get_mut(entity).unwrap();
+    if soot_sprite.id != current_soot.0 {
+        panic!("Wrong entity moved! Expected loop {:?}, got loop {:?}.", current_soot.0, soot_sprite.id);
+    }
+
+    soot_sprite.turn_number += 1;
+    // A double move keeps the turn for another step.
+    let another_move = soot_sprite.moves_left > 0;
+    if another_move {
+        soot_sprite.moves_left -= 1;
+    }
+
+    let exits_open = rules.exits_open(candy_collected.0, candy_remaining.0);
+    let can_move = |soot_id: SootId| {
+        for (soot_sprite, grid_location) in soots.iter() {
+            if soot_sprite.id != soot_id {
+                continue;
+            }
+            if exits_open && grid_location.0 == level_spec.end_for_loop(loop_counter.0 - soot_id.loop_number()) {
+                return false;
+            }
+            if settings.auto_resolve_idle_turns && recordings.is_exhausted(soot_sprite) {
+                return false;
+            }
+            if rules.out_of_turns(soot_sprite.turn_number) {
+                return false;
+            }
+        }
+        true
+    };
+
+    if another_move && can_move(current_soot.0) {
+        return;
+    }
+
+    let num_loops = loop_counter.0 + 1;
+    for turn_increment in 1..=num_loops {
+        let next_soot: SootId = ((current_soot.0.loop_number() + turn_increment) % num_loops).into();
+        if !can_move(next_soot) {
+            continue;
+        }
+        if next_soot.loop_number() <= current_soot.0.loop_number() {
+            round_counter.0 += 1;
+        }
+        current_soot.0 = next_soot;
+        // Anything saved up for this turn gets spent now.
+        for (mut soot_sprite, _) in soots.iter_mut().filter(|(soot_sprite, _)| soot_sprite.id == next_soot) {
+            soot_sprite.moves_left = soot_sprite.bonus_moves;
+            soot_sprite.bonus_moves = 0;
+        }
+        return;
+    }
+
+    // This case will happen if nobody can move; prepares us for next loop.
+    current_soot.0 = SootId::Player;
+}
+
+fn play_item_pickup_sound(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut event_reader: EventReader<ItemGet>)
+{
+    for event in event_reader.iter() {
+        let sound = event.item.pickup_sound();
+        commands.spawn(AudioBundle{
+            source: asset_server.load(sound.file),
+            settings: PlaybackSettings {
+                speed: sound.speed,
+                volume: Volume::new_relative(sound.volume),
+                // Otherwise every pickup leaves a finished sound entity behind.
+                ..PlaybackSettings::DESPAWN
+            },
+        });
+    }
+}
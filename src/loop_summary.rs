@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+use crate::{AppState, LoopCounter, Player, SootSprite};
+use crate::inventory::Inventory;
+
+/// How long the summary stays up before the next loop starts on its own.
+const SUMMARY_SECS: f32 = 3.;
+
+pub struct LoopSummaryPlugin;
+
+impl Plugin for LoopSummaryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::LoopTransition), spawn_loop_summary)
+            .add_systems(Update, start_next_loop.run_if(in_state(AppState::LoopTransition)))
+            .add_systems(OnExit(AppState::LoopTransition), despawn_loop_summary);
+    }
+}
+
+#[derive(Component)]
+struct LoopSummary(Timer);
+
+// The soots are still around until the next loop starts, so the summary reads straight off the player.
+fn spawn_loop_summary(
+    mut commands: Commands,
+    loop_counter: Res<LoopCounter>,
+    player: Query<(&Inventory, &SootSprite), With<Player>>,
+) {
+    let (points, fuel, moves) = player.get_single()
+        .map_or((0, 0, 0), |(inventory, soot)| (inventory.points, inventory.fuel, soot.turn_number));
+    commands.spawn((
+        LoopSummary(Timer::from_seconds(SUMMARY_SECS, TimerMode::Once)),
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.7).into(),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            format!("Loop {} done!", loop_counter.0 + 1),
+            TextStyle {font_size: 50., ..default()}));
+        for line in [
+            format!("Candy this loop: {} points", points),
+            format!("Fuel unused: {}", fuel),
+            format!("Moves taken: {}", moves),
+        ] {
+            parent.spawn(TextBundle::from_section(line, TextStyle {font_size: 40., ..default()}));
+        }
+        parent.spawn(TextBundle::from_section("Press any key to go on", TextStyle {font_size: 30., ..default()}));
+    });
+}
+
+fn start_next_loop(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut summaries: Query<&mut LoopSummary>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let skipped = keyboard_input.get_just_pressed().next().is_some() || mouse_input.get_just_pressed().next().is_some();
+    for mut summary in summaries.iter_mut() {
+        if summary.0.tick(time.delta()).finished() || skipped {
+            next_state.set(AppState::Playing);
+        }
+    }
+}
+
+fn despawn_loop_summary(mut commands: Commands, summaries: Query<Entity, With<LoopSummary>>) {
+    for entity in summaries.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
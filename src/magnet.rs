@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+
+use crate::{move_soot_on_grid, AppState, Move};
+use crate::inventory::{Item, ItemGet, PickUpItems};
+
+/// Moves a magnet keeps pulling for once picked up.
+const MAGNET_TURNS: i32 = 5;
+
+pub struct MagnetPlugin;
+
+impl Plugin for MagnetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            wear_off_magnets.after(move_soot_on_grid).before(PickUpItems),
+            magnetize.after(PickUpItems),
+        ).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Picks up candy from the four cells around the soot as well as its own, for `turns_left` more moves after this
+/// one.
+#[derive(Component, Clone, Copy)]
+pub struct Magnetized {
+    pub turns_left: i32,
+}
+
+// Another magnet on top of one still going just adds its turns.
+fn magnetize(mut commands: Commands, mut pickups: EventReader<ItemGet>, mut soots: Query<Option<&mut Magnetized>>) {
+    for pickup in pickups.iter().filter(|pickup| matches!(pickup.item, Item::Magnet)) {
+        match soots.get_mut(pickup.soot) {
+            Ok(Some(mut magnetized)) => magnetized.turns_left += MAGNET_TURNS,
+            Ok(None) => {
+                commands.entity(pickup.soot).insert(Magnetized {turns_left: MAGNET_TURNS});
+            },
+            Err(_) => {},
+        }
+    }
+}
+
+fn wear_off_magnets(mut commands: Commands, mut moves: EventReader<Move>, mut soots: Query<&mut Magnetized>) {
+    for event in moves.iter() {
+        let Ok(mut magnetized) = soots.get_mut(event.mover) else {
+            continue;
+        };
+        if magnetized.turns_left == 0 {
+            commands.entity(event.mover).remove::<Magnetized>();
+        } else {
+            magnetized.turns_left -= 1;
+        }
+    }
+}
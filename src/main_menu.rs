@@ -0,0 +1,174 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::AppState;
+use crate::campaign::CurrentCampaignLevel;
+use crate::persistence::switch_profile;
+use crate::profile::Profile;
+use crate::replay::{Replay, ReplayGhost};
+use crate::rules::GameRules;
+use crate::settings::Settings;
+use crate::spawn_level::{LevelSeed, LevelSource};
+use crate::text_input::{spawn_text_input, TextInput};
+use crate::toast::Toast;
+use crate::ui::spawn_button;
+
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::MainMenu), (spawn_main_menu, apply_deferred, update_profile_labels).chain())
+            .add_systems(Update, (
+                press_menu_buttons,
+                update_profile_labels.run_if(resource_changed::<Profile>()),
+            ).chain().run_if(in_state(AppState::MainMenu)))
+            .add_systems(OnExit(AppState::MainMenu), despawn_main_menu);
+    }
+}
+
+#[derive(Component)]
+struct MainMenu;
+
+/// Pins the seed for Play; left empty, every run gets a fresh board.
+#[derive(Component)]
+struct SeedInput;
+
+/// Path of a replay file to watch.
+#[derive(Component)]
+struct ReplayInput;
+
+#[derive(Component)]
+pub enum MenuButton {
+    Play,
+    Campaign,
+    WatchReplay,
+    Settings,
+    Quit,
+    Profile,
+    Hat,
+    Color,
+}
+
+fn spawn_main_menu(mut commands: Commands) {
+    commands.spawn((
+        MainMenu,
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+            // See-through, so the board playing itself behind the menu shows.
+            background_color: Color::rgba(0.1, 0.08, 0.12, 0.75).into(),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("Interference Factory", TextStyle {font_size: 80., ..default()}));
+        spawn_text_input(parent, "Seed (optional)", 20, |c| c.is_ascii_digit(), SeedInput);
+        spawn_button(parent, "Play", MenuButton::Play);
+        spawn_button(parent, "Campaign", MenuButton::Campaign);
+        spawn_text_input(parent, "Replay file", 260, |_| true, ReplayInput);
+        spawn_button(parent, "Watch replay", MenuButton::WatchReplay);
+        spawn_button(parent, "Settings", MenuButton::Settings);
+        // Labels are filled in by update_profile_labels.
+        spawn_button(parent, "", MenuButton::Profile);
+        spawn_button(parent, "", MenuButton::Hat);
+        spawn_button(parent, "", MenuButton::Color);
+        spawn_button(parent, "Quit", MenuButton::Quit);
+    });
+}
+
+fn despawn_main_menu(mut commands: Commands, query: Query<Entity, With<MainMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn press_menu_buttons(
+    interactions: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut profile: ResMut<Profile>,
+    mut settings: ResMut<Settings>,
+    seed_input: Query<&TextInput, With<SeedInput>>,
+    replay_input: Query<&TextInput, With<ReplayInput>>,
+    mut level_seed: ResMut<LevelSeed>,
+    mut level_source: ResMut<LevelSource>,
+    mut rules: ResMut<GameRules>,
+    mut replay_ghost: ResMut<ReplayGhost>,
+    mut current_level: ResMut<CurrentCampaignLevel>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit: EventWriter<AppExit>,
+    mut toasts: EventWriter<Toast>,
+) {
+    for (interaction, button) in interactions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            // A random board, or whatever was asked for on the command line or typed in.
+            MenuButton::Play => {
+                *level_source = LevelSource::from_args();
+                *level_seed = match seed_input.get_single().ok().and_then(|input| input.value.parse().ok()) {
+                    Some(seed) => LevelSeed::pinned(seed),
+                    None => LevelSeed::from_args(),
+                };
+                rules.board_transform = GameRules::from_args().board_transform;
+                current_level.0 = None;
+                next_state.set(AppState::Playing);
+            },
+            MenuButton::Campaign => next_state.set(AppState::LevelSelect),
+            MenuButton::WatchReplay => {
+                let Ok(input) = replay_input.get_single() else {
+                    continue;
+                };
+                match Replay::load(input.value.trim().as_ref()) {
+                    Ok(replay) => {
+                        replay.select_level(&mut level_source, &mut level_seed, &mut rules);
+                        replay_ghost.0 = Some(replay);
+                        current_level.0 = None;
+                        next_state.set(AppState::Playing);
+                    },
+                    Err(err) => {
+                        warn!("Couldn't load replay: {}", err);
+                        toasts.send(Toast::warning(format!("Couldn't load replay: {}", err)));
+                    },
+                }
+            },
+            MenuButton::Settings => next_state.set(AppState::Settings),
+            MenuButton::Quit => exit.send(AppExit),
+            MenuButton::Profile => {
+                if let Err(err) = switch_profile(&mut profile, &mut settings) {
+                    warn!("Couldn't switch profile: {}", err);
+                    toasts.send(Toast::warning(format!("Couldn't switch profile: {}", err)));
+                }
+            },
+            MenuButton::Hat => profile.cycle_hat(),
+            MenuButton::Color => profile.cycle_color(),
+        }
+    }
+}
+
+fn update_profile_labels(
+    profile: Res<Profile>,
+    settings: Res<Settings>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut labels: Query<&mut Text>,
+) {
+    for (button, children) in buttons.iter() {
+        let label = match button {
+            MenuButton::Profile => format!("Profile: Player {}", settings.profile_slot + 1),
+            MenuButton::Hat => format!("Hat: {}", profile.hat.map_or("none", |hat| hat.name())),
+            MenuButton::Color => format!("Color: {}", profile.color.map_or("plain", |color| color.name())),
+            _ => continue,
+        };
+        for &child in children.iter() {
+            if let Ok(mut text) = labels.get_mut(child) {
+                text.sections[0].value = label.clone();
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::{AppState, StartLoop, DespawnOnExitGameOver, LoopCounter, GRID_SPACING};
+use crate::grid::cursor_grid_location;
+use crate::spawn_level::SpawnLevel;
+
+/// Above the tiles and items, below the soots.
+const MARKER_Z: f32 = 0.5;
+const MARKER_COLOR: Color = Color::rgb(0.3, 0.8, 1.);
+const MAX_MARKER_NUMBER: u8 = 3;
+
+pub struct MarkersPlugin;
+
+impl Plugin for MarkersPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Markers::default())
+            .add_systems(OnEnter(AppState::Playing), (clear_markers_for_new_run, render_markers).chain().after(SpawnLevel).in_set(StartLoop))
+            .add_systems(Update, (
+                place_marker,
+                render_markers.run_if(resource_changed::<Markers>()),
+            ).chain().run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Planning aids the player drops on cells. They last for the whole run, across every loop.
+#[derive(Resource, Default)]
+pub struct Markers(pub HashMap<IVec2, Marker>);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Marker {
+    Flag,
+    Number(u8),
+}
+
+impl Marker {
+    /// Right-clicking a cell steps through flag, 1, 2, 3, then clears it.
+    fn next(marker: Option<Marker>) -> Option<Marker> {
+        match marker {
+            None => Some(Marker::Flag),
+            Some(Marker::Flag) => Some(Marker::Number(1)),
+            Some(Marker::Number(n)) if n < MAX_MARKER_NUMBER => Some(Marker::Number(n + 1)),
+            Some(Marker::Number(_)) => None,
+        }
+    }
+}
+
+#[derive(Component)]
+struct MarkerSprite;
+
+fn clear_markers_for_new_run(mut markers: ResMut<Markers>, loop_counter: Res<LoopCounter>) {
+    if loop_counter.0 == 0 {
+        markers.0.clear();
+    }
+}
+
+fn place_marker(
+    mouse: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut markers: ResMut<Markers>,
+) {
+    if !mouse.just_pressed(MouseButton::Right) {
+        return;
+    }
+
+    let (camera, camera_transform) = cameras.single();
+    let Some(cell) = cursor_grid_location(windows.single(), camera, camera_transform) else {
+        return;
+    };
+
+    match Marker::next(markers.0.get(&cell.0).copied()) {
+        Some(marker) => markers.0.insert(cell.0, marker),
+        None => markers.0.remove(&cell.0),
+    };
+}
+
+fn render_markers(mut commands: Commands, markers: Res<Markers>, sprites: Query<Entity, With<MarkerSprite>>) {
+    for entity in sprites.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (&cell, &marker) in markers.0.iter() {
+        // Tucked into the top-left corner so items in the cell stay visible.
+        let corner = (cell * GRID_SPACING).as_vec2() + Vec2::new(-1., 1.) * GRID_SPACING as f32 * 0.3;
+        let transform = Transform::from_translation(corner.extend(MARKER_Z));
+        match marker {
+            Marker::Flag => {
+                commands.spawn((
+                    MarkerSprite,
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: MARKER_COLOR,
+                            custom_size: Some(Vec2::new(20., 14.)),
+                            ..default()
+                        },
+                        transform,
+                        ..default()
+                    },
+                    DespawnOnExitGameOver,
+                ));
+            },
+            Marker::Number(n) => {
+                commands.spawn((
+                    MarkerSprite,
+                    Text2dBundle {
+                        text: Text::from_section(n.to_string(), TextStyle {font_size: 36., color: MARKER_COLOR, ..default()}),
+                        transform,
+                        ..default()
+                    },
+                    DespawnOnExitGameOver,
+                ));
+            },
+        }
+    }
+}
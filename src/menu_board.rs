@@ -0,0 +1,181 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::render::view::RenderLayers;
+use rand::Rng;
+
+use crate::{board_center, AppState, CAMERA_DEPTH, DespawnOnExitGameOver, SootId, SootSprite, SOOT_Z, VIEW_HEIGHT, VIEW_WIDTH};
+use crate::grid::{ApplyGridMovement, GridCells, GridLocation, MovementComplete, SnapToGrid};
+use crate::inventory::Item;
+use crate::level_spec::LevelSpec;
+use crate::settings::Settings;
+use crate::shop::Upgrades;
+use crate::solver::best_route;
+use crate::spawn_level::spawn_board;
+use crate::sprite_animation::{soot_sprite_bundle, Facing, SootSheet};
+
+/// Kept apart from the game's own camera, which only sees the default layer.
+const MENU_BOARD_LAYER: u8 = 1;
+/// How much further out than the game the board is seen from, so it sits small behind the buttons.
+const MENU_BOARD_ZOOM: f32 = 1.6;
+const SECS_PER_STEP: f32 = 0.6;
+/// How long a finished board stays up before the next one.
+const FINISHED_SECS: f32 = 2.;
+
+pub struct MenuBoardPlugin;
+
+impl Plugin for MenuBoardPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::MainMenu), spawn_menu_camera)
+            // Boards are put up in Update rather than on entering, so the entity count the menu is entered with doesn't
+            // depend on how big the last board was.
+            .add_systems(Update, (
+                spawn_menu_board.run_if(no_menu_board),
+                keep_on_menu_layer,
+                play_menu_board.after(ApplyGridMovement),
+            ).chain().run_if(in_state(AppState::MainMenu)))
+            .add_systems(OnExit(AppState::MainMenu), despawn_menu_board);
+    }
+}
+
+/// The camera the board behind the main menu is seen through.
+#[derive(Component)]
+struct MenuBoardCamera;
+
+/// Plays the best route for its board, one move at a time, the way a lone soot on a fresh run could.
+#[derive(Component)]
+struct MenuBot {
+    moves: Vec<IVec2>,
+    step: usize,
+    timer: Timer,
+}
+
+// The game's camera draws after this one, so it stops clearing the screen while the menu is up or the board would be
+// wiped before it's seen.
+fn spawn_menu_camera(mut commands: Commands, mut cameras: Query<&mut Camera2d, Without<MenuBoardCamera>>) {
+    for mut camera in cameras.iter_mut() {
+        camera.clear_color = ClearColorConfig::None;
+    }
+    commands.spawn((
+        MenuBoardCamera,
+        Camera2dBundle {
+            camera: Camera {order: -1, ..default()},
+            transform: Transform::from_translation(board_center().extend(0.)),
+            projection: OrthographicProjection {
+                scaling_mode: ScalingMode::AutoMin {
+                    min_width: VIEW_WIDTH * MENU_BOARD_ZOOM,
+                    min_height: VIEW_HEIGHT * MENU_BOARD_ZOOM,
+                },
+                near: -CAMERA_DEPTH,
+                far: CAMERA_DEPTH,
+                ..default()
+            },
+            ..default()
+        },
+        // The menu itself is drawn by the game's camera, on top.
+        UiCameraConfig {show_ui: false},
+        RenderLayers::layer(MENU_BOARD_LAYER),
+    ));
+}
+
+fn no_menu_board(bots: Query<(), With<MenuBot>>) -> bool {
+    bots.is_empty()
+}
+
+// Just the plain pieces: candy, fuel and walls, laid out by the same systems as the game's own board. Nothing's
+// played in the menu, so the level spec is free to borrow until a run generates its own.
+fn spawn_menu_board(world: &mut World) {
+    // Only the one soot ever plays it.
+    let generated = LevelSpec::generate(rand::thread_rng().gen(), 1);
+    let level_spec = LevelSpec {
+        candies: generated.candies,
+        fuel: generated.fuel,
+        walls: generated.walls,
+        ..default()
+    };
+    let upgrades = Upgrades::default();
+    let (moves, _) = best_route(&level_spec, level_spec.start, upgrades.starting_fuel, &upgrades);
+    let start = level_spec.start;
+    world.insert_resource(level_spec);
+    spawn_board(world);
+
+    let soot = soot_sprite_bundle(world.resource::<SootSheet>(), Color::WHITE, Transform::from_xyz(0., 0., SOOT_Z));
+    let animation = world.resource::<Settings>().animation_speed.animate_translation();
+    world.spawn((
+        MenuBot {moves, step: 0, timer: Timer::from_seconds(SECS_PER_STEP, TimerMode::Repeating)},
+        SootSprite {id: SootId::Player, turn_number: 0, moves_left: 0, bonus_moves: 0},
+        GridLocation(start),
+        soot,
+        SnapToGrid,
+        animation,
+        DespawnOnExitGameOver,
+    ));
+}
+
+// Everything on the board, including whatever's parented to it after it's spawned, is only for the menu's camera.
+fn keep_on_menu_layer(
+    mut commands: Commands,
+    board: Query<Entity, (With<DespawnOnExitGameOver>, Without<Parent>)>,
+    children: Query<&Children>,
+    layered: Query<(), With<RenderLayers>>,
+) {
+    for root in board.iter() {
+        for entity in std::iter::once(root).chain(children.iter_descendants(root)) {
+            if !layered.contains(entity) {
+                commands.entity(entity).insert(RenderLayers::layer(MENU_BOARD_LAYER));
+            }
+        }
+    }
+}
+
+// Moves go through the grid like the game's do; the bot just doesn't need them checked, and takes the candy and fuel
+// it lands on straight off the board.
+fn play_menu_board(
+    mut commands: Commands,
+    time: Res<Time>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    items: Query<(), With<Item>>,
+    mut landings: EventReader<MovementComplete>,
+    mut bots: Query<(Entity, &mut MenuBot, &mut GridLocation, &mut Facing)>,
+    board: Query<Entity, With<DespawnOnExitGameOver>>,
+) {
+    let landed: Vec<Entity> = landings.iter().map(|landing| landing.entity).collect();
+    for (entity, mut bot, mut location, mut facing) in bots.iter_mut() {
+        if landed.contains(&entity) {
+            for &item in cells.contents(location.0, &children).iter().filter(|&&child| items.contains(child)) {
+                commands.entity(item).despawn_recursive();
+            }
+        }
+        if !bot.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        if let Some(&offset) = bot.moves.get(bot.step) {
+            location.0 += offset;
+            *facing = facing.after_move(offset);
+        }
+        bot.step += 1;
+        let finished_steps = (FINISHED_SECS / SECS_PER_STEP).ceil() as usize;
+        if bot.step > bot.moves.len() + finished_steps {
+            for entity in board.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            return;
+        }
+    }
+}
+
+fn despawn_menu_board(
+    mut commands: Commands,
+    boards: Query<Entity, Or<(With<MenuBoardCamera>, With<DespawnOnExitGameOver>)>>,
+    mut cameras: Query<&mut Camera2d>,
+) {
+    for entity in boards.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for mut camera in cameras.iter_mut() {
+        camera.clear_color = ClearColorConfig::Default;
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{AppState, DespawnOnExitPlaying, SootId, SootSprite, StartLoop, MAX_X, MAX_Y};
+use crate::fog::{fogged, FOG_COLOR};
+use crate::grid::{GridCell, GridLocation};
+use crate::hazard::HazardHit;
+use crate::inventory::Item;
+use crate::palette::Palette;
+use crate::rules::GameRules;
+use crate::settings::Settings;
+use crate::spawn_level::Wall;
+use crate::ui::UpdateUi;
+
+const DOT_SIZE: f32 = 14.;
+const DOT_GAP: f32 = 2.;
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Playing), spawn_minimap.in_set(StartLoop))
+            .add_systems(Update, (show_minimap, update_minimap).in_set(UpdateUi).run_if(in_state(AppState::Playing)));
+    }
+}
+
+#[derive(Component)]
+struct Minimap;
+
+/// One dot on the minimap, standing for the board cell it holds.
+#[derive(Component)]
+struct MinimapDot(IVec2);
+
+fn spawn_minimap(mut commands: Commands, palette: Res<Palette>) {
+    commands.spawn((
+        Minimap,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(20.),
+                bottom: Val::Px(20.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(DOT_GAP),
+                padding: UiRect::all(Val::Px(DOT_GAP * 2.)),
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..default()
+        },
+        DespawnOnExitPlaying,
+    )).with_children(|parent| {
+        // Top row first, the way the board is drawn.
+        for y in (0..MAX_Y).rev() {
+            parent.spawn(NodeBundle {
+                style: Style {column_gap: Val::Px(DOT_GAP), ..default()},
+                ..default()
+            }).with_children(|parent| {
+                for x in 0..MAX_X {
+                    parent.spawn((
+                        MinimapDot(IVec2::new(x, y)),
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Px(DOT_SIZE),
+                                height: Val::Px(DOT_SIZE),
+                                ..default()
+                            },
+                            background_color: palette.tile.into(),
+                            ..default()
+                        },
+                    ));
+                }
+            });
+        }
+    });
+}
+
+// The whole board fits on screen unless the camera is following a soot, so that's the only time it's worth the space.
+fn show_minimap(settings: Res<Settings>, mut minimaps: Query<(Ref<Minimap>, &mut Visibility)>) {
+    for (minimap, mut visibility) in minimaps.iter_mut() {
+        if settings.is_changed() || minimap.is_added() {
+            *visibility = if settings.focus_mode { Visibility::Inherited } else { Visibility::Hidden };
+        }
+    }
+}
+
+fn dot_color(item: &Item, palette: &Palette) -> Color {
+    match item {
+        _ if item.is_candy() => palette.candy_dot,
+        Item::Fuel(_) => palette.fuel_dot,
+        _ => palette.item_dot,
+    }
+}
+
+// Redrawn whenever anything moves, turns up or goes away; soots over items over walls. Knockouts move soots without
+// tripping change detection, so they're caught by the hit instead.
+fn update_minimap(
+    palette: Res<Palette>,
+    rules: Res<GameRules>,
+    soots: Query<(&SootSprite, &GridLocation)>,
+    walls: Query<&Parent, With<Wall>>,
+    items: Query<(&Item, &Parent)>,
+    cell_locations: Query<&GridLocation, With<GridCell>>,
+    changed: Query<(), Or<(Changed<GridLocation>, Added<Item>, Added<Wall>, Added<MinimapDot>)>>,
+    mut removed_items: RemovedComponents<Item>,
+    mut removed_walls: RemovedComponents<Wall>,
+    mut hazard_hits: EventReader<HazardHit>,
+    mut dots: Query<(&MinimapDot, &mut BackgroundColor)>,
+) {
+    let removed = removed_items.iter().count() + removed_walls.iter().count() > 0;
+    let hit = hazard_hits.iter().count() > 0;
+    if changed.is_empty() && !removed && !hit && !palette.is_changed() {
+        return;
+    }
+
+    let on_cell = |parent: &Parent| cell_locations.get(parent.get()).ok().map(|location| location.0);
+    let mut colors = HashMap::new();
+    for cell in walls.iter().filter_map(on_cell) {
+        colors.insert(cell, palette.wall);
+    }
+    // Candy goes last so it shows over anything sharing its cell.
+    let mut items: Vec<_> = items.iter().filter_map(|(item, parent)| on_cell(parent).map(|cell| (cell, item))).collect();
+    items.sort_by_key(|(_, item)| item.is_candy());
+    for (cell, item) in items {
+        colors.insert(cell, dot_color(item, &palette));
+    }
+    // It's no help as a way to see through the fog.
+    for (x, y) in (0..MAX_X).flat_map(|x| (0..MAX_Y).map(move |y| (x, y))) {
+        let cell = IVec2::new(x, y);
+        if fogged(&rules, cell, soots.iter().map(|(_, location)| location.0)) {
+            colors.insert(cell, FOG_COLOR.with_a(1.));
+        }
+    }
+    // The player goes on top of any past self standing with them.
+    let mut soots: Vec<_> = soots.iter().collect();
+    soots.sort_by_key(|(soot, _)| soot.id == SootId::Player);
+    for (soot, location) in soots {
+        colors.insert(location.0, if soot.id == SootId::Player { palette.player_dot } else { palette.past_self.with_a(1.) });
+    }
+
+    for (dot, mut color) in dots.iter_mut() {
+        *color = colors.get(&dot.0).copied().unwrap_or(palette.tile).into();
+    }
+}
@@ -0,0 +1,270 @@
+use bevy::prelude::*;
+
+use crate::{fuel_cost, AppState, DespawnOnExitPlaying, MoveBuffer, Player, Recordings, SootId, SootSprite, GRID_SPACING, WAIT};
+use crate::grid::{AnimateTranslation, GridLocation};
+use crate::inventory::Inventory;
+use crate::level_spec::LevelSpec;
+use crate::palette::Palette;
+use crate::settings::Settings;
+use crate::shop::Upgrades;
+
+const PREVIEW_Z: f32 = 0.6;
+/// Above the player sprite, which the cue is parented to.
+const CUE_Z: f32 = 0.1;
+const CUE_SCALE: f32 = 0.5;
+const PAST_MOVE_ALPHA: f32 = 0.25;
+
+pub struct MovePreviewPlugin;
+
+impl Plugin for MovePreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            preview_pending_moves,
+            show_buffered_move_cue,
+            preview_held_move,
+            preview_past_moves,
+        ).run_if(in_state(AppState::Playing)));
+    }
+}
+
+#[derive(Component)]
+struct MovePreviewArrow;
+
+/// Highlights where the held direction key would take the player, when moves wait for the key to be let go.
+#[derive(Component)]
+struct HeldMovePreview;
+
+/// A small arrow on the player while they're still moving, pointing the way the next queued move will go.
+#[derive(Component)]
+struct BufferedMoveCue;
+
+/// Where a past self's recording will take it on its next turn.
+#[derive(Component)]
+struct PastMovePreview;
+
+fn preview_pending_moves(
+    mut commands: Commands,
+    move_buffer: Res<MoveBuffer>,
+    player: Query<(Ref<GridLocation>, &Inventory), With<Player>>,
+    upgrades: Res<Upgrades>,
+    level_spec: Res<LevelSpec>,
+    palette: Res<Palette>,
+    arrows: Query<Entity, With<MovePreviewArrow>>,
+) {
+    let Ok((player_location, inventory)) = player.get_single() else {
+        return;
+    };
+    if !move_buffer.is_changed() && !player_location.is_changed() {
+        return;
+    }
+
+    for entity in arrows.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    // Walk the queued plan from where the player is now, tracking fuel so steps we can't pay for stand out.
+    let mut position = player_location.0;
+    let mut fuel = inventory.fuel;
+    for offset in move_buffer.pending_moves() {
+        if offset == WAIT {
+            continue;
+        }
+        let cost = fuel_cost(offset, &upgrades);
+        let color = if cost > fuel {
+            palette.blocked_move
+        } else if cost > 0 {
+            palette.fuel_move
+        } else {
+            palette.free_move
+        };
+        fuel -= cost;
+
+        let arrow = spawn_arrow(&mut commands, position, offset, color, PREVIEW_Z);
+        commands.entity(arrow).insert((MovePreviewArrow, DespawnOnExitPlaying));
+        position = level_spec.landing_cell(position, offset);
+    }
+}
+
+fn show_buffered_move_cue(
+    mut commands: Commands,
+    move_buffer: Res<MoveBuffer>,
+    player: Query<(Entity, &AnimateTranslation), With<Player>>,
+    palette: Res<Palette>,
+    cues: Query<Entity, With<BufferedMoveCue>>,
+    mut shown: Local<Option<IVec2>>,
+) {
+    let Ok((player, animation)) = player.get_single() else {
+        return;
+    };
+    let pending = move_buffer.moves.front().copied().filter(|&offset| offset != WAIT && !animation.timer.finished());
+    if pending == *shown && (pending.is_none() || !cues.is_empty()) {
+        return;
+    }
+    *shown = pending;
+
+    for entity in cues.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(offset) = pending else {
+        return;
+    };
+    let cue = spawn_arrow(&mut commands, IVec2::ZERO, offset, palette.free_move, CUE_Z);
+    // Shrunk toward the player so it reads as part of the sprite rather than a step on the board.
+    let midpoint = offset.as_vec2() * GRID_SPACING as f32 / 2. * CUE_SCALE;
+    commands.entity(cue).insert((
+        BufferedMoveCue,
+        Transform::from_translation(midpoint.extend(CUE_Z))
+            .with_rotation(Quat::from_rotation_z(offset.as_vec2().y.atan2(offset.as_vec2().x)))
+            .with_scale(Vec3::splat(CUE_SCALE)),
+    )).set_parent(player);
+}
+
+fn preview_held_move(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    move_buffer: Res<MoveBuffer>,
+    player: Query<(&GridLocation, &Inventory), With<Player>>,
+    level_spec: Res<LevelSpec>,
+    upgrades: Res<Upgrades>,
+    palette: Res<Palette>,
+    previews: Query<Entity, With<HeldMovePreview>>,
+    mut shown: Local<Option<(IVec2, IVec2)>>,
+) {
+    let held = settings.key_bindings.held(&keyboard_input).filter(|_| settings.confirm_on_release);
+    let Ok((player_location, inventory)) = player.get_single() else {
+        return;
+    };
+    // Planned from where the queued moves leave the player.
+    let mut position = player_location.0;
+    let mut fuel = inventory.fuel;
+    for offset in move_buffer.pending_moves() {
+        fuel -= fuel_cost(offset, &upgrades);
+        position = level_spec.landing_cell(position, offset);
+    }
+    let wanted = held.map(|action| (position, action.offset()));
+    if wanted == *shown {
+        return;
+    }
+    *shown = wanted;
+
+    for entity in previews.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some((from, offset)) = wanted else {
+        return;
+    };
+
+    let cost = fuel_cost(offset, &upgrades);
+    let (destination, color, label) = if !level_spec.is_open(from + offset) {
+        (from + offset, palette.blocked_move, "blocked".to_string())
+    } else if cost > fuel {
+        (from + offset, palette.blocked_move, "not enough fuel".to_string())
+    } else if cost > 0 {
+        (level_spec.landing_cell(from, offset), palette.fuel_move, format!("-{} fuel", cost))
+    } else {
+        (level_spec.landing_cell(from, offset), palette.free_move, "free".to_string())
+    };
+    commands.spawn((
+        HeldMovePreview,
+        DespawnOnExitPlaying,
+        SpriteBundle {
+            sprite: Sprite {
+                color: color.with_a(0.35),
+                custom_size: Some(Vec2::splat(GRID_SPACING as f32 - 2.)),
+                ..default()
+            },
+            transform: Transform::from_translation((destination * GRID_SPACING).as_vec2().extend(PREVIEW_Z)),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(Text2dBundle {
+            text: Text::from_section(label, TextStyle {font_size: 24., color: Color::WHITE, ..default()}),
+            transform: Transform::from_xyz(0., -GRID_SPACING as f32 / 2. + 16., 0.01),
+            ..default()
+        });
+    });
+    let arrow = spawn_arrow(&mut commands, from, offset, color, PREVIEW_Z);
+    commands.entity(arrow).insert((HeldMovePreview, DespawnOnExitPlaying));
+}
+
+// Read the same way replay_move_attempts reads it: the recorded move for the soot's turn number, taken from wherever
+// it actually is, which isn't always where it was last time round.
+fn preview_past_moves(
+    mut commands: Commands,
+    recordings: Res<Recordings>,
+    level_spec: Res<LevelSpec>,
+    palette: Res<Palette>,
+    soots: Query<(&SootSprite, &GridLocation, &AnimateTranslation)>,
+    changed: Query<(), Or<(Changed<SootSprite>, Changed<GridLocation>)>>,
+    previews: Query<Entity, With<PastMovePreview>>,
+) {
+    if changed.is_empty() && !recordings.is_changed() {
+        return;
+    }
+
+    for entity in previews.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (soot, location, animation) in soots.iter() {
+        // Mid-move, the soot is already on its new cell but its turn number hasn't caught up; it's redrawn on landing.
+        if soot.id == SootId::Player || !animation.timer.finished() || recordings.is_exhausted(soot) {
+            continue;
+        }
+        let Some(&offset) = recordings.for_soot(soot.id).and_then(|recording| recording.moves.get(soot.turn_number as usize)) else {
+            continue;
+        };
+        if offset == WAIT {
+            continue;
+        }
+
+        let destination = level_spec.landing_cell(location.0, offset);
+        commands.spawn((
+            PastMovePreview,
+            DespawnOnExitPlaying,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: palette.past_self.with_a(PAST_MOVE_ALPHA),
+                    custom_size: Some(Vec2::splat(GRID_SPACING as f32 - 2.)),
+                    ..default()
+                },
+                transform: Transform::from_translation((destination * GRID_SPACING).as_vec2().extend(PREVIEW_Z)),
+                ..default()
+            },
+        ));
+        let arrow = spawn_arrow(&mut commands, location.0, offset, palette.past_self, PREVIEW_Z);
+        commands.entity(arrow).insert((PastMovePreview, DespawnOnExitPlaying));
+    }
+}
+
+/// Spawns an arrow pointing from the center of `from` toward the neighbouring cell at `from + offset`.
+pub fn spawn_arrow(commands: &mut Commands, from: IVec2, offset: IVec2, color: Color, z: f32) -> Entity {
+    let start = (from * GRID_SPACING).as_vec2();
+    let midpoint = start + offset.as_vec2() * GRID_SPACING as f32 / 2.;
+    let angle = offset.as_vec2().y.atan2(offset.as_vec2().x);
+    let length = GRID_SPACING as f32 * 0.6;
+
+    commands.spawn(SpatialBundle {
+        transform: Transform::from_translation(midpoint.extend(z)).with_rotation(Quat::from_rotation_z(angle)),
+        ..default()
+    }).with_children(|parent| {
+        parent.spawn(SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::new(length, 8.)),
+                ..default()
+            },
+            ..default()
+        });
+        parent.spawn(SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::splat(18.)),
+                ..default()
+            },
+            transform: Transform::from_xyz(length / 2., 0., 0.)
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            ..default()
+        });
+    }).id()
+}
@@ -0,0 +1,75 @@
+use bevy::audio::{PlaybackMode, Volume};
+use bevy::prelude::*;
+
+use crate::{AppState, LoopCounter};
+use crate::settings::Settings;
+
+/// Stems of one looping track, quietest first: pad, bass, drums, lead. Layer n joins in on loop n, so later loops build
+/// on earlier ones. They're all the same length, eight seconds at 120 bpm, so they loop in step.
+const MUSIC_LAYERS: [&str; 4] = [
+    "music/layer-1.wav",
+    "music/layer-2.wav",
+    "music/layer-3.wav",
+    "music/layer-4.wav",
+];
+/// Relative to the sound effects.
+const MUSIC_VOLUME: f32 = 0.5;
+/// How fast a layer fades in or out, in full volume per second.
+const FADE_RATE: f32 = 0.5;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, start_music)
+            .add_systems(Update, mix_music_layers);
+    }
+}
+
+/// One stem of the music. Every layer plays all the time, so they stay in step; the mix is just volume.
+#[derive(Component)]
+struct MusicLayer {
+    index: usize,
+    volume: f32,
+}
+
+// A stem that's missing just fails to load and stays quiet, which the asset server reports; there's no filesystem
+// to check up front on the web.
+fn start_music(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // Spawned together so they start on the same frame.
+    for (index, layer) in MUSIC_LAYERS.iter().enumerate() {
+        commands.spawn((
+            MusicLayer {index, volume: 0.},
+            AudioBundle {
+                source: asset_server.load(*layer),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Loop,
+                    volume: Volume::new_relative(0.),
+                    ..default()
+                },
+            },
+        ));
+    }
+}
+
+// Outside a run only the base layer plays.
+fn mix_music_layers(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    app_state: Res<State<AppState>>,
+    loop_counter: Res<LoopCounter>,
+    mut layers: Query<(&mut MusicLayer, Option<&AudioSink>)>,
+) {
+    let in_run = matches!(app_state.get(), AppState::Playing | AppState::Paused | AppState::LoopTransition | AppState::GameOver);
+    let loudest_layer = if in_run { loop_counter.0.max(0) as usize } else { 0 };
+    for (mut layer, sink) in layers.iter_mut() {
+        let target = if layer.index <= loudest_layer { 1. } else { 0. };
+        // Real time, so the mix still follows along behind the pause menu.
+        let step = FADE_RATE * time.raw_delta_seconds();
+        layer.volume = if layer.volume < target { (layer.volume + step).min(target) } else { (layer.volume - step).max(target) };
+        if let Some(sink) = sink {
+            sink.set_volume(layer.volume * MUSIC_VOLUME * settings.volume);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{AppState, StartLoop, DespawnOnExitGameOver, DespawnOnExitPlaying, LoopCounter, SootSprite, GRID_SPACING, MAX_X, MAX_Y};
+use crate::grid::{ApplyGridMovement, GridLocation};
+use crate::palette::Palette;
+use crate::rules::GameRules;
+use crate::spawn_level::SpawnLevel;
+
+const PAINT_Z: f32 = 0.05;
+
+pub struct PaintingPlugin;
+
+impl Plugin for PaintingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(PaintedCells::default())
+            .add_systems(OnEnter(AppState::Playing), (clear_paint, spawn_paint_display)
+                .before(SpawnLevel).in_set(StartLoop).run_if(painting_enabled))
+            .add_systems(Update, (
+                paint_cells,
+                (render_paint, update_paint_display).run_if(resource_changed::<PaintedCells>()),
+            ).chain().after(ApplyGridMovement).run_if(in_state(AppState::Playing).and_then(painting_enabled)));
+    }
+}
+
+/// Which loop last painted each cell this loop. Loops are counted from the start of the run, so a soot keeps its
+/// color as it goes from being the player to being a past self.
+#[derive(Resource, Default, Clone)]
+pub struct PaintedCells(HashMap<IVec2, i32>);
+
+impl PaintedCells {
+    pub fn is_board_painted(&self) -> bool {
+        self.0.len() as i32 == MAX_X * MAX_Y
+    }
+}
+
+#[derive(Component)]
+struct PaintSprite;
+
+#[derive(Component)]
+struct PaintDisplay;
+
+fn painting_enabled(rules: Res<GameRules>) -> bool {
+    rules.painting
+}
+
+fn clear_paint(mut painted: ResMut<PaintedCells>) {
+    painted.0.clear();
+}
+
+fn paint_cells(
+    mut painted: ResMut<PaintedCells>,
+    loop_counter: Res<LoopCounter>,
+    soots: Query<(&SootSprite, &GridLocation), Changed<GridLocation>>,
+) {
+    for (soot, location) in soots.iter() {
+        let painter = loop_counter.0 - soot.id.loop_number();
+        if painted.0.get(&location.0) != Some(&painter) {
+            painted.0.insert(location.0, painter);
+        }
+    }
+}
+
+fn render_paint(
+    mut commands: Commands,
+    painted: Res<PaintedCells>,
+    palette: Res<Palette>,
+    sprites: Query<Entity, With<PaintSprite>>,
+) {
+    for entity in sprites.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    for (&cell, &painter) in painted.0.iter() {
+        commands.spawn((
+            PaintSprite,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: palette.loop_color(painter),
+                    custom_size: Some(Vec2::splat(128.)),
+                    ..default()
+                },
+                transform: Transform::from_translation((cell * GRID_SPACING).as_vec2().extend(PAINT_Z)),
+                ..default()
+            },
+            DespawnOnExitGameOver,
+        ));
+    }
+}
+
+fn spawn_paint_display(mut commands: Commands) {
+    commands.spawn((
+        PaintDisplay,
+        TextBundle::from_section("", TextStyle {font_size: 40., ..default()})
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.),
+                left: Val::Px(10.),
+                ..default()
+            }),
+        DespawnOnExitPlaying,
+    ));
+}
+
+fn update_paint_display(painted: Res<PaintedCells>, mut display: Query<&mut Text, With<PaintDisplay>>) {
+    for mut text in display.iter_mut() {
+        text.sections[0].value = format!("Painted: {}/{}", painted.0.len(), MAX_X * MAX_Y);
+    }
+}
@@ -0,0 +1,229 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::doors::Door;
+use crate::gates::Gate;
+use crate::grid::GridCell;
+use crate::inventory::Item;
+use crate::settings::Settings;
+use crate::spawn_level::Wall;
+
+pub struct PalettePlugin;
+
+impl Plugin for PalettePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(PaletteChoice::default().palette())
+            .add_systems(Update, (
+                choose_palette.run_if(resource_changed::<Settings>()),
+                recolor_board.run_if(resource_changed::<Palette>()),
+            ).chain());
+    }
+}
+
+/// Color schemes to pick from in the settings. Each one keeps the colors that carry meaning apart for players who
+/// can't tell the standard ones apart.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PaletteChoice {
+    #[default]
+    Standard,
+    /// Red-green, with weak green.
+    Deuteranopia,
+    /// Red-green, with weak red: reds also look dark.
+    Protanopia,
+    /// Blue-yellow.
+    Tritanopia,
+    /// Told apart by brightness alone.
+    Monochrome,
+}
+
+impl PaletteChoice {
+    pub fn next(&self) -> Self {
+        match self {
+            PaletteChoice::Standard => PaletteChoice::Deuteranopia,
+            PaletteChoice::Deuteranopia => PaletteChoice::Protanopia,
+            PaletteChoice::Protanopia => PaletteChoice::Tritanopia,
+            PaletteChoice::Tritanopia => PaletteChoice::Monochrome,
+            PaletteChoice::Monochrome => PaletteChoice::Standard,
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        let standard = Palette {
+            tile: Color::rgb(0.6, 0.5, 0.7),
+            wall: Color::rgb(0.2, 0.15, 0.25),
+            door: Color::rgb(0.45, 0.28, 0.12),
+            key: Color::rgb(1., 0.8, 0.2),
+            past_self: Color::rgba(0.6, 0.6, 0.6, 0.6),
+            loops: [
+                Color::rgba(1., 0.5, 0.2, 0.5),
+                Color::rgba(0.2, 0.7, 1., 0.5),
+                Color::rgba(0.4, 1., 0.4, 0.5),
+                Color::rgba(1., 0.3, 0.8, 0.5),
+            ],
+            free_move: Color::rgba(0.4, 0.9, 0.4, 0.8),
+            fuel_move: Color::rgba(1., 0.6, 0.1, 0.8),
+            blocked_move: Color::rgba(0.9, 0.2, 0.2, 0.8),
+            button: Color::rgb(0.15, 0.15, 0.15),
+            button_hovered: Color::rgb(0.25, 0.25, 0.25),
+            button_pressed: Color::rgb(0.35, 0.75, 0.35),
+            info: Color::rgba(0.15, 0.15, 0.2, 0.9),
+            success: Color::rgba(0.15, 0.4, 0.2, 0.9),
+            warning: Color::rgba(0.5, 0.15, 0.1, 0.9),
+            player_dot: Color::GOLD,
+            candy_dot: Color::rgb(1., 0.4, 0.7),
+            fuel_dot: Color::rgb(1., 0.6, 0.1),
+            item_dot: Color::rgb(0.6, 0.9, 1.),
+        };
+        // The red-green and blue-yellow schemes lean on Okabe and Ito's colorblind-safe set.
+        let neutral_tile = Color::rgb(0.3, 0.3, 0.4);
+        match self {
+            PaletteChoice::Standard => standard,
+            PaletteChoice::Deuteranopia => Palette {
+                tile: neutral_tile,
+                door: Color::rgb(0.55, 0.35, 0.05),
+                key: Color::rgb(0.95, 0.9, 0.25),
+                loops: [
+                    Color::rgba(0.9, 0.6, 0., 0.5),
+                    Color::rgba(0.35, 0.7, 0.9, 0.5),
+                    Color::rgba(0.95, 0.9, 0.25, 0.5),
+                    Color::rgba(0., 0.45, 0.7, 0.5),
+                ],
+                free_move: Color::rgba(0.35, 0.7, 0.9, 0.8),
+                fuel_move: Color::rgba(0.95, 0.9, 0.25, 0.8),
+                blocked_move: Color::rgba(0.8, 0.4, 0., 0.8),
+                button_pressed: Color::rgb(0., 0.45, 0.7),
+                success: Color::rgba(0., 0.35, 0.6, 0.9),
+                warning: Color::rgba(0.6, 0.35, 0., 0.9),
+                player_dot: Color::rgb(0.95, 0.9, 0.25),
+                candy_dot: Color::rgb(0.8, 0.47, 0.65),
+                fuel_dot: Color::rgb(0.9, 0.6, 0.),
+                item_dot: Color::rgb(0.35, 0.7, 0.9),
+                ..standard
+            },
+            PaletteChoice::Protanopia => Palette {
+                tile: neutral_tile,
+                door: Color::rgb(0.5, 0.42, 0.2),
+                key: Color::rgb(0.95, 0.9, 0.25),
+                loops: [
+                    Color::rgba(0.95, 0.9, 0.25, 0.5),
+                    Color::rgba(0., 0.45, 0.7, 0.5),
+                    Color::rgba(0.9, 0.6, 0., 0.5),
+                    Color::rgba(0.35, 0.7, 0.9, 0.5),
+                ],
+                free_move: Color::rgba(0., 0.45, 0.7, 0.8),
+                fuel_move: Color::rgba(0.95, 0.9, 0.25, 0.8),
+                blocked_move: Color::rgba(0.1, 0.1, 0.1, 0.8),
+                button_pressed: Color::rgb(0., 0.45, 0.7),
+                success: Color::rgba(0., 0.35, 0.6, 0.9),
+                warning: Color::rgba(0.45, 0.4, 0.05, 0.9),
+                player_dot: Color::rgb(0.95, 0.9, 0.25),
+                candy_dot: Color::rgb(0.8, 0.47, 0.65),
+                fuel_dot: Color::rgb(0.9, 0.6, 0.),
+                item_dot: Color::rgb(0.35, 0.7, 0.9),
+                ..standard
+            },
+            PaletteChoice::Tritanopia => Palette {
+                tile: Color::rgb(0.35, 0.3, 0.3),
+                door: Color::rgb(0.5, 0.2, 0.2),
+                key: Color::rgb(0.8, 0.47, 0.65),
+                loops: [
+                    Color::rgba(0.85, 0.1, 0.1, 0.5),
+                    Color::rgba(0., 0.6, 0.5, 0.5),
+                    Color::rgba(1., 0.7, 0.75, 0.5),
+                    Color::rgba(0.2, 0.2, 0.2, 0.5),
+                ],
+                free_move: Color::rgba(0., 0.6, 0.5, 0.8),
+                fuel_move: Color::rgba(1., 0.7, 0.75, 0.8),
+                blocked_move: Color::rgba(0.85, 0.1, 0.1, 0.8),
+                button_pressed: Color::rgb(0., 0.6, 0.5),
+                success: Color::rgba(0., 0.45, 0.4, 0.9),
+                warning: Color::rgba(0.6, 0.05, 0.05, 0.9),
+                player_dot: Color::WHITE,
+                candy_dot: Color::rgb(1., 0.7, 0.75),
+                fuel_dot: Color::rgb(0.85, 0.1, 0.1),
+                item_dot: Color::rgb(0., 0.6, 0.5),
+                ..standard
+            },
+            PaletteChoice::Monochrome => Palette {
+                tile: Color::rgb(0.35, 0.35, 0.35),
+                wall: Color::rgb(0.1, 0.1, 0.1),
+                door: Color::rgb(0.55, 0.55, 0.55),
+                key: Color::rgb(0.95, 0.95, 0.95),
+                loops: [
+                    Color::rgba(1., 1., 1., 0.5),
+                    Color::rgba(0.7, 0.7, 0.7, 0.5),
+                    Color::rgba(0.45, 0.45, 0.45, 0.5),
+                    Color::rgba(0.15, 0.15, 0.15, 0.5),
+                ],
+                free_move: Color::rgba(0.95, 0.95, 0.95, 0.8),
+                fuel_move: Color::rgba(0.6, 0.6, 0.6, 0.8),
+                blocked_move: Color::rgba(0.2, 0.2, 0.2, 0.8),
+                button_pressed: Color::rgb(0.6, 0.6, 0.6),
+                success: Color::rgba(0.4, 0.4, 0.4, 0.9),
+                warning: Color::rgba(0.05, 0.05, 0.05, 0.9),
+                player_dot: Color::WHITE,
+                candy_dot: Color::rgb(0.88, 0.88, 0.88),
+                fuel_dot: Color::rgb(0.75, 0.75, 0.75),
+                item_dot: Color::rgb(0.5, 0.5, 0.5),
+                ..standard
+            },
+        }
+    }
+}
+
+/// The colors in use, for anything whose color means something. Read this rather than hard-coding a color.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Palette {
+    pub tile: Color,
+    pub wall: Color,
+    pub door: Color,
+    /// Tint on keys, on the board and in the editor.
+    pub key: Color,
+    /// Tint on past selves.
+    pub past_self: Color,
+    /// One per loop, counted from the start of the run, for whatever each loop leaves on the board.
+    pub loops: [Color; 4],
+    pub free_move: Color,
+    pub fuel_move: Color,
+    pub blocked_move: Color,
+    pub button: Color,
+    pub button_hovered: Color,
+    pub button_pressed: Color,
+    pub info: Color,
+    pub success: Color,
+    pub warning: Color,
+    /// What the minimap marks where the player, candy, fuel and any other item are.
+    pub player_dot: Color,
+    pub candy_dot: Color,
+    pub fuel_dot: Color,
+    pub item_dot: Color,
+}
+
+impl Palette {
+    pub fn loop_color(&self, loop_number: i32) -> Color {
+        self.loops[loop_number as usize % self.loops.len()]
+    }
+}
+
+fn choose_palette(settings: Res<Settings>, mut palette: ResMut<Palette>) {
+    *palette = settings.palette.palette();
+}
+
+// Everything else picks up the palette when it's next spawned; the board is already out.
+fn recolor_board(
+    palette: Res<Palette>,
+    mut tiles: Query<&mut Sprite, (With<GridCell>, Without<Wall>, Without<Item>)>,
+    mut walls: Query<(&mut Sprite, Option<&Door>), (With<Wall>, Without<Gate>, Without<Item>)>,
+    mut items: Query<(&Item, &mut Sprite), (Without<GridCell>, Without<Wall>)>,
+) {
+    for mut sprite in tiles.iter_mut() {
+        sprite.color = palette.tile;
+    }
+    for (mut sprite, door) in walls.iter_mut() {
+        sprite.color = if door.is_some() { palette.door } else { palette.wall };
+    }
+    for (_, mut sprite) in items.iter_mut().filter(|(item, _)| matches!(item, Item::Key)) {
+        sprite.color = palette.key;
+    }
+}
@@ -0,0 +1,205 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{AppState, DespawnOnExitGameOver, SootSprite, GRID_SPACING, SOOT_Z};
+use crate::grid::{ApplyGridMovement, MovementComplete};
+use crate::inventory::{Item, ItemGet, PickUpItems};
+use crate::settings::Settings;
+
+/// In front of the soots, so effects on them aren't hidden.
+const PARTICLE_Z: f32 = SOOT_Z + 1.;
+const CANDY_COLORS: [Color; 2] = [Color::rgb(1., 0.4, 0.7), Color::GOLD];
+const FUEL_COLORS: [Color; 3] = [Color::rgb(1., 0.6, 0.1), Color::YELLOW, Color::WHITE];
+const DUST_COLORS: [Color; 1] = [Color::rgba(0.8, 0.8, 0.8, 0.6)];
+const CONFETTI_COLORS: [Color; 4] = [Color::GOLD, Color::PINK, Color::CYAN, Color::LIME_GREEN];
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                burst_on_pickup.after(PickUpItems),
+                puff_on_landing.after(ApplyGridMovement),
+            ).run_if(in_state(AppState::Playing)))
+            // Effects finish playing out whatever state they were started in.
+            .add_systems(Update, (emit_particles, update_particles).chain());
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParticleEffect {
+    /// Candy and other points going pop.
+    Burst,
+    /// Dust kicked up where a soot lands.
+    Puff,
+    /// A slow glitter for fuel.
+    Sparkle,
+    /// Shot up over the exit as a soot makes it there.
+    Confetti,
+}
+
+/// How an effect's particles start out and move.
+struct EffectSpec {
+    count: usize,
+    /// Directions are picked from this range, counterclockwise from the right.
+    angle: (f32, f32),
+    speed: (f32, f32),
+    spin: f32,
+    gravity: f32,
+    lifetime_secs: f32,
+    size: Vec2,
+    /// Where the particles start, from the emitter.
+    offset: Vec2,
+    colors: &'static [Color],
+}
+
+impl ParticleEffect {
+    fn spec(self) -> EffectSpec {
+        match self {
+            ParticleEffect::Burst => EffectSpec {
+                count: 12,
+                angle: (0., TAU),
+                speed: (150., 300.),
+                spin: 0.,
+                gravity: -300.,
+                lifetime_secs: 0.5,
+                size: Vec2::splat(8.),
+                offset: Vec2::ZERO,
+                colors: &CANDY_COLORS,
+            },
+            ParticleEffect::Puff => EffectSpec {
+                count: 8,
+                angle: (0., PI),
+                speed: (30., 80.),
+                spin: 0.,
+                gravity: 40.,
+                lifetime_secs: 0.4,
+                size: Vec2::splat(12.),
+                offset: Vec2::new(0., -GRID_SPACING as f32 * 0.3),
+                colors: &DUST_COLORS,
+            },
+            ParticleEffect::Sparkle => EffectSpec {
+                count: 6,
+                angle: (0., TAU),
+                speed: (20., 60.),
+                spin: 6.,
+                gravity: 0.,
+                lifetime_secs: 0.7,
+                size: Vec2::splat(6.),
+                offset: Vec2::ZERO,
+                colors: &FUEL_COLORS,
+            },
+            ParticleEffect::Confetti => EffectSpec {
+                count: 24,
+                angle: (PI / 6., 5. * PI / 6.),
+                speed: (200., 400.),
+                spin: 10.,
+                gravity: -600.,
+                lifetime_secs: 1.2,
+                size: Vec2::new(8., 4.),
+                offset: Vec2::ZERO,
+                colors: &CONFETTI_COLORS,
+            },
+        }
+    }
+}
+
+/// Goes off once, on the frame after it's spawned, and is gone again.
+#[derive(Component)]
+pub struct ParticleEmitter(pub ParticleEffect);
+
+impl ParticleEmitter {
+    pub fn bundle(effect: ParticleEffect, position: Vec2) -> impl Bundle {
+        (
+            ParticleEmitter(effect),
+            TransformBundle::from_transform(Transform::from_translation(position.extend(PARTICLE_Z))),
+            DespawnOnExitGameOver,
+        )
+    }
+}
+
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    spin: f32,
+    gravity: f32,
+    timer: Timer,
+}
+
+fn emit_particles(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    emitters: Query<(Entity, &ParticleEmitter, &Transform)>,
+) {
+    let mut rng = rand::thread_rng();
+    for (entity, emitter, transform) in emitters.iter() {
+        commands.entity(entity).despawn();
+        // Every effect is decoration.
+        if settings.low_spec {
+            continue;
+        }
+
+        let spec = emitter.0.spec();
+        let origin = transform.translation + spec.offset.extend(0.);
+        for i in 0..spec.count {
+            let angle = rng.gen_range(spec.angle.0..spec.angle.1);
+            commands.spawn((
+                Particle {
+                    velocity: Vec2::from_angle(angle) * rng.gen_range(spec.speed.0..spec.speed.1),
+                    spin: if spec.spin > 0. { rng.gen_range(-spec.spin..spec.spin) } else { 0. },
+                    gravity: spec.gravity,
+                    timer: Timer::from_seconds(spec.lifetime_secs, TimerMode::Once),
+                },
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: spec.colors[i % spec.colors.len()],
+                        custom_size: Some(spec.size),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(origin),
+                    ..default()
+                },
+                DespawnOnExitGameOver,
+            ));
+        }
+    }
+}
+
+fn update_particles(mut commands: Commands, time: Res<Time>, mut particles: Query<(Entity, &mut Transform, &mut Sprite, &mut Particle)>) {
+    for (entity, mut transform, mut sprite, mut particle) in particles.iter_mut() {
+        if particle.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        particle.velocity.y += particle.gravity * time.delta_seconds();
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+        transform.rotate_z(particle.spin * time.delta_seconds());
+        let alpha = sprite.color.a();
+        sprite.color.set_a(alpha.min(particle.timer.percent_left()));
+    }
+}
+
+fn burst_on_pickup(mut commands: Commands, mut pickups: EventReader<ItemGet>, soots: Query<&Transform, With<SootSprite>>) {
+    for pickup in pickups.iter() {
+        let Ok(transform) = soots.get(pickup.soot) else {
+            continue;
+        };
+        let effect = match pickup.item {
+            Item::Fuel(_) => ParticleEffect::Sparkle,
+            _ => ParticleEffect::Burst,
+        };
+        commands.spawn(ParticleEmitter::bundle(effect, transform.translation.truncate()));
+    }
+}
+
+fn puff_on_landing(mut commands: Commands, mut landings: EventReader<MovementComplete>, soots: Query<&Transform, With<SootSprite>>) {
+    for &MovementComplete{entity} in landings.iter() {
+        if let Ok(transform) = soots.get(entity) {
+            commands.spawn(ParticleEmitter::bundle(ParticleEffect::Puff, transform.translation.truncate()));
+        }
+    }
+}
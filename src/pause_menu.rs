@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+use crate::{despawn_after_game_over, despawn_after_playing, not_suspended, reset_run, AppState, DespawnOnExitPlaying, StartLoop, Suspended};
+use crate::ui::spawn_button;
+
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Playing), spawn_pause_button.in_set(StartLoop))
+            .add_systems(Update, pause.run_if(in_state(AppState::Playing)))
+            .add_systems(OnEnter(AppState::Paused), (spawn_pause_menu, stop_time))
+            .add_systems(Update, (
+                resume_on_escape,
+                press_pause_buttons,
+            ).run_if(in_state(AppState::Paused)))
+            .add_systems(OnExit(AppState::Paused), (despawn_pause_menu, start_time))
+            // Leaving for anywhere but the paused loop abandons it; OnExit(Playing) was skipped, so clean up here.
+            .add_systems(OnExit(AppState::Paused),
+                (despawn_after_playing, despawn_after_game_over, reset_run).run_if(not_suspended));
+    }
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+/// On-screen way into the pause menu, for players without an Escape key.
+#[derive(Component)]
+struct OpenPauseMenu;
+
+#[derive(Component)]
+enum PauseButton {
+    Resume,
+    Restart,
+    Quit,
+}
+
+fn spawn_pause_button(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(20.),
+                bottom: Val::Px(20.),
+                ..default()
+            },
+            ..default()
+        },
+        DespawnOnExitPlaying,
+    )).with_children(|parent| {
+        spawn_button(parent, "Pause", OpenPauseMenu);
+    });
+}
+
+fn pause(
+    keyboard_input: Res<Input<KeyCode>>,
+    pause_button: Query<&Interaction, (Changed<Interaction>, With<OpenPauseMenu>)>,
+    mut suspended: ResMut<Suspended>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let pressed = pause_button.iter().any(|interaction| *interaction == Interaction::Pressed);
+    if keyboard_input.just_pressed(KeyCode::Escape) || pressed {
+        suspended.0 = true;
+        next_state.set(AppState::Paused);
+    }
+}
+
+// Timers, animations and effects all run off Time, so stopping it freezes them mid-flight.
+fn stop_time(mut time: ResMut<Time>) {
+    time.pause();
+}
+
+fn start_time(mut time: ResMut<Time>) {
+    time.unpause();
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    commands.spawn((
+        PauseMenu,
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.7).into(),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("Paused", TextStyle {font_size: 50., ..default()}));
+        spawn_button(parent, "Resume", PauseButton::Resume);
+        spawn_button(parent, "Restart", PauseButton::Restart);
+        spawn_button(parent, "Quit to menu", PauseButton::Quit);
+    });
+}
+
+fn despawn_pause_menu(mut commands: Commands, query: Query<Entity, With<PauseMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn resume_on_escape(keyboard_input: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<AppState>>) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Playing);
+    }
+}
+
+fn press_pause_buttons(
+    interactions: Query<(&Interaction, &PauseButton), Changed<Interaction>>,
+    mut suspended: ResMut<Suspended>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, button) in interactions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            PauseButton::Resume => next_state.set(AppState::Playing),
+            PauseButton::Restart => {
+                suspended.0 = false;
+                next_state.set(AppState::Playing);
+            },
+            PauseButton::Quit => {
+                suspended.0 = false;
+                next_state.set(AppState::MainMenu);
+            },
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::AppState;
+use crate::high_scores::HighScores;
+use crate::profile::{Preferences, Profile, PROFILE_SLOTS};
+use crate::settings::Settings;
+use crate::storage::{self, save_path};
+use crate::toast::Toast;
+
+const PROFILE_FILE: &str = "profile.ron";
+const SETTINGS_FILE: &str = "settings.ron";
+const HIGH_SCORES_FILE: &str = "high_scores.ron";
+
+/// Loads the profile, settings and high scores at startup, and writes them back out on every state transition and on exit.
+/// Must be added before any plugin that reads them while building.
+pub struct PersistencePlugin;
+
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        let mut settings = load::<Settings>(SETTINGS_FILE);
+        settings.profile_slot = settings.profile_slot.min(PROFILE_SLOTS - 1);
+        let profile = load::<Profile>(&profile_file(settings.profile_slot));
+        if let Some(preferences) = &profile.preferences {
+            preferences.apply(&mut settings);
+        }
+        app
+            .insert_resource(profile)
+            .insert_resource(settings)
+            .insert_resource(load::<HighScores>(HIGH_SCORES_FILE))
+            .add_systems(Last, (
+                keep_preferences.run_if(resource_changed::<Settings>()),
+                save_all.run_if(state_changed::<AppState>().or_else(on_event::<AppExit>())),
+            ).chain());
+    }
+}
+
+/// The first profile keeps the file it always had.
+fn profile_file(slot: usize) -> String {
+    match slot {
+        0 => PROFILE_FILE.to_string(),
+        slot => format!("profile-{}.ron", slot + 1),
+    }
+}
+
+// Not progress, so it doesn't set off the "Progress saved" toast.
+fn keep_preferences(settings: Res<Settings>, mut profile: ResMut<Profile>) {
+    profile.bypass_change_detection().preferences = Some(Preferences::from_settings(&settings));
+}
+
+/// Saves the profile in use and changes over to the next one, along with its controls and aids.
+pub fn switch_profile(profile: &mut Profile, settings: &mut Settings) -> Result<(), String> {
+    profile.preferences = Some(Preferences::from_settings(settings));
+    save(&profile_file(settings.profile_slot), profile)?;
+    settings.profile_slot = (settings.profile_slot + 1) % PROFILE_SLOTS;
+    *profile = load(&profile_file(settings.profile_slot));
+    if let Some(preferences) = &profile.preferences {
+        preferences.apply(settings);
+    }
+    Ok(())
+}
+
+// A missing or unreadable file just means starting fresh.
+fn load<T: DeserializeOwned + Default>(file: &str) -> T {
+    let Some(path) = save_path(file) else {
+        return default();
+    };
+    match storage::read(&path) {
+        Ok(contents) => ron::from_str(&contents).unwrap_or_else(|err| {
+            warn!("Ignoring unreadable save file {}: {}", path.display(), err);
+            default()
+        }),
+        Err(_) => default(),
+    }
+}
+
+fn save<T: Serialize>(file: &str, value: &T) -> Result<(), String> {
+    let path = save_path(file).ok_or("no home directory to save to")?;
+    let contents = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|err| format!("couldn't serialize {}: {}", file, err))?;
+    storage::write(&path, &contents)
+}
+
+fn save_all(
+    profile: Res<Profile>,
+    settings: Res<Settings>,
+    high_scores: Res<HighScores>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let results = [
+        save(&profile_file(settings.profile_slot), &*profile),
+        save(SETTINGS_FILE, &*settings),
+        save(HIGH_SCORES_FILE, &*high_scores),
+    ];
+    let mut failed = false;
+    for err in results.into_iter().filter_map(Result::err) {
+        warn!("Couldn't save: {}", err);
+        toasts.send(Toast::warning(format!("Couldn't save: {}", err)));
+        failed = true;
+    }
+    // Only confirm when there was progress to save; what was just loaded doesn't count.
+    let progressed = (profile.is_changed() && !profile.is_added()) || (high_scores.is_changed() && !high_scores.is_added());
+    if progressed && !failed {
+        toasts.send(Toast::info("Progress saved"));
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::math::cubic_splines::CubicSegment;
+use bevy::prelude::*;
+
+use crate::{AppState, DespawnOnExitPlaying, Player, StartLoop};
+use crate::inventory::{Inventory, Item, ItemGet, PickUpItems};
+use crate::palette::Palette;
+use crate::settings::{AnimationSpeed, Settings};
+use crate::spawn_level::item_bundle;
+use crate::ui::UpdateUi;
+
+const FLIGHT_SECS: f32 = 0.6;
+const FLYER_SIZE: f32 = 48.;
+
+pub struct PickupFlightPlugin;
+
+impl Plugin for PickupFlightPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(InFlight::default())
+            .add_event::<UiTweenFinished>()
+            .add_systems(OnEnter(AppState::Playing), clear_in_flight.in_set(StartLoop))
+            .add_systems(Update, (launch_pickups, run_ui_tweens, land_pickups)
+                .chain().after(PickUpItems).before(UpdateUi).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// What the player has picked up that's still on its way to the HUD. The HUD holds it back from its numbers until
+/// it lands.
+#[derive(Resource, Default)]
+pub struct InFlight {
+    pub points: i32,
+    pub fuel: i32,
+}
+
+/// Put on a HUD display so pickups know where to fly to.
+#[derive(Component, Clone, Copy, Eq, PartialEq)]
+pub enum HudTarget {
+    Score,
+    Fuel,
+}
+
+/// Slides a UI node between two points on the screen, in logical pixels from the top left.
+#[derive(Component)]
+struct UiTween {
+    from: Vec2,
+    to: Vec2,
+    timer: Timer,
+}
+
+/// Sent as a tween gets where it's going.
+#[derive(Event)]
+struct UiTweenFinished(Entity);
+
+#[derive(Component)]
+struct FlyingPickup {
+    points: i32,
+    fuel: i32,
+}
+
+fn clear_in_flight(mut in_flight: ResMut<InFlight>) {
+    *in_flight = InFlight::default();
+}
+
+// Past selves' pickups don't count towards the player's numbers, so only the player's fly.
+fn launch_pickups(
+    mut commands: Commands,
+    mut pickups: EventReader<ItemGet>,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+    settings: Res<Settings>,
+    mut in_flight: ResMut<InFlight>,
+    player: Query<(&GlobalTransform, &Inventory), With<Player>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    targets: Query<(&HudTarget, &GlobalTransform)>,
+) {
+    let (Ok((player_transform, inventory)), Some((camera, camera_transform))) =
+        (player.get_single(), cameras.iter().find(|(camera, _)| camera.is_active)) else {
+        return;
+    };
+    for pickup in pickups.iter().filter(|pickup| player.contains(pickup.soot)) {
+        let (target, points, fuel) = match pickup.item {
+            // Fuel that topped the tank up may have been partly wasted, and there's no telling how much, so the number
+            // only waits when it's clear the whole lot went in.
+            Item::Fuel(amount) if inventory.fuel < inventory.max_fuel => (HudTarget::Fuel, 0, amount),
+            Item::Fuel(_) => (HudTarget::Fuel, 0, 0),
+            item if item.points() > 0 => (HudTarget::Score, item.points(), 0),
+            _ => continue,
+        };
+        if settings.animation_speed == AnimationSpeed::Instant {
+            continue;
+        }
+        let Some(from) = camera.world_to_viewport(camera_transform, player_transform.translation()) else {
+            continue;
+        };
+        // UI nodes' transforms are their centers, in the same top-left pixel space the camera hands back.
+        let Some((_, to)) = targets.iter().find(|(hud_target, _)| **hud_target == target) else {
+            continue;
+        };
+
+        in_flight.points += points;
+        in_flight.fuel += fuel;
+        let (_, sprite, _) = item_bundle(pickup.item, &asset_server, &palette);
+        commands.spawn((
+            FlyingPickup {points, fuel},
+            UiTween {from, to: to.translation().truncate(), timer: Timer::from_seconds(FLIGHT_SECS, TimerMode::Once)},
+            ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(from.x - FLYER_SIZE / 2.),
+                    top: Val::Px(from.y - FLYER_SIZE / 2.),
+                    width: Val::Px(FLYER_SIZE),
+                    height: Val::Px(FLYER_SIZE),
+                    ..default()
+                },
+                image: UiImage::new(sprite.texture),
+                background_color: sprite.sprite.color.into(),
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            DespawnOnExitPlaying,
+        ));
+    }
+}
+
+// Slow to leave and quick to arrive, like it's being pulled in.
+fn run_ui_tweens(
+    time: Res<Time>,
+    mut tweens: Query<(Entity, &mut UiTween, &mut Style)>,
+    mut finished: EventWriter<UiTweenFinished>,
+) {
+    let ease = CubicSegment::new_bezier(Vec2::new(0.5, 0.), Vec2::new(0.9, 0.6));
+    for (entity, mut tween, mut style) in tweens.iter_mut() {
+        if tween.timer.finished() {
+            continue;
+        }
+        let progress = ease.ease(tween.timer.tick(time.delta()).percent());
+        let position = tween.from.lerp(tween.to, progress);
+        let size = match (style.width, style.height) {
+            (Val::Px(width), Val::Px(height)) => Vec2::new(width, height),
+            _ => Vec2::ZERO,
+        };
+        style.left = Val::Px(position.x - size.x / 2.);
+        style.top = Val::Px(position.y - size.y / 2.);
+        if tween.timer.just_finished() {
+            finished.send(UiTweenFinished(entity));
+        }
+    }
+}
+
+fn land_pickups(
+    mut commands: Commands,
+    mut finished: EventReader<UiTweenFinished>,
+    pickups: Query<&FlyingPickup>,
+    mut in_flight: ResMut<InFlight>,
+) {
+    for &UiTweenFinished(entity) in finished.iter() {
+        let Ok(pickup) = pickups.get(entity) else {
+            continue;
+        };
+        in_flight.points -= pickup.points;
+        in_flight.fuel -= pickup.fuel;
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode};
+
+use crate::{LoopCounter, SootSprite};
+use crate::achievements::{Achievement, AchievementUnlocked};
+use crate::inventory::Inventory;
+use crate::scoring::{FinishedLoopsScore, Score};
+
+/// A storefront (Steam, itch.io, ...) the game can report to. The core game never talks to a store directly; it only
+/// emits events, and this module forwards them to whichever backend is installed in `ActivePlatform`.
+pub trait Platform: Send + Sync + 'static {
+    fn set_rich_presence(&mut self, status: &str);
+    fn unlock_achievement(&mut self, achievement: Achievement);
+}
+
+/// Fallback backend for builds without a store SDK; just logs what it would have sent.
+pub struct LogPlatform;
+
+impl Platform for LogPlatform {
+    fn set_rich_presence(&mut self, status: &str) {
+        info!("Rich presence: {}", status);
+    }
+
+    fn unlock_achievement(&mut self, achievement: Achievement) {
+        info!("Achievement unlocked: {}", achievement.id());
+    }
+}
+
+#[derive(Resource)]
+pub struct ActivePlatform(pub Box<dyn Platform>);
+
+pub struct PlatformPlugin;
+
+impl Plugin for PlatformPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<ActivePlatform>() {
+            app.insert_resource(ActivePlatform(Box::new(LogPlatform)));
+        }
+
+        app
+            .insert_resource(RichPresence::default())
+            .add_systems(Update, (
+                keep_window_overlay_safe,
+                update_rich_presence,
+                forward_achievements,
+            ));
+    }
+}
+
+#[derive(Resource, Default)]
+struct RichPresence(String);
+
+fn update_rich_presence(
+    mut platform: ResMut<ActivePlatform>,
+    mut presence: ResMut<RichPresence>,
+    loop_counter: Res<LoopCounter>,
+    soots: Query<(&Inventory, &Score), With<SootSprite>>,
+    finished_loops: Res<FinishedLoopsScore>,
+) {
+    let points = finished_loops.run_total(soots.iter());
+    let status = format!("Loop {}, {} points", loop_counter.0 + 1, points);
+    if status == presence.0 {
+        return;
+    }
+
+    platform.0.set_rich_presence(&status);
+    presence.0 = status;
+}
+
+fn forward_achievements(mut platform: ResMut<ActivePlatform>, mut events: EventReader<AchievementUnlocked>) {
+    for &AchievementUnlocked(achievement) in events.iter() {
+        platform.0.unlock_achievement(achievement);
+    }
+}
+
+// Store overlays can't draw over an exclusive fullscreen swapchain, so downgrade it to borderless.
+fn keep_window_overlay_safe(mut windows: Query<&mut Window, (With<PrimaryWindow>, Changed<Window>)>) {
+    for mut window in windows.iter_mut() {
+        if matches!(window.mode, WindowMode::Fullscreen | WindowMode::SizedFullscreen) {
+            window.mode = WindowMode::BorderlessFullscreen;
+        }
+        if window.present_mode == PresentMode::Immediate {
+            window.present_mode = PresentMode::AutoVsync;
+        }
+    }
+}
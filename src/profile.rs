@@ -0,0 +1,67 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cosmetics::Cosmetic;
+use crate::palette::PaletteChoice;
+use crate::settings::{AnimationSpeed, KeyBindings, Settings};
+use crate::shop::Upgrades;
+
+/// How many profiles can be kept side by side on one machine, e.g. one for each player in a household.
+pub const PROFILE_SLOTS: usize = 3;
+
+/// What the player has earned and picked, kept between sessions by the persistence plugin.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub unlocked_cosmetics: BTreeSet<Cosmetic>,
+    pub hat: Option<Cosmetic>,
+    pub color: Option<Cosmetic>,
+    /// Campaign levels finished at least once, by file name.
+    pub completed_levels: BTreeSet<String>,
+    /// Points brought home from finished runs, to spend in the shop.
+    #[serde(alias = "candy_bank")]
+    pub points_bank: i32,
+    pub upgrades: Upgrades,
+    /// This player's own controls and aids, put in place whenever the profile is picked. A profile saved before it
+    /// had any keeps whatever the settings were.
+    pub preferences: Option<Preferences>,
+}
+
+/// The part of the settings that belongs to a player rather than the machine: controls, accessibility options and
+/// assists. Volume, window mode and the like stay with the machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preferences {
+    pub key_bindings: KeyBindings,
+    pub confirm_on_release: bool,
+    pub animation_speed: AnimationSpeed,
+    pub auto_resolve_idle_turns: bool,
+    pub focus_mode: bool,
+    pub reduce_motion: bool,
+    pub palette: PaletteChoice,
+}
+
+impl Preferences {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            key_bindings: settings.key_bindings,
+            confirm_on_release: settings.confirm_on_release,
+            animation_speed: settings.animation_speed,
+            auto_resolve_idle_turns: settings.auto_resolve_idle_turns,
+            focus_mode: settings.focus_mode,
+            reduce_motion: settings.reduce_motion,
+            palette: settings.palette,
+        }
+    }
+
+    pub fn apply(&self, settings: &mut Settings) {
+        settings.key_bindings = self.key_bindings;
+        settings.confirm_on_release = self.confirm_on_release;
+        settings.animation_speed = self.animation_speed;
+        settings.auto_resolve_idle_turns = self.auto_resolve_idle_turns;
+        settings.focus_mode = self.focus_mode;
+        settings.reduce_motion = self.reduce_motion;
+        settings.palette = self.palette;
+    }
+}
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+
+use crate::{AppState, DespawnOnExitPlaying, LoopCounter, Recordings, SootId, StartLoop, GRID_SPACING};
+use crate::level_spec::LevelSpec;
+use crate::palette::Palette;
+use crate::replay::route_cells;
+use crate::settings::Settings;
+use crate::spawn_level::SpawnLevel;
+
+/// Over the floor and walls, under the items.
+const PATH_Z: f32 = 0.15;
+const LINE_WIDTH: f32 = 6.;
+const BREADCRUMB_SIZE: f32 = 14.;
+/// How far apart the routes of different loops are drawn, so ones that share cells stay apart.
+const LOOP_OFFSET: f32 = 10.;
+const PATH_ALPHA: f32 = 0.6;
+
+pub struct RecordedPathsPlugin;
+
+impl Plugin for RecordedPathsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Playing), draw_recorded_paths.after(SpawnLevel).in_set(StartLoop));
+    }
+}
+
+// Past recordings don't change during a loop, so the paths are drawn once as it starts. They're a trail like any
+// other, so low-spec graphics go without.
+fn draw_recorded_paths(
+    mut commands: Commands,
+    loop_counter: Res<LoopCounter>,
+    recordings: Res<Recordings>,
+    level_spec: Res<LevelSpec>,
+    palette: Res<Palette>,
+    settings: Res<Settings>,
+) {
+    if settings.low_spec {
+        return;
+    }
+    for loop_num in 1..=loop_counter.0 {
+        let Some(recording) = recordings.for_soot(SootId::Recording(loop_num)) else {
+            continue;
+        };
+        let played_in = loop_counter.0 - loop_num;
+        let color = palette.loop_color(played_in).with_a(PATH_ALPHA);
+        // Spread around the cell centers, first loop up and to the left.
+        let offset = Vec2::new(-1., 1.) * LOOP_OFFSET * (played_in as f32 - (loop_counter.0 - 1) as f32 / 2.);
+        let mut cells = route_cells(&level_spec, level_spec.start_for_loop(played_in), recording.moves.iter().copied());
+        cells.dedup();
+
+        let center = |cell: IVec2| (cell * GRID_SPACING).as_vec2() + offset;
+        for &cell in cells.iter() {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(BREADCRUMB_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(center(cell).extend(PATH_Z)),
+                    ..default()
+                },
+                DespawnOnExitPlaying,
+            ));
+        }
+        for step in cells.windows(2) {
+            let (from, to) = (center(step[0]), center(step[1]));
+            let along = to - from;
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::new(along.length(), LINE_WIDTH)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(from.lerp(to, 0.5).extend(PATH_Z))
+                        .with_rotation(Quat::from_rotation_z(along.y.atan2(along.x))),
+                    ..default()
+                },
+                DespawnOnExitPlaying,
+            ));
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{next_turn, AppState, DespawnOnExitGameOver, LoopCounter, Player, Recordings, SootSprite, GRID_SPACING, SOOT_Z};
+use crate::level_spec::{BoardTransform, LevelSpec};
+use crate::storage::{self, save_path};
+use crate::rules::GameRules;
+use crate::spawn_level::{LevelSeed, LevelSource};
+use crate::toast::Toast;
+
+const GHOST_COLOR: Color = Color::rgba(0.5, 0.8, 1., 0.4);
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(ReplayGhost(None))
+            .add_event::<ExportReplay>()
+            // Watching starts from the main menu; anything else played afterwards is without a ghost.
+            .add_systems(OnEnter(AppState::MainMenu), stop_watching)
+            .add_systems(OnEnter(AppState::Editor), stop_watching)
+            .add_systems(Update, export_replay.run_if(on_event::<ExportReplay>()))
+            // After next_turn, so the ghost keeps pace with the player's turn count.
+            .add_systems(Update, move_replay_ghost.after(next_turn).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Asks for the run that just finished to be written out as a replay file.
+#[derive(Event)]
+pub struct ExportReplay;
+
+/// A replay being watched: a ghost walks its moves alongside the player, one move per player turn.
+#[derive(Resource)]
+pub struct ReplayGhost(pub Option<Replay>);
+
+type Cell = (i32, i32);
+
+/// Every move of a run, one list per loop in the order they were played, plus what's needed to rebuild the board.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    level: ReplayLevel,
+    loops: Vec<Vec<Cell>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum ReplayLevel {
+    Generated {seed: u64, transform: BoardTransform},
+    File(PathBuf),
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = storage::read(path)?;
+        ron::from_str(&contents).map_err(|err| format!("couldn't parse {}: {}", path.display(), err))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| format!("couldn't serialize replay: {}", err))?;
+        storage::write(path, &contents)
+    }
+
+    /// Sets up the next run on the board this replay was recorded on.
+    pub fn select_level(&self, level_source: &mut LevelSource, level_seed: &mut LevelSeed, rules: &mut GameRules) {
+        match &self.level {
+            ReplayLevel::Generated {seed, transform} => {
+                *level_source = LevelSource::Generated;
+                *level_seed = LevelSeed::pinned(*seed);
+                rules.board_transform = *transform;
+            },
+            ReplayLevel::File(path) => *level_source = LevelSource::File(path.clone()),
+        }
+    }
+}
+
+/// Every cell a soot visits making `moves` from `start`, beginning with `start` itself.
+pub fn route_cells(level_spec: &LevelSpec, start: IVec2, moves: impl IntoIterator<Item = IVec2>) -> Vec<IVec2> {
+    let mut cells = vec![start];
+    for offset in moves {
+        cells.push(level_spec.landing_cell(*cells.last().unwrap(), offset));
+    }
+    cells
+}
+
+fn stop_watching(mut replay_ghost: ResMut<ReplayGhost>) {
+    replay_ghost.0 = None;
+}
+
+fn export_replay(
+    mut events: EventReader<ExportReplay>,
+    recordings: Res<Recordings>,
+    level_source: Res<LevelSource>,
+    level_seed: Res<LevelSeed>,
+    rules: Res<GameRules>,
+    mut toasts: EventWriter<Toast>,
+) {
+    events.clear();
+    let level = match level_source.as_ref() {
+        LevelSource::Generated => ReplayLevel::Generated {seed: level_seed.seed, transform: rules.board_transform},
+        LevelSource::File(path) => ReplayLevel::File(path.clone()),
+    };
+    // Recordings are newest first.
+    let loops = recordings.0.iter().rev()
+        .map(|recording| recording.moves.iter().map(|offset| (offset.x, offset.y)).collect())
+        .collect();
+    let replay = Replay {level, loops};
+
+    let timestamp = storage::unix_time();
+    let Some(path) = save_path(&format!("replays/replay-{}.ron", timestamp)) else {
+        warn!("Couldn't save replay: no home directory to save to");
+        toasts.send(Toast::warning("Couldn't save replay: no home directory to save to"));
+        return;
+    };
+    match replay.save(&path) {
+        Ok(()) => {
+            info!("Saved replay to {}", path.display());
+            toasts.send(Toast::info(format!("Saved replay to {}", path.display())));
+        },
+        Err(err) => {
+            warn!("Couldn't save replay: {}", err);
+            toasts.send(Toast::warning(format!("Couldn't save replay: {}", err)));
+        },
+    }
+}
+
+#[derive(Component)]
+struct Ghost;
+
+fn move_replay_ghost(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    replay_ghost: Res<ReplayGhost>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    player: Query<&SootSprite, With<Player>>,
+    mut ghosts: Query<&mut Transform, With<Ghost>>,
+) {
+    let Some(replay) = &replay_ghost.0 else {
+        return;
+    };
+    let (Some(moves), Ok(player)) = (replay.loops.get(loop_counter.0 as usize), player.get_single()) else {
+        return;
+    };
+
+    let level_spec = level_spec.for_loop(loop_counter.0);
+    let cells = route_cells(&level_spec, level_spec.start, moves.iter().map(|&(x, y)| IVec2::new(x, y)));
+    let location = cells[(player.turn_number as usize).min(cells.len() - 1)];
+    let translation = (location * GRID_SPACING).as_vec2().extend(SOOT_Z - 0.1);
+
+    match ghosts.get_single_mut() {
+        Ok(mut transform) => transform.translation = translation,
+        Err(_) => {
+            commands.spawn((
+                Ghost,
+                SpriteBundle {
+                    texture: asset_server.load("soot-sprite.png"),
+                    sprite: Sprite {color: GHOST_COLOR, ..default()},
+                    transform: Transform::from_translation(translation),
+                    ..default()
+                },
+                DespawnOnExitGameOver,
+            ));
+        },
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+
+use crate::{AppState, DespawnOnExitGameOver, LoopCounter, Recordings, SootId, SootSprite, StartLoop, GRID_SPACING, SOOT_Z};
+use crate::intents::MoveIntent;
+use crate::level_spec::LevelSpec;
+use crate::palette::Palette;
+use crate::replay::route_cells;
+use crate::spawn_level::SpawnLevel;
+
+/// Slowest a rewind goes, in cells per second. Longer routes speed up so every rewind fits in `MAX_REWIND_SECS`.
+const REWIND_CELLS_PER_SEC: f32 = 20.;
+const MAX_REWIND_SECS: f32 = 1.5;
+const REWIND_GHOST_ALPHA: f32 = 0.6;
+
+pub struct RewindPlugin;
+
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Playing), start_rewind.after(SpawnLevel).in_set(StartLoop))
+            .add_systems(Update, (skip_rewind, play_rewind).chain().run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Stands in for a past self at the start of a loop, running its recorded route backwards from where it finished
+/// to where it starts again. The past self itself stays hidden until its ghost gets there.
+#[derive(Component)]
+struct RewindGhost {
+    soot: SootId,
+    /// Finish first, start last.
+    path: Vec<IVec2>,
+    cells_per_sec: f32,
+    elapsed: f32,
+}
+
+impl RewindGhost {
+    fn finished(&self) -> bool {
+        self.elapsed * self.cells_per_sec >= (self.path.len() - 1) as f32
+    }
+
+    fn position(&self) -> Vec2 {
+        let progress = (self.elapsed * self.cells_per_sec).min((self.path.len() - 1) as f32);
+        let index = progress as usize;
+        let from = (self.path[index] * GRID_SPACING).as_vec2();
+        let to = (self.path[(index + 1).min(self.path.len() - 1)] * GRID_SPACING).as_vec2();
+        from.lerp(to, progress.fract())
+    }
+}
+
+fn start_rewind(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    loop_counter: Res<LoopCounter>,
+    recordings: Res<Recordings>,
+    level_spec: Res<LevelSpec>,
+    palette: Res<Palette>,
+) {
+    // Past selves are the loops before this one; the first loop of a run has nobody to rewind.
+    for loop_num in 1..=loop_counter.0 {
+        let soot = SootId::Recording(loop_num);
+        let Some(recording) = recordings.for_soot(soot) else {
+            continue;
+        };
+        let level_spec = level_spec.for_loop(loop_counter.0 - loop_num);
+        let mut path = route_cells(&level_spec, level_spec.start, recording.moves.iter().copied());
+        path.dedup();
+        if path.len() < 2 {
+            continue;
+        }
+        path.reverse();
+        let cells_per_sec = REWIND_CELLS_PER_SEC.max((path.len() - 1) as f32 / MAX_REWIND_SECS);
+
+        let ghost = RewindGhost {soot, path, cells_per_sec, elapsed: 0.};
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load("soot-sprite.png"),
+                sprite: Sprite {
+                    color: palette.past_self.with_a(REWIND_GHOST_ALPHA),
+                    ..default()
+                },
+                transform: Transform::from_translation(ghost.position().extend(SOOT_Z)),
+                ..default()
+            },
+            ghost,
+            DespawnOnExitGameOver,
+        ));
+    }
+}
+
+// The player doesn't have to sit through it: the first move cuts straight to the loop.
+fn skip_rewind(mut intents: EventReader<MoveIntent>, mut ghosts: Query<&mut RewindGhost>) {
+    if intents.is_empty() {
+        return;
+    }
+    intents.clear();
+    for mut ghost in ghosts.iter_mut() {
+        ghost.elapsed = f32::INFINITY;
+    }
+}
+
+fn play_rewind(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut ghosts: Query<(Entity, &mut RewindGhost, &mut Transform)>,
+    mut soots: Query<(&SootSprite, &mut Visibility)>,
+) {
+    for (entity, mut ghost, mut transform) in ghosts.iter_mut() {
+        ghost.elapsed += time.delta_seconds();
+        let finished = ghost.finished();
+        for (_, mut visibility) in soots.iter_mut().filter(|(soot, _)| soot.id == ghost.soot) {
+            *visibility = if finished { Visibility::Inherited } else { Visibility::Hidden };
+        }
+        if finished {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.translation = ghost.position().extend(SOOT_Z);
+    }
+}
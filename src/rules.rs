@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::NUM_LOOPS;
+use crate::level_spec::BoardTransform;
+use crate::shop::Upgrades;
+
+/// Seconds per move in the timed variant, unless a level asks for something else.
+const DEFAULT_TURN_TIME: f32 = 3.;
+
+/// Moves each soot gets per loop in the move budget variant, unless a level asks for something else.
+const DEFAULT_TURN_LIMIT: i32 = 14;
+
+/// Rounds uncollected candy lasts in the decay variant, unless a level asks for something else.
+const DEFAULT_CANDY_DECAY: i32 = 12;
+
+/// Candy quota for `--collect-all`: more than any board holds, so every piece has to be picked up.
+const COLLECT_ALL: i32 = i32::MAX;
+
+/// How far soots see under `--fog`, in steps.
+const DEFAULT_FOG_RADIUS: i32 = 1;
+
+/// Candy it costs to run into a past self under the paradox rule, unless a level asks for something else.
+const DEFAULT_PARADOX_PENALTY: i32 = 1;
+
+/// Optional rule variants for a run. Everything defaults to the classic rules.
+#[derive(Resource, Debug, Clone)]
+pub struct GameRules {
+    /// Lets the player take everything a past self is carrying while they share a cell.
+    pub item_trading: bool,
+    /// Mutator applied to every generated board, see `BoardTransform`.
+    pub board_transform: BoardTransform,
+    /// Scoring variant: soots paint every cell they enter, and the goal is to paint the whole board.
+    pub painting: bool,
+    /// Fixed number of loops in a run, ignoring the extra loops bought in the shop.
+    pub loops: Option<i32>,
+    /// Whether past selves pick items up, taking them away from the present.
+    pub interference: bool,
+    /// Most moves a soot gets per loop.
+    pub turn_limit: Option<i32>,
+    /// Seconds the player gets to pick each move; a turn left to run out is spent waiting.
+    pub turn_time: Option<f32>,
+    /// Rounds before candy nobody has picked up hardens into a wall.
+    pub candy_decay: Option<i32>,
+    /// Candy the soots have to pick up between them each loop before the exits open. Asking for more than is left to
+    /// pick up means all of it.
+    pub candy_quota: Option<i32>,
+    /// Only cells within this many steps of a soot can be seen; the rest of the board is fogged over.
+    pub fog_radius: Option<i32>,
+    /// What happens when one soot moves onto another.
+    pub collisions: Collisions,
+    /// Loops added to this run by time crystals picked up along the way, on top of any fixed or bought ones.
+    pub bonus_loops: i32,
+}
+
+/// What happens when a soot moves onto a cell another soot is standing on. Soots that have reached their exit are
+/// out of the way either way.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Collisions {
+    /// Nothing: soots share the cell.
+    #[default]
+    PassThrough,
+    /// The cell counts as blocked.
+    Block,
+    /// The soot standing there is pushed back to where the mover came from.
+    Swap,
+    /// Soots can share the cell, but the one moving in loses this much candy for it.
+    Paradox(i32),
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            item_trading: false,
+            board_transform: default(),
+            painting: false,
+            loops: None,
+            interference: true,
+            turn_limit: None,
+            turn_time: None,
+            candy_decay: None,
+            candy_quota: None,
+            fog_radius: None,
+            collisions: default(),
+            bonus_loops: 0,
+        }
+    }
+}
+
+/// Rule changes a level file can ask for, so a level can teach one mechanic without touching anyone's settings.
+/// Anything left out plays by the rules the game was launched with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleOverrides {
+    pub loops: Option<i32>,
+    /// Turning this off also leaves nether candy uncollectable, since only past selves can pick it up.
+    pub interference: Option<bool>,
+    pub turn_limit: Option<i32>,
+    pub turn_time: Option<f32>,
+    pub candy_decay: Option<i32>,
+    pub candy_quota: Option<i32>,
+    pub fog_radius: Option<i32>,
+    /// e.g. `collisions: Some(Paradox(2))`.
+    pub collisions: Option<Collisions>,
+}
+
+impl GameRules {
+    /// Rule variants can be switched on from the command line, e.g. `interference-factory --trading`.
+    pub fn from_args() -> Self {
+        let mut rules = GameRules::default();
+        let (mut mirror, mut rotate) = (false, false);
+        for arg in std::env::args().skip(1) {
+            match arg.as_str() {
+                "--trading" => rules.item_trading = true,
+                "--mirror" => mirror = true,
+                "--rotate" => rotate = true,
+                "--painting" => rules.painting = true,
+                "--timed" => rules.turn_time = Some(DEFAULT_TURN_TIME),
+                "--move-budget" => rules.turn_limit = Some(DEFAULT_TURN_LIMIT),
+                "--decay" => rules.candy_decay = Some(DEFAULT_CANDY_DECAY),
+                "--collect-all" => rules.candy_quota = Some(COLLECT_ALL),
+                "--fog" => rules.fog_radius = Some(DEFAULT_FOG_RADIUS),
+                "--blocking" => rules.collisions = Collisions::Block,
+                "--swapping" => rules.collisions = Collisions::Swap,
+                "--paradox" => rules.collisions = Collisions::Paradox(DEFAULT_PARADOX_PENALTY),
+                _ => {},
+            }
+        }
+        rules.board_transform = BoardTransform::new(mirror, rotate);
+        rules
+    }
+
+    /// Puts the level's overrides in place for the run, replacing whatever the previous level asked for.
+    pub fn apply_overrides(&mut self, overrides: &RuleOverrides) {
+        let launch = GameRules::from_args();
+        self.loops = overrides.loops.or(launch.loops);
+        self.interference = overrides.interference.unwrap_or(launch.interference);
+        self.turn_limit = overrides.turn_limit.or(launch.turn_limit);
+        self.turn_time = overrides.turn_time.or(launch.turn_time);
+        self.candy_decay = overrides.candy_decay.or(launch.candy_decay);
+        self.candy_quota = overrides.candy_quota.or(launch.candy_quota);
+        self.fog_radius = overrides.fog_radius.or(launch.fog_radius);
+        self.collisions = overrides.collisions.unwrap_or(launch.collisions);
+        self.bonus_loops = 0;
+    }
+
+    pub fn num_loops(&self, upgrades: &Upgrades) -> i32 {
+        self.loops.unwrap_or(NUM_LOOPS + upgrades.extra_loops) + self.bonus_loops
+    }
+
+    pub fn out_of_turns(&self, turn_number: i32) -> bool {
+        self.turn_limit.is_some_and(|limit| turn_number >= limit)
+    }
+
+    /// Candy still to pick up this loop before the exits open, given how much has been picked up and how much is left.
+    pub fn candy_needed(&self, collected: i32, remaining: i32) -> i32 {
+        self.candy_quota.map_or(0, |quota| quota.min(collected + remaining) - collected).max(0)
+    }
+
+    pub fn exits_open(&self, collected: i32, remaining: i32) -> bool {
+        self.candy_needed(collected, remaining) == 0
+    }
+}
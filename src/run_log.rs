@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use crate::{validate_move, AppState, LoopCounter, Move, SootId, SootSprite, StartLoop};
+use crate::inventory::{Item, ItemGet, PickUpItems};
+
+pub struct RunLogPlugin;
+
+impl Plugin for RunLogPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(RunLog::default())
+            .add_systems(OnEnter(AppState::Playing), start_loop_log.in_set(StartLoop))
+            .add_systems(Update, (
+                log_fuel_spent.after(validate_move),
+                log_pickups.after(PickUpItems),
+            ).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Everything notable that's happened in the current loop, oldest first.
+#[derive(Resource, Default)]
+pub struct RunLog(pub Vec<RunEvent>);
+
+#[derive(Clone, Copy)]
+pub enum RunEvent {
+    LoopStarted(i32),
+    Pickup {soot: SootId, item: Item},
+    FuelSpent {soot: SootId, offset: IVec2, amount: i32},
+}
+
+impl RunEvent {
+    pub fn describe(&self) -> String {
+        let who = |soot: &SootId| match soot {
+            SootId::Player => "You".to_string(),
+            SootId::Recording(loop_number) => format!("Past self {}", loop_number),
+        };
+        match self {
+            RunEvent::LoopStarted(loop_number) => format!("Loop {} begins", loop_number + 1),
+            RunEvent::Pickup {soot, item} => format!("{} collected {}", who(soot), item.name()),
+            RunEvent::FuelSpent {soot, offset, amount} => {
+                let direction = if offset.y > 0 { "up" } else { "left" };
+                format!("{} spent {} fuel moving {}", who(soot), amount, direction)
+            },
+        }
+    }
+}
+
+fn start_loop_log(mut log: ResMut<RunLog>, loop_counter: Res<LoopCounter>) {
+    log.0.clear();
+    log.0.push(RunEvent::LoopStarted(loop_counter.0));
+}
+
+fn log_fuel_spent(mut log: ResMut<RunLog>, mut moves: EventReader<Move>, soots: Query<&SootSprite>) {
+    for &Move{mover, offset, fuel_cost, ..} in moves.iter() {
+        let Ok(soot) = soots.get(mover) else {
+            continue;
+        };
+        if fuel_cost > 0 {
+            log.0.push(RunEvent::FuelSpent {soot: soot.id, offset, amount: fuel_cost});
+        }
+    }
+}
+
+fn log_pickups(mut log: ResMut<RunLog>, mut pickups: EventReader<ItemGet>, soots: Query<&SootSprite>) {
+    for pickup in pickups.iter() {
+        if let Ok(soot) = soots.get(pickup.soot) {
+            log.0.push(RunEvent::Pickup {soot: soot.id, item: pickup.item});
+        }
+    }
+}
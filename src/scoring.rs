@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+
+use crate::{next_turn, AppState, LoopCounter, Player, Recordings, RoundCounter, SootId, SootSprite, StartLoop};
+use crate::grid::MovementComplete;
+use crate::inventory::{Inventory, ItemGet, PickUpItems};
+use crate::level_spec::LevelSpec;
+use crate::replay::route_cells;
+use crate::rules::GameRules;
+use crate::toast::Toast;
+
+/// Extra points a turn that picks up candy earns for each candy turn straight before it.
+const COMBO_BONUS: i32 = 1;
+/// Extra points for the player and a past self picking up candy in the same round, for each past self that does.
+const SAME_ROUND_BONUS: i32 = 2;
+/// Extra points for the player picking up candy the loop before walked right past.
+const SKIPPED_CANDY_BONUS: i32 = 2;
+/// Where a route has to pass for the candy it didn't take to count as skipped.
+const NEIGHBOURS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+pub struct ScoringPlugin;
+
+impl Plugin for ScoringPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(FinishedLoopsScore(0))
+            .add_systems(OnEnter(AppState::Playing), clear_finished_loops_score.in_set(StartLoop))
+            .add_systems(OnEnter(AppState::LoopTransition), bank_finished_loop)
+            .add_systems(Update, (count_combos, award_synergy).chain()
+                .after(PickUpItems).before(next_turn).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// What a soot has scored on top of the candy it carries, for the way it went about collecting it.
+#[derive(Component, Default, Clone, Copy)]
+pub struct Score {
+    /// Turns in a row, up to the last one, that the soot picked up candy on.
+    pub combo: i32,
+    /// The round the soot last picked up candy in, to match up with what the other soots picked up.
+    pub candy_round: Option<i32>,
+    pub bonus: i32,
+    /// From award_synergy, which only the player earns. A past self replaying the loop doesn't earn it again.
+    pub synergy: i32,
+}
+
+impl Score {
+    pub fn total(&self, inventory: &Inventory) -> i32 {
+        inventory.points + self.bonus + self.synergy
+    }
+}
+
+/// Points from the loops of this run that are already over, where nobody on the board is carrying them any more. The
+/// board is laid out afresh every loop, so with interference past selves pick their candy up all over again, combos
+/// and all, and only each loop's synergy bonuses are banked here; without it they come away empty-handed, so each
+/// loop's player banks their whole score here instead.
+#[derive(Resource)]
+pub struct FinishedLoopsScore(pub i32);
+
+impl FinishedLoopsScore {
+    /// The whole run's score so far, given every soot on the board this loop.
+    pub fn run_total<'a>(&self, soots: impl IntoIterator<Item = (&'a Inventory, &'a Score)>) -> i32 {
+        self.0 + soots.into_iter().map(|(inventory, score)| score.total(inventory)).sum::<i32>()
+    }
+}
+
+fn clear_finished_loops_score(loop_counter: Res<LoopCounter>, mut finished: ResMut<FinishedLoopsScore>) {
+    if loop_counter.0 == 0 {
+        finished.0 = 0;
+    }
+}
+
+fn bank_finished_loop(
+    mut finished: ResMut<FinishedLoopsScore>,
+    rules: Res<GameRules>,
+    player: Query<(&Inventory, &Score), With<Player>>,
+) {
+    for (inventory, score) in player.iter() {
+        finished.0 += if rules.interference { score.synergy } else { score.total(inventory) };
+    }
+}
+
+// A soot's pickups come in on the frame it lands, so each landing settles whether the combo carries on. A wait counts
+// as a turn like any other, and breaks it.
+fn count_combos(
+    mut landings: EventReader<MovementComplete>,
+    mut pickups: EventReader<ItemGet>,
+    mut scores: Query<&mut Score>,
+) {
+    let pickups: Vec<Entity> = pickups.iter()
+        .filter(|pickup| pickup.item.is_candy())
+        .map(|pickup| pickup.soot)
+        .collect();
+    for landing in landings.iter() {
+        let Ok(mut score) = scores.get_mut(landing.entity) else {
+            continue;
+        };
+        if !pickups.contains(&landing.entity) {
+            score.combo = 0;
+            continue;
+        }
+        if score.combo > 0 {
+            score.bonus += COMBO_BONUS * score.combo;
+        }
+        score.combo += 1;
+    }
+}
+
+// Rewards planning across loops, so the bonuses all go to the player, and are banked at the end of the loop. A round's
+// pair is only counted the first time each soot in it picks up candy, however many pieces that is.
+fn award_synergy(
+    mut pickups: EventReader<ItemGet>,
+    round_counter: Res<RoundCounter>,
+    loop_counter: Res<LoopCounter>,
+    level_spec: Res<LevelSpec>,
+    recordings: Res<Recordings>,
+    mut soots: Query<(&SootSprite, &mut Score)>,
+    player: Query<Entity, With<Player>>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let pickups: Vec<&ItemGet> = pickups.iter().filter(|pickup| pickup.item.is_candy()).collect();
+    if pickups.is_empty() {
+        return;
+    }
+    let round = round_counter.0;
+    // Where the last loop went, as recorded, which is what the player had to plan around.
+    let last_route = recordings.for_soot(SootId::Recording(1))
+        .filter(|_| loop_counter.0 > 0)
+        .map(|recording| route_cells(&level_spec, level_spec.start_for_loop(loop_counter.0 - 1), recording.moves.iter().copied()))
+        .unwrap_or_default();
+
+    for pickup in pickups {
+        let Ok((soot, score)) = soots.get(pickup.soot) else {
+            continue;
+        };
+        let is_player = soot.id == SootId::Player;
+        let mut bonus = 0;
+        if score.candy_round != Some(round) {
+            let partners = soots.iter()
+                .filter(|(other, other_score)| {
+                    (other.id == SootId::Player) != is_player && other_score.candy_round == Some(round)
+                })
+                .count() as i32;
+            if partners > 0 {
+                bonus += SAME_ROUND_BONUS * partners;
+                toasts.send(Toast::success(format!("In step with a past self! +{}", SAME_ROUND_BONUS * partners)));
+            }
+        }
+        let skipped = !last_route.contains(&pickup.cell)
+            && NEIGHBOURS.iter().any(|&offset| last_route.contains(&(pickup.cell + offset)));
+        if is_player && skipped {
+            bonus += SKIPPED_CANDY_BONUS;
+            toasts.send(Toast::success(format!("Saved for later! +{}", SKIPPED_CANDY_BONUS)));
+        }
+
+        if let Ok((_, mut score)) = soots.get_mut(pickup.soot) {
+            score.candy_round = Some(round);
+        }
+        if bonus > 0 {
+            if let Ok((_, mut score)) = soots.get_mut(player) {
+                score.synergy += bonus;
+            }
+        }
+    }
+}
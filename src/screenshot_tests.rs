@@ -0,0 +1,376 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::prelude::*;
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::render::camera::{CameraUpdateSystem, RenderTarget};
+use bevy::render::main_graph::node::CAMERA_DRIVER;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext};
+use bevy::render::render_resource::{
+    Buffer, BufferDescriptor, BufferUsages, Extent3d, ImageCopyBuffer, ImageDataLayout, MapMode, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::texture::{BevyDefault, TextureFormatPixelInfo};
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::{ExitCondition, PrimaryWindow, WindowResolution};
+use bevy::winit::WinitPlugin;
+
+use crate::AppState;
+use crate::spawn_level::LevelSeed;
+
+const GOLDEN_DIR: &str = "tests/screenshots";
+const WIDTH: u32 = 1280;
+const HEIGHT: u32 = 720;
+/// Every frame advances the clock by exactly this much, so animations are at the same point on every run.
+const FRAME_TIME: Duration = Duration::from_nanos(16_666_667);
+/// Long enough for a screen to finish fading and sliding in.
+const SETTLE_FRAMES: u32 = 90;
+const SEED: u64 = 1;
+/// How far apart a channel can be before a pixel counts as different; covers GPU and driver rounding.
+const CHANNEL_TOLERANCE: u8 = 8;
+/// Share of pixels that can differ before a screen fails, for antialiasing and font rasterization noise.
+const PIXEL_TOLERANCE: f64 = 0.002;
+const READBACK_NODE: &str = "screenshot_readback";
+
+/// The game with no window at all: the cameras draw into an image instead, which is read back for the captures. Still
+/// needs a GPU, though a software adapter like lavapipe does fine.
+pub fn headless_app() -> App {
+    let mut app = crate::app_with(DefaultPlugins
+        .set(WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        })
+        .disable::<WinitPlugin>());
+    // Winit drove the frames; without it, this does, as fast as they'll go.
+    app.add_plugins(ScheduleRunnerPlugin::run_loop(Duration::ZERO));
+    app
+}
+
+/// Walks the game through its main screens, capturing each one and comparing it against the golden image with the
+/// same name. Differing captures are written next to the goldens as `<name>.actual.png`, and so are captures of
+/// screens with no golden yet; `update` records the goldens instead. Exits non-zero if any screen differs or has no
+/// golden.
+///
+/// Goes with `headless_app`, which it draws into instead of a window.
+pub struct ScreenshotTestPlugin {
+    pub update: bool,
+}
+
+impl ScreenshotTestPlugin {
+    /// `--update` re-records the goldens.
+    pub fn from_args() -> Self {
+        Self {update: std::env::args().any(|arg| arg == "--update")}
+    }
+}
+
+impl Plugin for ScreenshotTestPlugin {
+    fn build(&self, app: &mut App) {
+        let target = app.world.resource_mut::<Assets<Image>>().add(capture_target());
+        let readback = Readback {target, state: default()};
+        let captures: Arc<Mutex<Vec<(&'static str, Image)>>> = default();
+        app
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME))
+            .insert_resource(LevelSeed::pinned(SEED))
+            .insert_resource(readback.clone())
+            .insert_resource(Script {
+                step: 0,
+                waiting: 0,
+                held_key: None,
+                captures: captures.clone(),
+                expected_captures: 0,
+                update: self.update,
+            })
+            .add_systems(Startup, spawn_virtual_window)
+            .add_systems(First, run_script)
+            .add_systems(PostUpdate, draw_into_target.before(CameraUpdateSystem));
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .insert_resource(readback)
+            .insert_resource(Captures(captures))
+            .add_systems(Render, finish_readback.in_set(RenderSet::Cleanup));
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        graph.add_node(READBACK_NODE, ReadbackNode);
+        graph.add_node_edge(CAMERA_DRIVER, READBACK_NODE);
+    }
+}
+
+enum Step {
+    Go(AppState),
+    Press(KeyCode),
+    Wait(u32),
+    Capture(&'static str),
+}
+
+const SCRIPT: [Step; 14] = [
+    Step::Go(AppState::Playing),
+    Step::Wait(SETTLE_FRAMES),
+    Step::Capture("hud"),
+    Step::Press(KeyCode::Escape),
+    Step::Wait(SETTLE_FRAMES),
+    Step::Capture("pause"),
+    Step::Press(KeyCode::Escape),
+    Step::Wait(SETTLE_FRAMES),
+    Step::Go(AppState::GameOver),
+    Step::Wait(SETTLE_FRAMES),
+    Step::Capture("game_over"),
+    Step::Go(AppState::Shop),
+    Step::Wait(SETTLE_FRAMES),
+    Step::Capture("shop"),
+];
+
+#[derive(Resource)]
+struct Script {
+    step: usize,
+    waiting: u32,
+    held_key: Option<KeyCode>,
+    /// Filled in by the render world, which reads a capture back a frame or two after it's asked for.
+    captures: Arc<Mutex<Vec<(&'static str, Image)>>>,
+    expected_captures: usize,
+    update: bool,
+}
+
+/// What the cameras draw into, and what's been asked of it.
+#[derive(Resource, Clone)]
+struct Readback {
+    target: Handle<Image>,
+    state: Arc<Mutex<ReadbackState>>,
+}
+
+#[derive(Default)]
+struct ReadbackState {
+    /// A capture to copy out of the target once it's next drawn.
+    requested: Option<&'static str>,
+    /// Copied out, and waiting for the GPU to hand it over.
+    copying: Option<Copying>,
+}
+
+struct Copying {
+    name: &'static str,
+    buffer: Buffer,
+    /// Bytes per row in `buffer`, which the GPU pads out past the image's own.
+    padded_row: usize,
+    mapping: bool,
+    mapped: Arc<AtomicBool>,
+}
+
+#[derive(Resource)]
+struct Captures(Arc<Mutex<Vec<(&'static str, Image)>>>);
+
+/// Captures are compared pixel for pixel, so they all come out the same size.
+fn capture_target() -> Image {
+    let size = Extent3d {width: WIDTH, height: HEIGHT, depth_or_array_layers: 1};
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("screenshot target"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::bevy_default(),
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    image
+}
+
+// Nothing's ever shown in it, but the UI is laid out against the primary window, and the key presses need one to come
+// from.
+fn spawn_virtual_window(mut commands: Commands) {
+    commands.spawn((
+        Window {
+            resolution: WindowResolution::new(WIDTH as f32, HEIGHT as f32).with_scale_factor_override(1.),
+            ..default()
+        },
+        PrimaryWindow,
+    ));
+}
+
+fn draw_into_target(readback: Res<Readback>, mut cameras: Query<&mut Camera, Added<Camera>>) {
+    for mut camera in cameras.iter_mut() {
+        camera.target = RenderTarget::Image(readback.target.clone());
+    }
+}
+
+fn run_script(
+    mut script: ResMut<Script>,
+    readback: Res<Readback>,
+    windows: Query<Entity, With<PrimaryWindow>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut keyboard: EventWriter<KeyboardInput>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    if let Some(key_code) = script.held_key.take() {
+        keyboard.send(KeyboardInput {scan_code: 0, key_code: Some(key_code), state: ButtonState::Released, window});
+    }
+    if script.waiting > 0 {
+        script.waiting -= 1;
+        return;
+    }
+    // Don't move on until the last capture has come back.
+    if script.captures.lock().unwrap().len() < script.expected_captures {
+        return;
+    }
+
+    let Some(step) = SCRIPT.get(script.step) else {
+        finish(&script);
+        return;
+    };
+    script.step += 1;
+    match step {
+        Step::Go(state) => next_state.set(*state),
+        Step::Press(key_code) => {
+            keyboard.send(KeyboardInput {scan_code: 0, key_code: Some(*key_code), state: ButtonState::Pressed, window});
+            script.held_key = Some(*key_code);
+        },
+        Step::Wait(frames) => script.waiting = *frames,
+        Step::Capture(name) => {
+            readback.state.lock().unwrap().requested = Some(*name);
+            script.expected_captures += 1;
+        },
+    }
+}
+
+/// Copies the target into a buffer the CPU can read, once the cameras have drawn into it.
+struct ReadbackNode;
+
+impl render_graph::Node for ReadbackNode {
+    fn run(&self, _graph: &mut RenderGraphContext, render_context: &mut RenderContext, world: &World) -> Result<(), NodeRunError> {
+        let readback = world.resource::<Readback>();
+        let mut state = readback.state.lock().unwrap();
+        let (Some(name), None) = (state.requested, &state.copying) else {
+            return Ok(());
+        };
+        let Some(image) = world.resource::<RenderAssets<Image>>().get(&readback.target) else {
+            return Ok(());
+        };
+
+        let size = Extent3d {width: WIDTH, height: HEIGHT, depth_or_array_layers: 1};
+        let padded_row = RenderDevice::align_copy_bytes_per_row(WIDTH as usize * image.texture_format.pixel_size());
+        let buffer = render_context.render_device().create_buffer(&BufferDescriptor {
+            label: Some("screenshot readback"),
+            size: (padded_row * HEIGHT as usize) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        render_context.command_encoder().copy_texture_to_buffer(
+            image.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {offset: 0, bytes_per_row: Some(padded_row as u32), rows_per_image: None},
+            },
+            size,
+        );
+        state.requested = None;
+        state.copying = Some(Copying {name, buffer, padded_row, mapping: false, mapped: default()});
+        Ok(())
+    }
+}
+
+// The copy's been submitted by now. Mapping is picked up by the GPU the next time anything's submitted, so it's a frame
+// or so before the data can be read.
+fn finish_readback(readback: Res<Readback>, captures: Res<Captures>, render_device: Res<RenderDevice>) {
+    let mut state = readback.state.lock().unwrap();
+    let Some(copying) = state.copying.as_mut() else {
+        return;
+    };
+    if !copying.mapping {
+        let mapped = copying.mapped.clone();
+        render_device.map_buffer(&copying.buffer.slice(..), MapMode::Read, move |result| {
+            result.expect("the screenshot readback buffer couldn't be mapped");
+            mapped.store(true, Ordering::Release);
+        });
+        copying.mapping = true;
+        return;
+    }
+    if !copying.mapped.load(Ordering::Acquire) {
+        return;
+    }
+
+    let Some(copying) = state.copying.take() else {
+        return;
+    };
+    let row = WIDTH as usize * TextureFormat::bevy_default().pixel_size();
+    let data: Vec<u8> = copying.buffer.slice(..).get_mapped_range()
+        .chunks(copying.padded_row)
+        .flat_map(|padded| padded[..row].iter().copied())
+        .collect();
+    copying.buffer.unmap();
+    let size = Extent3d {width: WIDTH, height: HEIGHT, depth_or_array_layers: 1};
+    let image = Image::new(size, TextureDimension::D2, data, TextureFormat::bevy_default());
+    captures.0.lock().unwrap().push((copying.name, image));
+}
+
+fn finish(script: &Script) {
+    let dir = PathBuf::from(GOLDEN_DIR);
+    let mut failed = false;
+    for (name, image) in script.captures.lock().unwrap().drain(..) {
+        let actual = match image.try_into_dynamic() {
+            Ok(actual) => actual.to_rgba8(),
+            Err(err) => {
+                error!("{}: couldn't read the capture: {}", name, err);
+                failed = true;
+                continue;
+            },
+        };
+        let golden_path = dir.join(format!("{}.png", name));
+        if script.update {
+            if let Err(err) = std::fs::create_dir_all(&dir).map_err(|err| err.to_string())
+                .and_then(|_| actual.save(&golden_path).map_err(|err| err.to_string())) {
+                error!("{}: couldn't write {}: {}", name, golden_path.display(), err);
+                failed = true;
+            } else {
+                info!("{}: recorded {}", name, golden_path.display());
+            }
+            continue;
+        }
+
+        // A missing golden fails like a differing one, so a checkout without goldens can't pass by comparing nothing.
+        let result = if golden_path.exists() {
+            image::open(&golden_path)
+                .map_err(|err| format!("couldn't read {}: {}", golden_path.display(), err))
+                .and_then(|golden| compare(&golden.to_rgba8(), &actual))
+        } else {
+            Err(format!("there's no golden at {}; record one with --update", golden_path.display()))
+        };
+        match result {
+            Ok(()) => info!("{}: matches", name),
+            Err(err) => {
+                error!("{}: {}", name, err);
+                let actual_path = dir.join(format!("{}.actual.png", name));
+                if let Err(err) = actual.save(&actual_path) {
+                    warn!("Couldn't write {}: {}", actual_path.display(), err);
+                }
+                failed = true;
+            },
+        }
+    }
+    std::process::exit(if failed { 1 } else { 0 });
+}
+
+fn compare(golden: &image::RgbaImage, actual: &image::RgbaImage) -> Result<(), String> {
+    if golden.dimensions() != actual.dimensions() {
+        return Err(format!("captured at {:?}, but the golden is {:?}", actual.dimensions(), golden.dimensions()));
+    }
+    let differing = golden.pixels().zip(actual.pixels())
+        .filter(|(expected, got)| expected.0.iter().zip(got.0.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+        .count();
+    let share = differing as f64 / (golden.width() * golden.height()) as f64;
+    if share > PIXEL_TOLERANCE {
+        return Err(format!("{:.2}% of pixels differ", share * 100.));
+    }
+    Ok(())
+}
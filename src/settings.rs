@@ -0,0 +1,405 @@
+use std::time::Duration;
+
+use bevy::audio::VolumeLevel;
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowMode};
+use serde::{Deserialize, Serialize};
+
+use crate::grid::AnimateTranslation;
+use crate::palette::PaletteChoice;
+use crate::toast::Toast;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Settings>()
+            .add_systems(Startup, reset_conflicting_key_bindings)
+            .add_systems(Update, (
+                cycle_animation_speed,
+                toggle_auto_resolve_idle_turns,
+                change_game_speed,
+                (apply_animation_speed, apply_game_speed, apply_volume, apply_window_mode, apply_low_spec).run_if(resource_changed::<Settings>()),
+            ).chain());
+    }
+}
+
+/// Player-facing preferences. Systems read these instead of hard-coding tuning values.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub animation_speed: AnimationSpeed,
+    /// Pass over past selves with nothing left to replay in the same frame, rather than spending a frame skipping each.
+    pub auto_resolve_idle_turns: bool,
+    /// Multiplier on game time; everything timed, from animations to the replay of past selves, runs at this rate.
+    pub game_speed: f32,
+    /// Lean the camera in toward the soot whose turn it is.
+    pub focus_mode: bool,
+    /// Keep the camera still, whatever else is turned on.
+    pub reduce_motion: bool,
+    /// Flat sprites only: no antialiasing, confetti, route trails, background art or waving flag, for old GPUs and
+    /// browsers.
+    pub low_spec: bool,
+    /// Colors for the board and HUD, including schemes for color vision deficiencies.
+    pub palette: PaletteChoice,
+    /// Master volume, from 0 (muted) to 1.
+    pub volume: f32,
+    pub window_mode: WindowMode,
+    pub key_bindings: KeyBindings,
+    /// Move when a direction key is let go rather than when it's pressed, previewing where it leads while held.
+    pub confirm_on_release: bool,
+    /// Which profile is in use. Controls and aids are kept per profile and copied in here when it's picked.
+    pub profile_slot: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            animation_speed: default(),
+            auto_resolve_idle_turns: true,
+            game_speed: 1.,
+            focus_mode: false,
+            reduce_motion: false,
+            low_spec: false,
+            palette: default(),
+            volume: 1.,
+            window_mode: WindowMode::Windowed,
+            key_bindings: default(),
+            confirm_on_release: false,
+            profile_slot: 0,
+        }
+    }
+}
+
+const GAME_SPEEDS: [f32; 6] = [0.5, 1., 1.5, 2., 3., 4.];
+
+impl Settings {
+    /// Steps the game speed one notch up or down, stopping at either end.
+    pub fn step_game_speed(&mut self, faster: bool) {
+        let current = GAME_SPEEDS.iter().position(|speed| *speed == self.game_speed).unwrap_or(1);
+        let next = if faster { (current + 1).min(GAME_SPEEDS.len() - 1) } else { current.saturating_sub(1) };
+        self.game_speed = GAME_SPEEDS[next];
+    }
+
+    /// Like `step_game_speed(true)`, but wraps back around to the slowest speed.
+    pub fn cycle_game_speed(&mut self) {
+        let current = GAME_SPEEDS.iter().position(|speed| *speed == self.game_speed).unwrap_or(1);
+        self.game_speed = GAME_SPEEDS[(current + 1) % GAME_SPEEDS.len()];
+    }
+
+    /// Turns the volume up a tenth, wrapping from full back to muted.
+    pub fn cycle_volume(&mut self) {
+        let tenths = (self.volume * 10.).round() as i32;
+        self.volume = ((tenths + 1) % 11) as f32 / 10.;
+    }
+
+    pub fn cycle_window_mode(&mut self) {
+        self.window_mode = match self.window_mode {
+            WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+            WindowMode::BorderlessFullscreen => WindowMode::Fullscreen,
+            _ => WindowMode::Windowed,
+        };
+    }
+}
+
+/// How many keys each movement action can have bound at once.
+pub const KEYS_PER_ACTION: usize = 2;
+
+/// Which keys move which way. Each action has a primary key and an alternate; either slot can be empty, as long as
+/// the action keeps at least one key.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub up: [Option<KeyCode>; KEYS_PER_ACTION],
+    pub down: [Option<KeyCode>; KEYS_PER_ACTION],
+    pub left: [Option<KeyCode>; KEYS_PER_ACTION],
+    pub right: [Option<KeyCode>; KEYS_PER_ACTION],
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            up: [Some(KeyCode::W), Some(KeyCode::Up)],
+            down: [Some(KeyCode::S), Some(KeyCode::Down)],
+            left: [Some(KeyCode::A), Some(KeyCode::Left)],
+            right: [Some(KeyCode::D), Some(KeyCode::Right)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MoveAction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl MoveAction {
+    pub const ALL: [MoveAction; 4] = [MoveAction::Up, MoveAction::Down, MoveAction::Left, MoveAction::Right];
+
+    pub fn offset(&self) -> IVec2 {
+        match self {
+            MoveAction::Up => IVec2::Y,
+            MoveAction::Down => IVec2::NEG_Y,
+            MoveAction::Left => IVec2::NEG_X,
+            MoveAction::Right => IVec2::X,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn slots(&self, action: MoveAction) -> [Option<KeyCode>; KEYS_PER_ACTION] {
+        match action {
+            MoveAction::Up => self.up,
+            MoveAction::Down => self.down,
+            MoveAction::Left => self.left,
+            MoveAction::Right => self.right,
+        }
+    }
+
+    fn slots_mut(&mut self, action: MoveAction) -> &mut [Option<KeyCode>; KEYS_PER_ACTION] {
+        match action {
+            MoveAction::Up => &mut self.up,
+            MoveAction::Down => &mut self.down,
+            MoveAction::Left => &mut self.left,
+            MoveAction::Right => &mut self.right,
+        }
+    }
+
+    pub fn keys(&self, action: MoveAction) -> impl Iterator<Item = KeyCode> {
+        self.slots(action).into_iter().flatten()
+    }
+
+    pub fn just_pressed(&self, action: MoveAction, input: &Input<KeyCode>) -> bool {
+        input.any_just_pressed(self.keys(action))
+    }
+
+    pub fn pressed(&self, action: MoveAction, input: &Input<KeyCode>) -> bool {
+        input.any_pressed(self.keys(action))
+    }
+
+    pub fn just_released(&self, action: MoveAction, input: &Input<KeyCode>) -> bool {
+        input.any_just_released(self.keys(action))
+    }
+
+    /// The one direction being held down, if exactly one is.
+    pub fn held(&self, input: &Input<KeyCode>) -> Option<MoveAction> {
+        let mut held = MoveAction::ALL.into_iter().filter(|&action| self.pressed(action, input));
+        let action = held.next()?;
+        held.next().is_none().then_some(action)
+    }
+
+    /// The action and slot `key` is bound to, if any.
+    fn find(&self, key: KeyCode) -> Option<(MoveAction, usize)> {
+        MoveAction::ALL.into_iter().find_map(|action| {
+            self.slots(action).iter().position(|&slot| slot == Some(key)).map(|slot| (action, slot))
+        })
+    }
+
+    /// What else `key` would do if it were bound to `action`, if anything.
+    pub fn conflict(&self, action: MoveAction, key: KeyCode) -> Option<BindingConflict> {
+        if let Some(&(_, shortcut)) = SHORTCUTS.iter().find(|(shortcut_key, _)| *shortcut_key == key) {
+            return Some(BindingConflict::Shortcut(shortcut));
+        }
+        self.find(key)
+            .filter(|&(other, _)| other != action)
+            .map(|(other, _)| BindingConflict::Action(other))
+    }
+
+    /// Binds `key` to one of `action`'s slots. Wherever the key was bound before gets this slot's old key in exchange;
+    /// if that was another action, it comes back. Shortcut keys can't be taken.
+    pub fn bind(&mut self, action: MoveAction, slot: usize, key: KeyCode) -> Result<Option<MoveAction>, BindingConflict> {
+        if let Some(BindingConflict::Shortcut(shortcut)) = self.conflict(action, key) {
+            return Err(BindingConflict::Shortcut(shortcut));
+        }
+        let old = self.slots(action)[slot];
+        let previous = self.find(key);
+        if let Some((other, other_slot)) = previous {
+            self.slots_mut(other)[other_slot] = old;
+        }
+        self.slots_mut(action)[slot] = Some(key);
+        match previous {
+            Some((other, _)) if other == action => Ok(None),
+            // Losing its only key would leave the other action unusable; undo the swap instead.
+            Some((other, other_slot)) if self.keys(other).next().is_none() => {
+                self.slots_mut(other)[other_slot] = Some(key);
+                self.slots_mut(action)[slot] = old;
+                Err(BindingConflict::Action(other))
+            },
+            Some((other, _)) => Ok(Some(other)),
+            None => Ok(None),
+        }
+    }
+
+    /// Empties one of `action`'s slots, unless it's the last key the action has.
+    pub fn unbind(&mut self, action: MoveAction, slot: usize) -> bool {
+        if self.keys(action).count() <= 1 && self.slots(action)[slot].is_some() {
+            return false;
+        }
+        self.slots_mut(action)[slot] = None;
+        true
+    }
+
+    /// Whether every action has a key, and no key does two things.
+    pub fn is_valid(&self) -> bool {
+        let mut seen = vec![];
+        MoveAction::ALL.into_iter().all(|action| {
+            self.keys(action).next().is_some() && self.keys(action).all(|key| {
+                let fresh = !seen.contains(&key) && SHORTCUTS.iter().all(|(shortcut_key, _)| *shortcut_key != key);
+                seen.push(key);
+                fresh
+            })
+        })
+    }
+}
+
+/// Fixed keys the game listens for while playing, so they can't double as movement keys.
+const SHORTCUTS: [(KeyCode, &str); 13] = [
+    (KeyCode::Escape, "Pause"),
+    (KeyCode::Z, "Undo"),
+    (KeyCode::T, "Trade"),
+    (KeyCode::E, "Use item"),
+    (KeyCode::H, "Hint"),
+    (KeyCode::F2, "Animation speed"),
+    (KeyCode::F3, "Skip idle past selves"),
+    (KeyCode::F5, "Level editor"),
+    (KeyCode::F6, "Focus camera"),
+    (KeyCode::Home, "Reset camera"),
+    (KeyCode::F12, "Debug overlay"),
+    (KeyCode::BracketLeft, "Slower"),
+    (KeyCode::BracketRight, "Faster"),
+];
+
+/// Why a key can't be bound to an action as things stand.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BindingConflict {
+    /// Is the only key moving the other way.
+    Action(MoveAction),
+    /// Already does something else.
+    Shortcut(&'static str),
+}
+
+impl std::fmt::Display for BindingConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindingConflict::Action(action) => write!(f, "it's the only key for Move {:?}", action),
+            BindingConflict::Shortcut(shortcut) => write!(f, "already used for {}", shortcut),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AnimationSpeed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+    Instant,
+}
+
+impl AnimationSpeed {
+    pub fn duration(&self) -> Duration {
+        match self {
+            AnimationSpeed::Slow => Duration::from_millis(400),
+            AnimationSpeed::Normal => Duration::from_millis(200),
+            AnimationSpeed::Fast => Duration::from_millis(100),
+            AnimationSpeed::Instant => Duration::ZERO,
+        }
+    }
+
+    pub fn ease(&self) -> CubicSegment<Vec2> {
+        match self {
+            AnimationSpeed::Slow | AnimationSpeed::Normal => CubicSegment::new_bezier(Vec2::new(0., 0.), Vec2::new(0.4, 1.5)),
+            // Less overshoot; a big bounce reads as jitter at high speed.
+            AnimationSpeed::Fast => CubicSegment::new_bezier(Vec2::new(0., 0.), Vec2::new(0.4, 1.1)),
+            AnimationSpeed::Instant => CubicSegment::new_bezier(Vec2::new(0., 0.), Vec2::new(1., 1.)),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            AnimationSpeed::Slow => AnimationSpeed::Normal,
+            AnimationSpeed::Normal => AnimationSpeed::Fast,
+            AnimationSpeed::Fast => AnimationSpeed::Instant,
+            AnimationSpeed::Instant => AnimationSpeed::Slow,
+        }
+    }
+
+    /// An idle AnimateTranslation for a freshly spawned entity, already finished so it doesn't block the first turn.
+    pub fn animate_translation(&self) -> AnimateTranslation {
+        let duration = self.duration();
+        let mut timer = Timer::new(duration, TimerMode::Once);
+        timer.tick(duration);
+        AnimateTranslation {
+            start: default(),
+            end: default(),
+            timer,
+            ease: self.ease(),
+        }
+    }
+}
+
+// Hand-edited or outdated settings files can bind a key twice; fall back to the defaults rather than play ambiguously.
+fn reset_conflicting_key_bindings(mut settings: ResMut<Settings>, mut toasts: EventWriter<Toast>) {
+    if settings.key_bindings.is_valid() {
+        return;
+    }
+    warn!("Saved key bindings {:?} conflict, resetting them", settings.key_bindings);
+    settings.key_bindings = default();
+    toasts.send(Toast::warning("Saved key bindings conflicted, so they were reset"));
+}
+
+fn cycle_animation_speed(keyboard_input: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        settings.animation_speed = settings.animation_speed.next();
+        info!("Animation speed: {:?}", settings.animation_speed);
+    }
+}
+
+fn toggle_auto_resolve_idle_turns(keyboard_input: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        settings.auto_resolve_idle_turns = !settings.auto_resolve_idle_turns;
+        info!("Auto-resolve idle turns: {}", settings.auto_resolve_idle_turns);
+    }
+}
+
+fn change_game_speed(keyboard_input: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    let faster = keyboard_input.just_pressed(KeyCode::BracketRight);
+    if faster || keyboard_input.just_pressed(KeyCode::BracketLeft) {
+        settings.step_game_speed(faster);
+        info!("Game speed: {}x", settings.game_speed);
+    }
+}
+
+fn apply_game_speed(settings: Res<Settings>, mut time: ResMut<Time>) {
+    time.set_relative_speed(settings.game_speed);
+}
+
+// Only sounds started afterwards pick up the new volume; they're all short.
+fn apply_volume(settings: Res<Settings>, mut global_volume: ResMut<GlobalVolume>) {
+    global_volume.volume = VolumeLevel::new(settings.volume);
+}
+
+fn apply_low_spec(settings: Res<Settings>, mut msaa: ResMut<Msaa>) {
+    let wanted = if settings.low_spec { Msaa::Off } else { Msaa::default() };
+    if *msaa != wanted {
+        *msaa = wanted;
+    }
+}
+
+fn apply_window_mode(settings: Res<Settings>, mut windows: Query<&mut Window, With<PrimaryWindow>>) {
+    for mut window in windows.iter_mut() {
+        if window.mode != settings.window_mode {
+            window.mode = settings.window_mode;
+        }
+    }
+}
+
+fn apply_animation_speed(settings: Res<Settings>, mut animations: Query<&mut AnimateTranslation>) {
+    for mut animation in animations.iter_mut() {
+        animation.timer.set_duration(settings.animation_speed.duration());
+        animation.ease = settings.animation_speed.ease();
+    }
+}
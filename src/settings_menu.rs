@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+
+use crate::AppState;
+use crate::settings::{MoveAction, Settings, KEYS_PER_ACTION};
+use crate::toast::Toast;
+use crate::ui::spawn_button;
+
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Rebinding(None))
+            .add_systems(OnEnter(AppState::Settings), (spawn_settings_menu, apply_deferred, update_settings_labels).chain())
+            .add_systems(Update, (
+                press_settings_buttons,
+                read_keys,
+                update_settings_labels.run_if(resource_changed::<Settings>().or_else(resource_changed::<Rebinding>())),
+            ).chain().run_if(in_state(AppState::Settings)))
+            .add_systems(OnExit(AppState::Settings), despawn_settings_menu);
+    }
+}
+
+/// The movement key slot waiting for a new binding, if any. The next key pressed is bound to it.
+#[derive(Resource)]
+struct Rebinding(Option<(MoveAction, usize)>);
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component)]
+enum SettingsButton {
+    Volume,
+    WindowMode,
+    AnimationSpeed,
+    GameSpeed,
+    AutoResolveIdleTurns,
+    FocusMode,
+    ReduceMotion,
+    LowSpec,
+    Palette,
+    ConfirmOnRelease,
+    Bind(MoveAction, usize),
+    Back,
+}
+
+fn spawn_settings_menu(mut commands: Commands) {
+    commands.spawn((
+        SettingsMenu,
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+            background_color: Color::rgb(0.1, 0.08, 0.12).into(),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("Settings", TextStyle {font_size: 60., ..default()}));
+        // Two columns: general options on the left, key bindings on the right.
+        parent.spawn(NodeBundle {
+            style: Style {column_gap: Val::Px(40.), ..default()},
+            ..default()
+        }).with_children(|parent| {
+            let column = NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            };
+            // Labels are filled in by update_settings_labels.
+            parent.spawn(column.clone()).with_children(|parent| {
+                spawn_button(parent, "", SettingsButton::Volume);
+                spawn_button(parent, "", SettingsButton::WindowMode);
+                spawn_button(parent, "", SettingsButton::AnimationSpeed);
+                spawn_button(parent, "", SettingsButton::GameSpeed);
+                spawn_button(parent, "", SettingsButton::AutoResolveIdleTurns);
+                spawn_button(parent, "", SettingsButton::FocusMode);
+                spawn_button(parent, "", SettingsButton::ReduceMotion);
+                spawn_button(parent, "", SettingsButton::LowSpec);
+                spawn_button(parent, "", SettingsButton::Palette);
+                spawn_button(parent, "", SettingsButton::ConfirmOnRelease);
+            });
+            // One row per action: its name, then a button per key slot.
+            parent.spawn(column).with_children(|parent| {
+                for action in MoveAction::ALL {
+                    parent.spawn(NodeBundle {
+                        style: Style {align_items: AlignItems::Center, column_gap: Val::Px(10.), ..default()},
+                        ..default()
+                    }).with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(format!("Move {:?}", action), TextStyle::default()));
+                        for slot in 0..KEYS_PER_ACTION {
+                            spawn_button(parent, "", SettingsButton::Bind(action, slot));
+                        }
+                    });
+                }
+                parent.spawn(TextBundle::from_section(
+                    "Backspace clears a key",
+                    TextStyle {font_size: 20., color: Color::GRAY, ..default()}));
+            });
+        });
+        spawn_button(parent, "Back", SettingsButton::Back);
+    });
+}
+
+fn despawn_settings_menu(mut commands: Commands, query: Query<Entity, With<SettingsMenu>>, mut rebinding: ResMut<Rebinding>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    rebinding.0 = None;
+}
+
+fn press_settings_buttons(
+    interactions: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, button) in interactions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            SettingsButton::Volume => settings.cycle_volume(),
+            SettingsButton::WindowMode => settings.cycle_window_mode(),
+            SettingsButton::AnimationSpeed => settings.animation_speed = settings.animation_speed.next(),
+            SettingsButton::GameSpeed => settings.cycle_game_speed(),
+            SettingsButton::AutoResolveIdleTurns => settings.auto_resolve_idle_turns = !settings.auto_resolve_idle_turns,
+            SettingsButton::FocusMode => settings.focus_mode = !settings.focus_mode,
+            SettingsButton::ReduceMotion => settings.reduce_motion = !settings.reduce_motion,
+            SettingsButton::LowSpec => settings.low_spec = !settings.low_spec,
+            SettingsButton::Palette => settings.palette = settings.palette.next(),
+            SettingsButton::ConfirmOnRelease => settings.confirm_on_release = !settings.confirm_on_release,
+            SettingsButton::Bind(action, slot) => rebinding.0 = Some((*action, *slot)),
+            SettingsButton::Back => next_state.set(AppState::MainMenu),
+        }
+    }
+}
+
+// Escape cancels a rebind in progress, or otherwise goes back to the main menu.
+fn read_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut toasts: EventWriter<Toast>,
+) {
+    let Some((action, slot)) = rebinding.0 else {
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            next_state.set(AppState::MainMenu);
+        }
+        return;
+    };
+    let Some(&key) = keyboard_input.get_just_pressed().next() else {
+        return;
+    };
+    if key == KeyCode::Escape {
+        rebinding.0 = None;
+        return;
+    }
+    if key == KeyCode::Back {
+        if !settings.key_bindings.unbind(action, slot) {
+            toasts.send(Toast::warning(format!("Move {:?} needs at least one key", action)));
+        }
+        rebinding.0 = None;
+        return;
+    }
+    // A refused key leaves the rebind open for another try.
+    match settings.key_bindings.bind(action, slot, key) {
+        Ok(None) => {},
+        Ok(Some(swapped)) => toasts.send(Toast::info(format!("{:?} moved over from Move {:?}", key, swapped))),
+        Err(conflict) => {
+            toasts.send(Toast::warning(format!("Can't use {:?}: {}", key, conflict)));
+            return;
+        },
+    }
+    rebinding.0 = None;
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
+
+fn update_settings_labels(
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    buttons: Query<(&SettingsButton, &Children)>,
+    mut labels: Query<&mut Text>,
+) {
+    for (button, children) in buttons.iter() {
+        let label = match button {
+            SettingsButton::Volume => format!("Volume: {}%", (settings.volume * 100.).round()),
+            SettingsButton::WindowMode => format!("Window: {}", match settings.window_mode {
+                WindowMode::Windowed => "windowed",
+                WindowMode::BorderlessFullscreen => "borderless",
+                _ => "fullscreen",
+            }),
+            SettingsButton::AnimationSpeed => format!("Animation speed: {:?}", settings.animation_speed),
+            SettingsButton::GameSpeed => format!("Game speed: {}x", settings.game_speed),
+            SettingsButton::AutoResolveIdleTurns => format!("Skip idle past selves: {}", on_off(settings.auto_resolve_idle_turns)),
+            SettingsButton::FocusMode => format!("Focus camera on soot: {}", on_off(settings.focus_mode)),
+            SettingsButton::ReduceMotion => format!("Reduce motion: {}", on_off(settings.reduce_motion)),
+            SettingsButton::LowSpec => format!("Low-spec graphics: {}", on_off(settings.low_spec)),
+            SettingsButton::Palette => format!("Colors: {:?}", settings.palette),
+            SettingsButton::ConfirmOnRelease => format!("Move on key release: {}", on_off(settings.confirm_on_release)),
+            SettingsButton::Bind(action, slot) if rebinding.0 == Some((*action, *slot)) => "Press a key".to_string(),
+            SettingsButton::Bind(action, slot) => match settings.key_bindings.slots(*action)[*slot] {
+                Some(key) => format!("{:?}", key),
+                None => "-".to_string(),
+            },
+            SettingsButton::Back => continue,
+        };
+        for &child in children.iter() {
+            if let Ok(mut text) = labels.get_mut(child) {
+                text.sections[0].value = label.clone();
+            }
+        }
+    }
+}
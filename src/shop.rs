@@ -0,0 +1,267 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{AppState, LoopCounter};
+use crate::inventory::Inventory;
+use crate::profile::Profile;
+use crate::rules::GameRules;
+use crate::toast::Toast;
+use crate::ui::spawn_button;
+
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Upgrades::default())
+            .add_systems(Startup, load_upgrades)
+            .add_systems(Update, save_upgrades.run_if(resource_changed::<Upgrades>()))
+            .add_systems(OnEnter(AppState::GameOver), bank_points)
+            .add_systems(OnEnter(AppState::Shop), spawn_shop)
+            .add_systems(Update, (
+                press_shop_buttons,
+                (despawn_shop, spawn_shop).chain().run_if(resource_changed::<Profile>()),
+            ).chain().run_if(in_state(AppState::Shop)))
+            .add_systems(OnExit(AppState::Shop), despawn_shop);
+    }
+}
+
+/// Permanent boosts bought with banked points. Kept in the profile; this is the copy gameplay reads.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Upgrades {
+    /// Fuel every soot starts each loop with.
+    pub starting_fuel: i32,
+    pub extra_loops: i32,
+    /// Moving up no longer burns fuel.
+    pub free_uphill: bool,
+    /// Moving left no longer burns fuel.
+    pub free_leftward: bool,
+    /// Moves the player can queue up on top of the usual limit.
+    pub extra_queued_moves: i32,
+    /// Points the player starts each loop holding, as a head start on the score.
+    #[serde(alias = "starting_candy")]
+    pub starting_points: i32,
+    /// Hints the player can ask for each loop.
+    pub hints: i32,
+}
+
+impl LoopCounter {
+    pub fn is_final_loop(&self, rules: &GameRules, upgrades: &Upgrades) -> bool {
+        self.0 >= rules.num_loops(upgrades) - 1
+    }
+}
+
+const MAX_STARTING_FUEL: i32 = 3;
+const MAX_EXTRA_LOOPS: i32 = 2;
+const MAX_EXTRA_QUEUED_MOVES: i32 = 2;
+const MAX_STARTING_POINTS: i32 = 3;
+const MAX_HINTS: i32 = 3;
+
+/// The upgrade tree, one branch per column. Each upgrade needs the one above it.
+const BRANCHES: [(&str, &[Upgrade]); 3] = [
+    ("Fuel", &[Upgrade::StartingFuel, Upgrade::FreeUphill, Upgrade::FreeLeftward]),
+    ("Loops", &[Upgrade::ExtraLoop, Upgrade::StartingPoints]),
+    ("Controls", &[Upgrade::LongerQueue, Upgrade::Hints]),
+];
+
+#[derive(Component, Clone, Copy)]
+enum ShopButton {
+    Buy(Upgrade),
+    NextRun,
+    MainMenu,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Upgrade {
+    StartingFuel,
+    FreeUphill,
+    FreeLeftward,
+    ExtraLoop,
+    StartingPoints,
+    LongerQueue,
+    Hints,
+}
+
+impl Upgrade {
+    fn name(&self) -> &'static str {
+        match self {
+            Upgrade::StartingFuel => "Start with +1 fuel",
+            Upgrade::FreeUphill => "Climb up for free",
+            Upgrade::FreeLeftward => "Move left for free",
+            Upgrade::ExtraLoop => "One more loop per run",
+            Upgrade::StartingPoints => "Start with +1 point",
+            Upgrade::LongerQueue => "Queue +1 move",
+            Upgrade::Hints => "+1 hint per loop",
+        }
+    }
+
+    /// The upgrade above this one in its branch, which has to be bought first.
+    fn requires(&self) -> Option<Upgrade> {
+        BRANCHES.iter()
+            .find_map(|(_, branch)| branch.iter().position(|upgrade| upgrade == self).map(|index| (branch, index)))
+            .and_then(|(branch, index)| index.checked_sub(1).map(|previous| branch[previous]))
+    }
+
+    fn is_owned(&self, upgrades: &Upgrades) -> bool {
+        match self {
+            Upgrade::StartingFuel => upgrades.starting_fuel > 0,
+            Upgrade::FreeUphill => upgrades.free_uphill,
+            Upgrade::FreeLeftward => upgrades.free_leftward,
+            Upgrade::ExtraLoop => upgrades.extra_loops > 0,
+            Upgrade::StartingPoints => upgrades.starting_points > 0,
+            Upgrade::LongerQueue => upgrades.extra_queued_moves > 0,
+            Upgrade::Hints => upgrades.hints > 0,
+        }
+    }
+
+    fn is_unlocked(&self, upgrades: &Upgrades) -> bool {
+        self.requires().is_none_or(|required| required.is_owned(upgrades))
+    }
+
+    /// None once it's maxed out.
+    fn price(&self, upgrades: &Upgrades) -> Option<i32> {
+        match self {
+            Upgrade::StartingFuel => (upgrades.starting_fuel < MAX_STARTING_FUEL).then_some(5 * (upgrades.starting_fuel + 1)),
+            Upgrade::FreeUphill => (!upgrades.free_uphill).then_some(15),
+            Upgrade::FreeLeftward => (!upgrades.free_leftward).then_some(30),
+            Upgrade::ExtraLoop => (upgrades.extra_loops < MAX_EXTRA_LOOPS).then_some(20 * (upgrades.extra_loops + 1)),
+            Upgrade::StartingPoints => (upgrades.starting_points < MAX_STARTING_POINTS).then_some(10 * (upgrades.starting_points + 1)),
+            Upgrade::LongerQueue => (upgrades.extra_queued_moves < MAX_EXTRA_QUEUED_MOVES).then_some(8 * (upgrades.extra_queued_moves + 1)),
+            Upgrade::Hints => (upgrades.hints < MAX_HINTS).then_some(6 * (upgrades.hints + 1)),
+        }
+    }
+
+    fn apply(&self, upgrades: &mut Upgrades) {
+        match self {
+            Upgrade::StartingFuel => upgrades.starting_fuel += 1,
+            Upgrade::FreeUphill => upgrades.free_uphill = true,
+            Upgrade::FreeLeftward => upgrades.free_leftward = true,
+            Upgrade::ExtraLoop => upgrades.extra_loops += 1,
+            Upgrade::StartingPoints => upgrades.starting_points += 1,
+            Upgrade::LongerQueue => upgrades.extra_queued_moves += 1,
+            Upgrade::Hints => upgrades.hints += 1,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Shop;
+
+fn load_upgrades(mut upgrades: ResMut<Upgrades>, profile: Res<Profile>) {
+    *upgrades = profile.upgrades.clone();
+}
+
+fn save_upgrades(upgrades: Res<Upgrades>, mut profile: ResMut<Profile>) {
+    profile.upgrades = upgrades.clone();
+}
+
+// Everything the soots carried at the end of the run goes in the bank.
+fn bank_points(
+    mut profile: ResMut<Profile>,
+    loop_counter: Res<LoopCounter>,
+    rules: Res<GameRules>,
+    upgrades: Res<Upgrades>,
+    inventories: Query<&Inventory>,
+) {
+    if loop_counter.is_final_loop(&rules, &upgrades) {
+        profile.points_bank += inventories.iter().map(|inventory| inventory.points).sum::<i32>();
+    }
+}
+
+fn spawn_shop(mut commands: Commands, profile: Res<Profile>) {
+    commands.spawn((
+        Shop,
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+            background_color: Color::rgb(0.1, 0.08, 0.12).into(),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            format!("Shop - {} points banked", profile.points_bank),
+            TextStyle {font_size: 50., ..default()}));
+        parent.spawn(NodeBundle {
+            style: Style {column_gap: Val::Px(40.), align_items: AlignItems::FlexStart, ..default()},
+            ..default()
+        }).with_children(|parent| {
+            for (title, branch) in BRANCHES {
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                }).with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(title, TextStyle {font_size: 36., ..default()}));
+                    for (index, upgrade) in branch.iter().enumerate() {
+                        if index > 0 {
+                            parent.spawn(TextBundle::from_section("|", TextStyle {font_size: 24., color: Color::GRAY, ..default()}));
+                        }
+                        spawn_upgrade(parent, *upgrade, &profile.upgrades);
+                    }
+                });
+            }
+        });
+        spawn_button(parent, "Next run", ShopButton::NextRun);
+        spawn_button(parent, "Main menu", ShopButton::MainMenu);
+    });
+}
+
+fn spawn_upgrade(parent: &mut ChildBuilder, upgrade: Upgrade, upgrades: &Upgrades) {
+    let greyed = |text: String| TextBundle::from_section(text, TextStyle {font_size: 24., color: Color::GRAY, ..default()});
+    match (upgrade.price(upgrades), upgrade.requires()) {
+        (None, _) => {
+            parent.spawn(greyed(format!("{} (owned)", upgrade.name())));
+        },
+        (Some(_), Some(required)) if !required.is_owned(upgrades) => {
+            parent.spawn(greyed(format!("{} (needs {})", upgrade.name(), required.name())));
+        },
+        (Some(price), _) => spawn_button(parent, format!("{} ({} points)", upgrade.name(), price), ShopButton::Buy(upgrade)),
+    }
+}
+
+fn despawn_shop(mut commands: Commands, query: Query<Entity, With<Shop>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn press_shop_buttons(
+    interactions: Query<(&Interaction, &ShopButton), Changed<Interaction>>,
+    mut profile: ResMut<Profile>,
+    mut upgrades: ResMut<Upgrades>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut toasts: EventWriter<Toast>,
+) {
+    for (interaction, button) in interactions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            ShopButton::Buy(upgrade) => {
+                let Some(price) = upgrade.price(&upgrades).filter(|_| upgrade.is_unlocked(&upgrades)) else {
+                    continue;
+                };
+                if price > profile.points_bank {
+                    toasts.send(Toast::warning(format!("Not enough points for {}", upgrade.name())));
+                    continue;
+                }
+                profile.points_bank -= price;
+                upgrade.apply(&mut upgrades);
+            },
+            ShopButton::NextRun => next_state.set(AppState::Playing),
+            ShopButton::MainMenu => next_state.set(AppState::MainMenu),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bevy::input::ButtonState;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::mouse::MouseButtonInput;
+use bevy::prelude::*;
+use bevy::window::{CursorMoved, PrimaryWindow};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::{AppState, LoopCounter};
+use crate::main_menu::MenuButton;
+use crate::spawn_level::{LevelSeed, SpawnLevel};
+
+/// Inject something every this many frames; fast enough to pile up inputs, slow enough for menus to react.
+const FRAMES_PER_ACTION: u32 = 3;
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+/// Entities the main menu may gain over its first visits before we call it a leak.
+const LEAK_TOLERANCE: usize = 50;
+/// Main menu visits to skip before taking the baseline, so one-off startup entities settle in first.
+const WARMUP_VISITS: u32 = 3;
+const INPUT_HISTORY: usize = 50;
+
+/// Movement dominates so runs actually get played; the rest exercise pausing, settings hotkeys and trading.
+/// The editor is left out since it writes to the level files.
+const KEYS: [KeyCode; 17] = [
+    KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+    KeyCode::W, KeyCode::A, KeyCode::S, KeyCode::D,
+    KeyCode::Down, KeyCode::Right,
+    KeyCode::T, KeyCode::Z, KeyCode::Escape, KeyCode::F2, KeyCode::F3, KeyCode::F6, KeyCode::BracketRight,
+];
+const WINDOW_SIZES: [(f32, f32); 3] = [(1280., 720.), (800., 600.), (1920., 1080.)];
+
+/// Drives the real app with synthetic keyboard, mouse and window events for a long time, logging entity counts and
+/// memory use as it goes. Every run plays a fresh board, seeded from the soak's own seed. A panic prints the seed and
+/// the last few inputs; a leak or panic exits non-zero.
+pub struct SoakPlugin {
+    pub duration: Duration,
+    pub seed: u64,
+}
+
+impl SoakPlugin {
+    /// `--minutes <n>` (default 60) and `--seed <n>` (default random).
+    pub fn from_args() -> Self {
+        let arg = |name: &str| std::env::args().skip_while(|arg| arg != name).nth(1);
+        Self {
+            duration: Duration::from_secs(60 * arg("--minutes").and_then(|minutes| minutes.parse().ok()).unwrap_or(60)),
+            seed: arg("--seed").and_then(|seed| seed.parse().ok()).unwrap_or_else(|| rand::thread_rng().gen()),
+        }
+    }
+}
+
+impl Plugin for SoakPlugin {
+    fn build(&self, app: &mut App) {
+        info!("Soaking for {:?} with seed {}", self.duration, self.seed);
+        let history = InputHistory::default();
+        install_panic_hook(self.seed, history.clone());
+
+        app
+            .insert_resource(Soak {
+                rng: StdRng::seed_from_u64(self.seed),
+                level_rng: StdRng::seed_from_u64(self.seed),
+                started: Instant::now(),
+                duration: self.duration,
+                last_report: Instant::now(),
+                frame: 0,
+                held_key: None,
+                held_button: false,
+                main_menu_visits: 0,
+                baseline_entities: None,
+                leaked: false,
+            })
+            .insert_resource(history)
+            .add_systems(First, inject_input)
+            .add_systems(OnEnter(AppState::Playing), seed_level.before(SpawnLevel))
+            .add_systems(OnEnter(AppState::MainMenu), check_for_leaks)
+            .add_systems(Last, report);
+    }
+}
+
+#[derive(Resource)]
+struct Soak {
+    rng: StdRng,
+    /// Kept apart from the inputs' rng, so the boards come out the same however the inputs happen to land.
+    level_rng: StdRng,
+    started: Instant,
+    duration: Duration,
+    last_report: Instant,
+    frame: u32,
+    held_key: Option<KeyCode>,
+    held_button: bool,
+    main_menu_visits: u32,
+    baseline_entities: Option<usize>,
+    leaked: bool,
+}
+
+/// Recent injected inputs, shared with the panic hook.
+#[derive(Resource, Clone, Default)]
+struct InputHistory(Arc<Mutex<VecDeque<String>>>);
+
+impl InputHistory {
+    fn push(&self, input: String) {
+        let mut history = self.0.lock().unwrap();
+        if history.len() == INPUT_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(input);
+    }
+}
+
+fn install_panic_hook(seed: u64, history: InputHistory) {
+    let started = Instant::now();
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        eprintln!("Soak test panicked after {:?} (seed {})", started.elapsed(), seed);
+        if let Ok(history) = history.0.lock() {
+            eprintln!("Last inputs: {:?}", history);
+        }
+        default_hook(panic_info);
+    }));
+}
+
+fn inject_input(
+    mut soak: ResMut<Soak>,
+    history: Res<InputHistory>,
+    mut windows: Query<(Entity, &mut Window), With<PrimaryWindow>>,
+    buttons: Query<(&Node, &GlobalTransform, Option<&MenuButton>), With<Button>>,
+    mut keyboard: EventWriter<KeyboardInput>,
+    mut mouse: EventWriter<MouseButtonInput>,
+    mut cursor: EventWriter<CursorMoved>,
+) {
+    let Ok((window_entity, mut window)) = windows.get_single_mut() else {
+        return;
+    };
+
+    // Let go of whatever was pressed last frame, so every press is a fresh just_pressed.
+    if let Some(key_code) = soak.held_key.take() {
+        keyboard.send(KeyboardInput {scan_code: 0, key_code: Some(key_code), state: ButtonState::Released, window: window_entity});
+    }
+    if soak.held_button {
+        soak.held_button = false;
+        mouse.send(MouseButtonInput {button: MouseButton::Left, state: ButtonState::Released, window: window_entity});
+    }
+
+    soak.frame += 1;
+    if !soak.frame.is_multiple_of(FRAMES_PER_ACTION) {
+        return;
+    }
+
+    // Quitting would end the soak early.
+    let targets: Vec<Vec2> = buttons.iter()
+        .filter(|(node, _, menu_button)| node.size() != Vec2::ZERO && !matches!(menu_button, Some(MenuButton::Quit)))
+        .map(|(_, transform, _)| transform.translation().truncate())
+        .collect();
+
+    if soak.rng.gen_bool(0.002) {
+        let (width, height) = *WINDOW_SIZES.choose(&mut soak.rng).unwrap();
+        window.resolution.set(width, height);
+        history.push(format!("resize to {}x{}", width, height));
+    } else if !targets.is_empty() && soak.rng.gen_bool(0.5) {
+        let position = *targets.choose(&mut soak.rng).unwrap();
+        window.set_cursor_position(Some(position));
+        cursor.send(CursorMoved {window: window_entity, position});
+        mouse.send(MouseButtonInput {button: MouseButton::Left, state: ButtonState::Pressed, window: window_entity});
+        soak.held_button = true;
+        history.push(format!("click at {}", position));
+    } else {
+        let key_code = *KEYS.choose(&mut soak.rng).unwrap();
+        keyboard.send(KeyboardInput {scan_code: 0, key_code: Some(key_code), state: ButtonState::Pressed, window: window_entity});
+        soak.held_key = Some(key_code);
+        history.push(format!("{:?}", key_code));
+    }
+}
+
+// `--seed` pins the level seed too, which would have every run play the same board.
+fn seed_level(mut soak: ResMut<Soak>, mut level_seed: ResMut<LevelSeed>, loop_counter: Res<LoopCounter>) {
+    if loop_counter.0 == 0 {
+        *level_seed = LevelSeed::pinned(soak.level_rng.gen());
+    }
+}
+
+// Every visit to the main menu should leave the world in the same shape, so growth here is a leak.
+fn check_for_leaks(mut soak: ResMut<Soak>, entities: Query<Entity>) {
+    soak.main_menu_visits += 1;
+    let count = entities.iter().count();
+    if soak.main_menu_visits <= WARMUP_VISITS {
+        return;
+    }
+
+    match soak.baseline_entities {
+        None => soak.baseline_entities = Some(count),
+        Some(baseline) if count > baseline + LEAK_TOLERANCE => {
+            error!("Possible entity leak: {} entities at the main menu, up from {}", count, baseline);
+            soak.leaked = true;
+        },
+        Some(_) => {},
+    }
+}
+
+fn report(mut soak: ResMut<Soak>, entities: Query<Entity>, app_state: Res<State<AppState>>) {
+    let finished = soak.started.elapsed() >= soak.duration;
+    if soak.last_report.elapsed() < REPORT_INTERVAL && !finished {
+        return;
+    }
+    soak.last_report = Instant::now();
+
+    info!("Soak {:?}/{:?}: {} entities, {} resident, in {:?}",
+        soak.started.elapsed(), soak.duration, entities.iter().count(),
+        resident_memory().map_or("unknown memory".to_string(), |bytes| format!("{} MiB", bytes / (1 << 20))),
+        app_state.get());
+
+    if finished {
+        info!("Soak finished{}", if soak.leaked { " with leaks" } else { " cleanly" });
+        std::process::exit(if soak.leaked { 1 } else { 0 });
+    }
+}
+
+/// Resident set size of this process, where the OS makes it easy to find.
+fn resident_memory() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(resident_pages * 4096)
+}
@@ -0,0 +1,273 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::fuel_cost;
+use crate::inventory::{Item, BASE_FUEL_CAPACITY};
+use crate::level_spec::LevelSpec;
+use crate::shop::Upgrades;
+
+const DIRECTIONS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+/// Keeps `best_route` quick with upgrades that make most moves free, at the cost of only looking at shorter routes.
+const MAX_ROUTE_STATES: usize = 200_000;
+
+/// Candy that none of the first `num_loops` loops' soots can pick up on its way from its spawn cell to the exit,
+/// without any upgrades.
+///
+/// Each route is checked on its own with every fuel pickup still on the board, so fuel one loop takes away from
+/// another isn't accounted for, and numbered candy is only checked for reachability, not order.
+pub fn unreachable_candies(level_spec: &LevelSpec, num_loops: i32) -> Vec<IVec2> {
+    let routes: HashSet<(IVec2, IVec2)> = (0..num_loops)
+        .map(|loop_number| (level_spec.start_for_loop(loop_number), level_spec.end_for_loop(loop_number)))
+        .collect();
+    let candies = level_spec.candies.iter().map(|(location, _)| *location)
+        .chain(level_spec.nether_candies.iter().copied())
+        .chain(level_spec.numbered_candies.iter().copied());
+
+    candies
+        .filter(|&candy| !routes.iter().any(|&(start, end)| can_collect(level_spec, start, end, candy)))
+        .collect()
+}
+
+#[derive(Clone, Copy, Hash, Eq, PartialEq)]
+struct SearchState {
+    location: IVec2,
+    fuel: i32,
+    /// Bit per entry of `fuel_pickups` that's been picked up.
+    fuel_taken: u64,
+    visited_target: bool,
+}
+
+/// Every fuel pickup on the board and how much it holds: single fuel, then canisters.
+fn fuel_pickups(level_spec: &LevelSpec) -> Vec<(IVec2, i32)> {
+    level_spec.fuel.iter().map(|&location| (location, 1))
+        .chain(level_spec.canisters.iter().copied())
+        .take(64)
+        .collect()
+}
+
+/// Picks up whatever fuel in `pickups` is at `location` and hasn't been taken yet, up to the fuel capacity.
+fn take_fuel(pickups: &[(IVec2, i32)], location: IVec2, fuel_taken: &mut u64, fuel: &mut i32) {
+    for (index, &(_, amount)) in pickups.iter().enumerate().take(64).filter(|(_, (cell, _))| *cell == location) {
+        if *fuel_taken & (1 << index) == 0 {
+            *fuel_taken |= 1 << index;
+            *fuel = (*fuel + amount).min(BASE_FUEL_CAPACITY);
+        }
+    }
+}
+
+/// Breadth-first search over position, fuel on hand and fuel picked up, for a route through `target` that ends
+/// on `end`.
+fn can_collect(level_spec: &LevelSpec, start: IVec2, end: IVec2, target: IVec2) -> bool {
+    let fuel_pickups = fuel_pickups(level_spec);
+    let initial = SearchState {location: start, fuel: 0, fuel_taken: 0, visited_target: start == target};
+    let mut seen = HashSet::from([initial]);
+    let mut queue = VecDeque::from([initial]);
+
+    while let Some(state) = queue.pop_front() {
+        if state.visited_target && state.location == end {
+            return true;
+        }
+
+        for offset in DIRECTIONS {
+            let cost = fuel_cost(offset, &Upgrades::default());
+            if cost > state.fuel || !level_spec.is_open(state.location + offset) {
+                continue;
+            }
+            let location = level_spec.landing_cell(state.location, offset);
+
+            let mut next = SearchState {
+                location,
+                fuel: state.fuel - cost,
+                visited_target: state.visited_target || location == target,
+                ..state
+            };
+            take_fuel(&fuel_pickups, location, &mut next.fuel_taken, &mut next.fuel);
+
+            if seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    false
+}
+
+/// Shortest walk from `start` to `target` for a soot holding `fuel`, picking up any of the `fuel_cells` it passes
+/// through on the way (each with how much fuel it holds), as a list of one-cell offsets. `None` if the soot can't get there.
+pub fn find_path(
+    level_spec: &LevelSpec,
+    fuel_cells: &[(IVec2, i32)],
+    start: IVec2,
+    fuel: i32,
+    target: IVec2,
+    upgrades: &Upgrades,
+) -> Option<Vec<IVec2>> {
+    let initial = SearchState {location: start, fuel, fuel_taken: 0, visited_target: start == target};
+    let mut came_from: HashMap<SearchState, (SearchState, IVec2)> = HashMap::new();
+    let mut seen = HashSet::from([initial]);
+    let mut queue = VecDeque::from([initial]);
+
+    while let Some(state) = queue.pop_front() {
+        if state.visited_target {
+            let mut path = vec![];
+            let mut current = state;
+            while let Some(&(previous, offset)) = came_from.get(&current) {
+                path.push(offset);
+                current = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for offset in DIRECTIONS {
+            let cost = fuel_cost(offset, upgrades);
+            if cost > state.fuel || !level_spec.is_open(state.location + offset) {
+                continue;
+            }
+            let location = level_spec.landing_cell(state.location, offset);
+
+            let mut next = SearchState {
+                location,
+                fuel: state.fuel - cost,
+                visited_target: location == target,
+                ..state
+            };
+            take_fuel(fuel_cells, location, &mut next.fuel_taken, &mut next.fuel);
+
+            if seen.insert(next) {
+                came_from.insert(next, (state, offset));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+#[derive(Clone, Copy, Hash, Eq, PartialEq)]
+struct RouteState {
+    location: IVec2,
+    fuel: i32,
+    fuel_taken: u64,
+    /// Bit per candy in `collectable_candies` order that's been picked up.
+    candy_taken: u64,
+}
+
+/// Candy the player can pick up themselves and what each is worth: plain candy, then numbered candy in collection
+/// order.
+fn collectable_candies(level_spec: &LevelSpec) -> Vec<(IVec2, i32)> {
+    level_spec.candies.iter().map(|&(location, color)| (location, Item::Candy(color).points()))
+        .chain(level_spec.numbered_candies.iter().enumerate()
+            .map(|(index, &location)| (location, Item::NumberedCandy((index + 1) as u8).points())))
+        .take(64)
+        .collect()
+}
+
+/// The route from `start` to the exit that scores the most points, for a lone soot holding `fuel` on an untouched
+/// board. Returns the moves and the points they score.
+///
+/// Past selves aren't simulated, so this is the best one loop could do with the whole board to itself.
+pub fn best_route(level_spec: &LevelSpec, start: IVec2, fuel: i32, upgrades: &Upgrades) -> (Vec<IVec2>, i32) {
+    let candies = collectable_candies(level_spec);
+    let fuel_pickups = fuel_pickups(level_spec);
+    let numbered_from = level_spec.candies.len().min(candies.len());
+    let take = |state: &mut RouteState| {
+        take_fuel(&fuel_pickups, state.location, &mut state.fuel_taken, &mut state.fuel);
+        for (index, &(candy, _)) in candies.iter().enumerate() {
+            // Numbered candy only comes off the board once every lower number is gone.
+            let in_order = (numbered_from..index).all(|earlier| state.candy_taken & (1 << earlier) != 0);
+            if candy == state.location && (index < numbered_from || in_order) {
+                state.candy_taken |= 1 << index;
+            }
+        }
+    };
+
+    let points = |state: &RouteState| -> i32 {
+        candies.iter().enumerate()
+            .filter(|(index, _)| state.candy_taken & (1 << index) != 0)
+            .map(|(_, &(_, points))| points)
+            .sum()
+    };
+
+    let mut initial = RouteState {location: start, fuel, fuel_taken: 0, candy_taken: 0};
+    take(&mut initial);
+    let mut came_from: HashMap<RouteState, (RouteState, IVec2)> = HashMap::new();
+    let mut seen = HashSet::from([initial]);
+    let mut queue = VecDeque::from([initial]);
+    // Breadth-first, so the first route found for a given haul is also the shortest.
+    let mut best: Option<RouteState> = None;
+
+    while let Some(state) = queue.pop_front() {
+        if state.location == level_spec.end {
+            if best.is_none_or(|best| points(&state) > points(&best)) {
+                best = Some(state);
+            }
+            // The loop ends as soon as the exit is reached.
+            continue;
+        }
+
+        for offset in DIRECTIONS {
+            let cost = fuel_cost(offset, upgrades);
+            if cost > state.fuel || !level_spec.is_open(state.location + offset) {
+                continue;
+            }
+            let location = level_spec.landing_cell(state.location, offset);
+
+            let mut next = RouteState {location, fuel: state.fuel - cost, ..state};
+            take(&mut next);
+            if seen.len() < MAX_ROUTE_STATES && seen.insert(next) {
+                came_from.insert(next, (state, offset));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let Some(best) = best else {
+        return (vec![], 0);
+    };
+    let mut route = vec![];
+    let mut current = best;
+    while let Some(&(previous, offset)) = came_from.get(&current) {
+        route.push(offset);
+        current = previous;
+    }
+    route.reverse();
+    (route, points(&best))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_spec::CandyColor;
+
+    const CANDY: IVec2 = IVec2::new(2, 2);
+
+    /// Candy walled off on the sides a soot can reach it from for free, so getting to it takes fuel.
+    fn walled_in_candy() -> LevelSpec {
+        LevelSpec {
+            candies: vec![(CANDY, CandyColor::Red)],
+            walls: vec![CANDY + IVec2::NEG_X, CANDY + IVec2::Y],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn walled_in_candy_without_fuel_is_unreachable() {
+        assert_eq!(unreachable_candies(&walled_in_candy(), 1), vec![CANDY]);
+    }
+
+    #[test]
+    fn fuel_on_the_way_makes_walled_in_candy_reachable() {
+        let level_spec = LevelSpec {fuel: vec![CANDY + IVec2::new(1, 1)], ..walled_in_candy()};
+        assert!(unreachable_candies(&level_spec, 1).is_empty());
+    }
+
+    #[test]
+    fn generated_levels_have_no_unreachable_candy() {
+        for seed in 0..8 {
+            let level_spec = LevelSpec::generate(seed, 3);
+            assert!(unreachable_candies(&level_spec, 3).is_empty(), "seed {} has unreachable candy", seed);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+
+use crate::{AppState, DespawnOnExitPlaying, LoopCounter, SootId, SootSprite, StartLoop};
+use crate::inventory::Inventory;
+use crate::palette::Palette;
+use crate::ui::UpdateUi;
+
+const ICON_SIZE: f32 = 40.;
+
+pub struct SootPanelPlugin;
+
+impl Plugin for SootPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Playing), spawn_soot_panel.in_set(StartLoop))
+            .add_systems(Update, (add_soot_rows, apply_deferred, update_soot_rows)
+                .chain().in_set(UpdateUi).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Down the side of the screen, one row per soot on the board.
+#[derive(Component)]
+struct SootPanel;
+
+/// The text of a soot's row in the panel.
+#[derive(Component)]
+struct SootRow(Entity);
+
+fn spawn_soot_panel(mut commands: Commands) {
+    commands.spawn((
+        SootPanel,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(20.),
+                top: Val::Px(80.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.),
+                ..default()
+            },
+            ..default()
+        },
+        DespawnOnExitPlaying,
+    ));
+}
+
+fn describe(inventory: &Inventory) -> String {
+    format!("{} points, {} fuel", inventory.points, inventory.fuel)
+}
+
+// Rows are added as the soots turn up, so it doesn't matter which of the loop's setup runs first.
+fn add_soot_rows(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+    loop_counter: Res<LoopCounter>,
+    panels: Query<Entity, With<SootPanel>>,
+    soots: Query<(Entity, &SootSprite, &Inventory), Added<SootSprite>>,
+) {
+    let Ok(panel) = panels.get_single() else {
+        return;
+    };
+    let mut soots: Vec<_> = soots.iter().collect();
+    soots.sort_by_key(|(_, soot, _)| soot.id.loop_number());
+    for (entity, soot, inventory) in soots {
+        let played_in = loop_counter.0 - soot.id.loop_number() + 1;
+        let (label, tint) = match soot.id {
+            SootId::Player => (format!("Loop {} (you)", played_in), Color::WHITE),
+            SootId::Recording(_) => (format!("Loop {}", played_in), palette.past_self),
+        };
+        commands.entity(panel).with_children(|parent| {
+            parent.spawn(NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            }).with_children(|parent| {
+                parent.spawn(ImageBundle {
+                    style: Style {
+                        width: Val::Px(ICON_SIZE),
+                        height: Val::Px(ICON_SIZE),
+                        ..default()
+                    },
+                    image: UiImage::new(asset_server.load("soot-sprite.png")),
+                    background_color: tint.into(),
+                    ..default()
+                });
+                parent.spawn(TextBundle::from_section(label, TextStyle {font_size: 30., ..default()}));
+                parent.spawn((
+                    SootRow(entity),
+                    TextBundle::from_section(describe(inventory), TextStyle {font_size: 30., ..default()}),
+                ));
+            });
+        });
+    }
+}
+
+fn update_soot_rows(
+    soots: Query<&Inventory, (With<SootSprite>, Changed<Inventory>)>,
+    mut rows: Query<(&SootRow, &mut Text)>,
+) {
+    for (row, mut text) in rows.iter_mut() {
+        if let Ok(inventory) = soots.get(row.0) {
+            text.sections[0].value = describe(inventory);
+        }
+    }
+}
@@ -1,258 +1,902 @@
-use std::collections::HashMap;
-use std::time::Duration;
+use std::path::PathBuf;
 
+use bevy::ecs::schedule::ScheduleLabel;
 use bevy::prelude::*;
-use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
 use rand::Rng;
 
-use crate::inventory::{Inventory, Item};
-use crate::{AppState, DespawnOnExitGameOver, Player, MAX_X, MAX_Y, SootSprite, LoopCounter, GRID_SPACING, START_SPACE, END_SPACE, SootId};
-use crate::grid::{GridLocation, AnimateTranslation, SnapToGrid};
+use crate::inventory::{CandyCollected, CandyRemaining, Inventory, Item, BASE_FUEL_CAPACITY};
+use crate::{board_center, AppState, StartLoop, DespawnOnExitGameOver, Player, MAX_X, MAX_Y, SootSprite, LoopCounter, GRID_SPACING, SOOT_Z, SootId};
+use crate::grid::{GridCell, GridCells, GridLocation, SnapToGrid};
+use crate::conveyor::Conveyor;
+use crate::doors::door_bundle;
+use crate::gates::{Gate, GateControl, PressurePlate, Switch};
+use crate::hazard::HazardTile;
+use crate::ice::Ice;
+use crate::level_spec::{Hazard, LevelSpec};
+use crate::palette::Palette;
+use crate::rules::{GameRules, RuleOverrides};
+use crate::scoring::Score;
+use crate::settings::Settings;
+use crate::shop::Upgrades;
+use crate::solver::unreachable_candies;
+use crate::sprite_animation::{soot_sprite_bundle, SootSheet};
+use crate::teleporter::Teleporter;
 
 
 #[derive(SystemSet, Hash, Debug, Clone, Eq, PartialEq)]
 pub struct SpawnLevel;
 
+/// Lays the board out from the current `LevelSpec`: the cells, the background and everything that sits on the cells,
+/// but no soots. Run at the start of every loop, and for the board behind the main menu.
+#[derive(ScheduleLabel, Hash, Debug, Clone, Eq, PartialEq)]
+pub struct SpawnBoard;
+
 pub struct SpawnLevelPlugin;
 
 impl Plugin for SpawnLevelPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::Playing),
             (
-                reset_level,
+                generate_level,
+                (spawn_player, spawn_past_self, spawn_board),
+                apply_deferred,
+            ).in_set(SpawnLevel).in_set(StartLoop).chain())
+            .add_systems(SpawnBoard,
+            (
+                clear_level,
                 (
-                    spawn_player,
-                    spawn_past_self,
                     spawn_grid,
+                    spawn_background,
                     add_candies_to_level,
                     add_fuel_to_level,
+                    add_nether_candies_to_level,
+                    add_numbered_candies_to_level,
+                    add_walls_to_level,
+                    add_keys_and_doors_to_level,
+                    add_power_ups_to_level,
+                    add_teleporters_to_level,
+                    add_ice_to_level,
+                    add_conveyors_to_level,
+                    add_hazards_to_level,
+                    add_plates_to_level,
+                    add_switches_to_level,
                 ),
                 spawn_level,
                 apply_deferred,
-                distribute_on_grid,
-            ).in_set(SpawnLevel).chain())
-            .insert_resource::<Level>(default());
+                reveal_nether_candies,
+                (distribute_on_grid, count_candy_on_board, add_exit_locks),
+            ).chain())
+            // Labels go on in Update, so numbered candy put back mid-loop gets one too.
+            .add_systems(Update, (label_numbered_candies, distribute_on_grid, unlock_exits).run_if(in_state(AppState::Playing)))
+            .insert_resource::<Level>(default())
+            .insert_resource::<LevelSpec>(default())
+            .insert_resource(LevelSeed::from_args())
+            .insert_resource(LevelSource::from_args());
     }
 }
 
-fn spawn_grid(mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>
-) {
-    let mesh: Mesh2dHandle = meshes.add(Mesh::from(shape::Quad::default())).into();
-    let material = materials.add(ColorMaterial::from(Color::PURPLE));
+/// Behind the grid cells and everything on them.
+const BACKGROUND_Z: f32 = -1.;
+/// How far the background reaches past the outside edges of the board.
+const BACKGROUND_MARGIN: f32 = 40.;
+const DIRT_FLOOR_SIZE: Vec2 = Vec2::new(4098., 2304.);
+
+// Shows through the gaps between the cells and frames the board. There's no level entity to hang it off, so it goes
+// with the rest of the board when the loop ends, the same as the cells. Low-spec graphics leave the clear color showing
+// instead.
+fn spawn_background(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
+    if settings.low_spec {
+        return;
+    }
+    let size = IVec2::new(MAX_X, MAX_Y).as_vec2() * GRID_SPACING as f32 + Vec2::splat(BACKGROUND_MARGIN * 2.);
+    // Cropped to the board's shape rather than stretched.
+    let crop = Vec2::new(DIRT_FLOOR_SIZE.y * size.x / size.y, DIRT_FLOOR_SIZE.y).min(DIRT_FLOOR_SIZE);
+    commands.spawn((
+        SpriteBundle {
+            texture: asset_server.load("sootsprite_0000s_0010_dirt-floor.png"),
+            sprite: Sprite {
+                custom_size: Some(size),
+                rect: Some(Rect::from_center_size(DIRT_FLOOR_SIZE / 2., crop)),
+                ..default()
+            },
+            transform: Transform::from_translation(board_center().extend(BACKGROUND_Z)),
+            ..default()
+        },
+        DespawnOnExitGameOver,
+    ));
+}
+
+/// The pieces of floor in floor-tiles.png, left to right. The rims are drawn on the top and left, and flipped round
+/// to whichever sides face off the board.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum FloorTile {
+    Center,
+    TopEdge,
+    LeftEdge,
+    TopLeftCorner,
+}
+
+const FLOOR_TILE_SIZE: f32 = 256.;
+
+/// Picks the piece of floor for `cell` and how to flip it, by which of its neighbors aren't floor.
+fn autotile(cell: IVec2, is_floor: impl Fn(IVec2) -> bool) -> (FloorTile, bool, bool) {
+    let open = |offset: IVec2| !is_floor(cell + offset);
+    let (up, down, left, right) = (open(IVec2::Y), open(IVec2::NEG_Y), open(IVec2::NEG_X), open(IVec2::X));
+    let tile = match (up || down, left || right) {
+        (false, false) => FloorTile::Center,
+        (true, false) => FloorTile::TopEdge,
+        (false, true) => FloorTile::LeftEdge,
+        (true, true) => FloorTile::TopLeftCorner,
+    };
+    (tile, right && !left, down && !up)
+}
+
+/// The floor sprite for a cell of a board filling the whole grid.
+pub fn floor_sprite(cell: IVec2, asset_server: &AssetServer, palette: &Palette) -> SpriteBundle {
+    let in_bounds = |cell: IVec2| cell.x >= 0 && cell.x < MAX_X && cell.y >= 0 && cell.y < MAX_Y;
+    let (tile, flip_x, flip_y) = autotile(cell, in_bounds);
+    let left = tile as usize as f32 * FLOOR_TILE_SIZE;
+    SpriteBundle {
+        texture: asset_server.load("floor-tiles.png"),
+        sprite: Sprite {
+            // The tiles are pale, so the palette still decides the floor's color.
+            color: palette.tile,
+            custom_size: Some(Vec2::splat(128.)),
+            rect: Some(Rect::new(left, 0., left + FLOOR_TILE_SIZE, FLOOR_TILE_SIZE)),
+            flip_x,
+            flip_y,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+// Cells are unscaled so whatever's parented to them can be laid out in pixels relative to the cell center.
+fn spawn_grid(mut commands: Commands, mut cells: ResMut<GridCells>, asset_server: Res<AssetServer>, palette: Res<Palette>) {
     let make_grid_item = |x: i32, y:i32| {
         let grid_location = GridLocation(IVec2 {x, y});
-        let size: Vec3 = Vec3::splat(128.);
         (
-            grid_location.clone(),
+            GridCell,
+            grid_location,
             SnapToGrid,
-            MaterialMesh2dBundle {
-                mesh: mesh.clone(),
-                transform: Transform::default().with_scale(size),
-                material: material.clone(),
-                ..default()
-            },
+            floor_sprite(IVec2 {x, y}, &asset_server, &palette),
             DespawnOnExitGameOver,
         )
     };
+    cells.0.clear();
     for x in 0..MAX_X {
         for y in 0..MAX_Y {
-            commands.spawn(make_grid_item(x, y));
+            let cell = commands.spawn(make_grid_item(x, y)).id();
+            cells.0.insert(IVec2 {x, y}, cell);
         }
     }
 }
 
-fn spawn_player(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let grid_location = GridLocation(IVec2 {x: 0, y: MAX_Y - 1});
-    let make_finished_timer = |duration: Duration| {
-        let mut timer = Timer::new(duration, TimerMode::Once);
-        timer.tick(duration);
-        timer
-    };
+fn spawn_player(
+    mut commands: Commands,
+    soot_sheet: Res<SootSheet>,
+    settings: Res<Settings>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    upgrades: Res<Upgrades>,
+) {
+    let grid_location = GridLocation(level_spec.start_for_loop(loop_counter.0));
 
     commands.spawn((
         Player,
-        SootSprite{id: SootId::Player, turn_number: 0},
+        SootSprite{id: SootId::Player, turn_number: 0, moves_left: 0, bonus_moves: 0},
         grid_location,
-        Inventory{candies: 0, fuel: 0},
-        SpriteBundle {
-            texture: asset_server.load("soot-sprite.png"),
-            ..default()
-        },
+        Inventory{points: upgrades.starting_points, fuel: upgrades.starting_fuel, max_fuel: BASE_FUEL_CAPACITY, keys: 0, bombs: 0},
+        Score::default(),
+        soot_sprite_bundle(&soot_sheet, Color::WHITE, Transform::from_xyz(0., 0., SOOT_Z)),
         SnapToGrid,
-        AnimateTranslation{
-            start: default(),
-            end: default(),
-            timer: make_finished_timer(Duration::from_millis(200)),
-            ease: CubicSegment::new_bezier(Vec2::new(0., 0.), Vec2::new(0.4, 1.5)),
-        },
+        settings.animation_speed.animate_translation(),
         DespawnOnExitGameOver,
     ));
 }
 
-fn spawn_past_self(mut commands: Commands, asset_server: Res<AssetServer>, loop_counter: Res<LoopCounter>) {
+fn spawn_past_self(
+    mut commands: Commands,
+    soot_sheet: Res<SootSheet>,
+    loop_counter: Res<LoopCounter>,
+    settings: Res<Settings>,
+    level_spec: Res<LevelSpec>,
+    upgrades: Res<Upgrades>,
+    palette: Res<Palette>,
+) {
+    // Past selves get the same head start, or their recorded moves wouldn't replay.
     for loop_num in 1..=loop_counter.0 {
-        let grid_location = GridLocation(IVec2 {x: 0, y: MAX_Y - 1});
-        let make_finished_timer = |duration: Duration| {
-            let mut timer = Timer::new(duration, TimerMode::Once);
-            timer.tick(duration);
-            timer
-        };
+        let grid_location = GridLocation(level_spec.start_for_loop(loop_counter.0 - loop_num));
 
         commands.spawn((
-            SootSprite{id: SootId::Recording(loop_num), turn_number: 0},
+            SootSprite{id: SootId::Recording(loop_num), turn_number: 0, moves_left: 0, bonus_moves: 0},
             grid_location,
-            Inventory{candies: 0, fuel: 0},
-            SpriteBundle {
-                texture: asset_server.load("soot-sprite.png"),
-                sprite: Sprite {
-                    color: Color::rgba(0.6, 0.6, 0.6, 0.6),
-                    ..default()
-                },
-                ..default()
-            },
+            Inventory{points: 0, fuel: upgrades.starting_fuel, max_fuel: BASE_FUEL_CAPACITY, keys: 0, bombs: 0},
+            Score::default(),
+            soot_sprite_bundle(&soot_sheet, palette.past_self, Transform::from_xyz(0., 0., SOOT_Z)),
             SnapToGrid,
-            AnimateTranslation{
-                start: default(),
-                end: default(),
-                timer: make_finished_timer(Duration::from_millis(200)),
-                ease: CubicSegment::new_bezier(Vec2::new(0., 0.), Vec2::new(0.4, 1.5)),
-            },
+            settings.animation_speed.animate_translation(),
             DespawnOnExitGameOver,
         ));
     }
 }
 
 trait BundleBox {
-    fn apply_bundle(&self, commands: &mut Commands);
+    fn apply_bundle(&self, parent: &mut ChildBuilder);
 }
 impl<T: Bundle + Clone> BundleBox for T {
-    fn apply_bundle(&self, commands: &mut Commands) {
-        commands.spawn(self.clone());
+    fn apply_bundle(&self, parent: &mut ChildBuilder) {
+        parent.spawn(self.clone());
+    }
+}
+
+/// Seed for the current run's board. A fresh one is rolled for every run unless pinned with `--seed <n>` or from the
+/// main menu.
+#[derive(Resource)]
+pub struct LevelSeed {
+    pub seed: u64,
+    pinned: bool,
+}
+
+impl LevelSeed {
+    pub fn from_args() -> Self {
+        let pinned_seed = std::env::args().skip_while(|arg| arg != "--seed").nth(1)
+            .and_then(|seed| seed.parse().ok());
+        Self {
+            seed: pinned_seed.unwrap_or_default(),
+            pinned: pinned_seed.is_some(),
+        }
+    }
+
+    pub fn pinned(seed: u64) -> Self {
+        Self {seed, pinned: true}
+    }
+}
+
+/// Where the next run's board comes from.
+#[derive(Resource, Debug, Clone)]
+pub enum LevelSource {
+    Generated,
+    File(PathBuf),
+}
+
+impl LevelSource {
+    /// Handcrafted levels are picked with `--level <path>`.
+    pub fn from_args() -> Self {
+        match std::env::args().skip_while(|arg| arg != "--level").nth(1) {
+            Some(path) => LevelSource::File(path.into()),
+            None => LevelSource::Generated,
+        }
     }
 }
 
+/// Everything that sits on a cell, by cell. Spawned as children of the cell, so it goes away with the board.
 #[derive(Resource, Default)]
 struct Level {
-    spawn: Vec<Box<dyn BundleBox + Send + Sync>>,
+    spawn: Vec<(IVec2, Box<dyn BundleBox + Send + Sync>)>,
 }
 
-const NUM_CANDIES: usize = 10;
-
-fn add_candies_to_level(mut level: ResMut<Level>, loop_counter: Res<LoopCounter>, asset_server: Res<AssetServer>) {
+fn generate_level(
+    mut level_spec: ResMut<LevelSpec>,
+    mut level_seed: ResMut<LevelSeed>,
+    level_source: Res<LevelSource>,
+    loop_counter: Res<LoopCounter>,
+    mut rules: ResMut<GameRules>,
+    upgrades: Res<Upgrades>,
+) {
     if loop_counter.0 != 0 {
         return;
     }
 
-    let mut rng = rand::thread_rng();
-    for _ in 0..NUM_CANDIES {
-        let color =  match rng.gen_range(0..3) {
-            0 => "red-candy.png",
-            1 => "green-candy.png",
-            2 => "yellow-candy.png",
-            _ => unreachable!(),
-        };
-        let mut location = IVec2 {x: rng.gen_range(0..MAX_X), y: rng.gen_range(0..MAX_Y)};
-        while location == (START_SPACE) {
-            location = IVec2 {x: rng.gen_range(0..MAX_X), y: rng.gen_range(0..MAX_Y)};
+    if let LevelSource::File(path) = level_source.as_ref() {
+        match LevelSpec::load(path) {
+            Ok(spec) => {
+                rules.apply_overrides(&spec.rules);
+                // Handcrafted levels are played as written, but an unreachable candy is almost certainly a mistake.
+                for candy in unreachable_candies(&spec, rules.num_loops(&upgrades)) {
+                    warn!("{}: candy at {} can't be collected", path.display(), candy);
+                }
+                *level_spec = spec.transformed(rules.board_transform);
+                return;
+            },
+            Err(err) => warn!("Falling back to a generated level: {}", err),
         }
+    }
+
+    if !level_seed.pinned {
+        level_seed.seed = rand::thread_rng().gen();
+    }
+    info!("Level seed: {}", level_seed.seed);
+    // Generated levels don't override any rules, so the loop count is settled before there's a layout.
+    rules.apply_overrides(&RuleOverrides::default());
+    *level_spec = LevelSpec::generate(level_seed.seed, rules.num_loops(&upgrades)).transformed(rules.board_transform);
+}
+
+pub fn spawn_board(world: &mut World) {
+    world.run_schedule(SpawnBoard);
+}
+
+// What's on the board is only worked out on the first loop; every loop after lays the same things out again.
+fn clear_level(mut level: ResMut<Level>, loop_counter: Res<LoopCounter>) {
+    if loop_counter.0 == 0 {
+        level.spawn.clear();
+    }
+}
+
+fn add_candies_to_level(
+    mut level: ResMut<Level>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+) {
+    if loop_counter.0 != 0 {
+        return;
+    }
+
+    for &(location, color) in level_spec.candies.iter() {
+        level.spawn.push((location, Box::new(item_bundle(Item::Candy(color), &asset_server, &palette))));
+    }
+    for &(location, color, rounds) in level_spec.timed_candies.iter() {
+        level.spawn.push((location, Box::new(item_bundle(Item::TimedCandy(color, rounds), &asset_server, &palette))));
+    }
+}
+
+fn add_fuel_to_level(
+    mut level: ResMut<Level>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+) {
+    if loop_counter.0 != 0 {
+        return;
+    }
+
+    for &location in level_spec.fuel.iter() {
+        level.spawn.push((location, Box::new(item_bundle(Item::Fuel(1), &asset_server, &palette))));
+    }
+    for &(location, amount) in level_spec.canisters.iter() {
+        level.spawn.push((location, Box::new(item_bundle(Item::Fuel(amount), &asset_server, &palette))));
+    }
+}
+
+/// An item as it sits on the board, ready to parent to its cell. Also puts picked-up items back on undo.
+pub fn item_bundle(item: Item, asset_server: &AssetServer, palette: &Palette) -> (Item, SpriteBundle, DistributeOnGrid) {
+    let (texture, color, size) = match item {
+        Item::Candy(color) | Item::TimedCandy(color, _) => (color.texture(), Color::WHITE, 64.),
+        // Bigger canisters are drawn bigger.
+        Item::Fuel(amount) => ("fuel.png", Color::WHITE, canister_size(amount)),
+        Item::NetherCandy => ("blue-candy.png", NETHER_CANDY_COLOR, 64.),
+        Item::NumberedCandy(_) => ("blue-candy.png", Color::WHITE, 64.),
+        // Drawn pale, so the palette decides what color keys are, to go with their doors.
+        Item::Key => ("key.png", palette.key, 64.),
+        Item::Bomb => ("bomb.png", Color::WHITE, 64.),
+        Item::Magnet => ("magnet.png", Color::WHITE, 64.),
+        Item::TimeCrystal => ("blue-candy.png", TIME_CRYSTAL_COLOR, 64.),
+        Item::DoubleMove => ("double-move.png", Color::WHITE, 64.),
+    };
+    (
+        item,
+        SpriteBundle {
+            texture: asset_server.load(texture),
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::splat(size)),
+                ..default()
+            },
+            ..default()
+        },
+        DistributeOnGrid,
+    )
+}
+
+/// Sprite size for a fuel canister holding `amount`.
+pub fn canister_size(amount: i32) -> f32 {
+    48. + 16. * amount.clamp(1, 4) as f32
+}
+
+/// Blocks movement into its cell.
+#[derive(Component, Clone, Copy)]
+pub struct Wall;
+
+const WALL_Z: f32 = 0.1;
+
+fn add_walls_to_level(
+    mut level: ResMut<Level>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    palette: Res<Palette>,
+) {
+    if loop_counter.0 != 0 {
+        return;
+    }
+
+    for &location in level_spec.walls.iter() {
+        level.spawn.push((location, Box::new(wall_bundle(&palette))));
+    }
+}
 
+/// A wall as it sits in its cell. Also used for walls that show up mid-loop.
+pub fn wall_bundle(palette: &Palette) -> (Wall, SpriteBundle) {
+    (
+        Wall,
+        SpriteBundle {
+            sprite: Sprite {
+                color: palette.wall,
+                custom_size: Some(Vec2::splat(128.)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., WALL_Z),
+            ..default()
+        },
+    )
+}
+
+const TIME_CRYSTAL_COLOR: Color = Color::rgb(0.6, 1., 0.95);
+
+fn add_keys_and_doors_to_level(
+    mut level: ResMut<Level>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+) {
+    if loop_counter.0 != 0 {
+        return;
+    }
+
+    for &location in level_spec.keys.iter() {
+        level.spawn.push((location, Box::new(item_bundle(Item::Key, &asset_server, &palette))));
+    }
+    for &location in level_spec.doors.iter() {
+        level.spawn.push((location, Box::new(door_bundle(&palette))));
+    }
+}
+
+fn add_power_ups_to_level(
+    mut level: ResMut<Level>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+) {
+    if loop_counter.0 != 0 {
+        return;
+    }
+
+    for &location in level_spec.bombs.iter() {
+        level.spawn.push((location, Box::new(item_bundle(Item::Bomb, &asset_server, &palette))));
+    }
+    for &location in level_spec.magnets.iter() {
+        level.spawn.push((location, Box::new(item_bundle(Item::Magnet, &asset_server, &palette))));
+    }
+    for &location in level_spec.time_crystals.iter() {
+        level.spawn.push((location, Box::new(item_bundle(Item::TimeCrystal, &asset_server, &palette))));
+    }
+    for &location in level_spec.double_moves.iter() {
+        level.spawn.push((location, Box::new(item_bundle(Item::DoubleMove, &asset_server, &palette))));
+    }
+}
+
+const ICE_Z: f32 = 0.05;
+
+fn add_ice_to_level(mut level: ResMut<Level>, level_spec: Res<LevelSpec>, loop_counter: Res<LoopCounter>) {
+    if loop_counter.0 != 0 {
+        return;
+    }
+
+    for &location in level_spec.ice.iter() {
         let bundle = (
-            Item::Candy,
-            GridLocation (location),
+            Ice,
             SpriteBundle {
-                texture: asset_server.load(color),
                 sprite: Sprite {
-                    custom_size: Some(Vec2::splat(64.)),
+                    color: Color::rgba(0.75, 0.9, 1., 0.7),
+                    custom_size: Some(Vec2::splat(128.)),
                     ..default()
                 },
+                transform: Transform::from_xyz(0., 0., ICE_Z),
+                ..default()
+            },
+        );
+
+        level.spawn.push((location, Box::new(bundle)));
+    }
+}
+
+const CONVEYOR_Z: f32 = 0.05;
+const CONVEYOR_COLOR: Color = Color::rgb(0.3, 0.3, 0.35);
+const CONVEYOR_ARROW_COLOR: Color = Color::rgb(0.9, 0.8, 0.2);
+
+fn add_conveyors_to_level(mut level: ResMut<Level>, level_spec: Res<LevelSpec>, loop_counter: Res<LoopCounter>) {
+    if loop_counter.0 != 0 {
+        return;
+    }
+
+    for &(location, direction) in level_spec.conveyors.iter() {
+        level.spawn.push((location, Box::new(ConveyorTile {direction: direction.offset()})));
+    }
+}
+
+/// A belt with a chevron pointing the way it pushes. The chevron needs children, so this can't be a plain bundle.
+#[derive(Clone, Copy)]
+struct ConveyorTile {
+    direction: IVec2,
+}
+
+impl BundleBox for ConveyorTile {
+    fn apply_bundle(&self, parent: &mut ChildBuilder) {
+        let angle = self.direction.as_vec2().y.atan2(self.direction.as_vec2().x);
+        parent.spawn((
+            Conveyor {direction: self.direction},
+            SpriteBundle {
+                sprite: Sprite {
+                    color: CONVEYOR_COLOR,
+                    custom_size: Some(Vec2::splat(128.)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., 0., CONVEYOR_Z).with_rotation(Quat::from_rotation_z(angle)),
+                ..default()
+            },
+        )).with_children(|parent| {
+            for side in [-1., 1.] {
+                parent.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: CONVEYOR_ARROW_COLOR,
+                        custom_size: Some(Vec2::new(50., 10.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., side * 16., 0.01)
+                        .with_rotation(Quat::from_rotation_z(-side * std::f32::consts::FRAC_PI_4)),
+                    ..default()
+                });
+            }
+        });
+    }
+}
+
+const HAZARD_Z: f32 = 0.05;
+
+fn add_hazards_to_level(mut level: ResMut<Level>, level_spec: Res<LevelSpec>, loop_counter: Res<LoopCounter>) {
+    if loop_counter.0 != 0 {
+        return;
+    }
+
+    for &(location, hazard) in level_spec.hazards.iter() {
+        let color = match hazard {
+            Hazard::Burn(_) => Color::rgba(1., 0.35, 0.05, 0.7),
+            Hazard::Knockout => Color::rgba(0.5, 0., 0.15, 0.8),
+        };
+        let bundle = (
+            HazardTile(hazard),
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(110.)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., 0., HAZARD_Z),
                 ..default()
             },
-            DistributeOnGrid,
-            DespawnOnExitGameOver,
         );
 
-        level.spawn.push(Box::new(bundle));
+        level.spawn.push((location, Box::new(bundle)));
     }
 }
 
-const NUM_FUEL: usize = 2;
+const PLATE_Z: f32 = 0.05;
+const GATE_Z: f32 = 0.1;
+/// One color per gate, shared with the plates that open it.
+const GATE_COLORS: [Color; 3] = [
+    Color::rgb(0.9, 0.6, 0.1),
+    Color::rgb(0.3, 0.7, 0.3),
+    Color::rgb(0.6, 0.3, 0.8),
+];
 
-fn add_fuel_to_level(mut level: ResMut<Level>, loop_counter: Res<LoopCounter>, asset_server: Res<AssetServer>) {
+fn add_plates_to_level(mut level: ResMut<Level>, level_spec: Res<LevelSpec>, loop_counter: Res<LoopCounter>) {
     if loop_counter.0 != 0 {
         return;
     }
 
-    let mut rng = rand::thread_rng();
-    for _ in 0..NUM_FUEL {
-        let mut location = IVec2 {x: rng.gen_range(0..MAX_X), y: rng.gen_range(0..MAX_Y)};
-        while location == (START_SPACE) || location == (END_SPACE) {
-            location = IVec2 {x: rng.gen_range(0..MAX_X), y: rng.gen_range(0..MAX_Y)};
+    let mut gates: Vec<IVec2> = level_spec.plates.iter().map(|&(_, gate)| gate).collect();
+    gates.sort_by_key(|gate| (gate.x, gate.y));
+    gates.dedup();
+    for (index, &gate) in gates.iter().enumerate() {
+        let color = GATE_COLORS[index % GATE_COLORS.len()];
+        let plates: Vec<IVec2> = level_spec.plates.iter()
+            .filter(|&&(_, linked)| linked == gate)
+            .map(|&(plate, _)| plate)
+            .collect();
+        for &plate in plates.iter() {
+            let bundle = (
+                PressurePlate,
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: color.with_a(0.6),
+                        custom_size: Some(Vec2::splat(80.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., 0., PLATE_Z),
+                    ..default()
+                },
+            );
+            level.spawn.push((plate, Box::new(bundle)));
         }
+        level.spawn.push((gate, Box::new(gate_bundle(GateControl::Plates(plates), color, Vec2::new(128., 40.)))));
+    }
+}
+
+const SWITCH_Z: f32 = 0.05;
+/// One color per switch id, shared with its gates.
+const SWITCH_COLORS: [Color; 3] = [
+    Color::rgb(0.2, 0.6, 1.),
+    Color::rgb(1., 0.4, 0.6),
+    Color::rgb(0.9, 0.9, 0.9),
+];
+
+fn add_switches_to_level(mut level: ResMut<Level>, level_spec: Res<LevelSpec>, loop_counter: Res<LoopCounter>) {
+    if loop_counter.0 != 0 {
+        return;
+    }
 
+    let color = |id: u8| SWITCH_COLORS[id as usize % SWITCH_COLORS.len()];
+    for &(location, id) in level_spec.switches.iter() {
         let bundle = (
-            Item::Fuel,
-            GridLocation (location),
+            Switch(id),
             SpriteBundle {
-                texture: asset_server.load("fuel.png"),
                 sprite: Sprite {
-                    custom_size: Some(Vec2::splat(64.)),
+                    color: color(id).with_a(0.4),
+                    custom_size: Some(Vec2::splat(50.)),
                     ..default()
                 },
+                transform: Transform::from_xyz(0., 0., SWITCH_Z)
+                    .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
                 ..default()
             },
-            DistributeOnGrid,
-            DespawnOnExitGameOver,
         );
+        level.spawn.push((location, Box::new(bundle)));
+    }
+    // Upright bars, to tell them apart from the gates plates open.
+    for &(location, id, open_at_start) in level_spec.switch_gates.iter() {
+        let control = GateControl::Switch {id, open_at_start};
+        level.spawn.push((location, Box::new(gate_bundle(control, color(id), Vec2::new(40., 128.)))));
+    }
+}
+
+/// Starts out shut; opens on the first frame if whatever controls it says so.
+fn gate_bundle(control: GateControl, color: Color, size: Vec2) -> (Wall, Gate, SpriteBundle) {
+    (
+        Wall,
+        Gate {control, open: false},
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., GATE_Z),
+            ..default()
+        },
+    )
+}
+
+const TELEPORTER_Z: f32 = 0.06;
+/// One color per pair, so it's clear which ends go together.
+const TELEPORTER_COLORS: [Color; 3] = [
+    Color::rgba(0.2, 0.9, 0.9, 0.6),
+    Color::rgba(1., 0.5, 0.8, 0.6),
+    Color::rgba(0.9, 0.9, 0.3, 0.6),
+];
+
+fn add_teleporters_to_level(mut level: ResMut<Level>, level_spec: Res<LevelSpec>, loop_counter: Res<LoopCounter>) {
+    if loop_counter.0 != 0 {
+        return;
+    }
+
+    for (index, &(a, b)) in level_spec.teleporters.iter().enumerate() {
+        let color = TELEPORTER_COLORS[index % TELEPORTER_COLORS.len()];
+        for (location, partner) in [(a, b), (b, a)] {
+            let bundle = (
+                Teleporter {partner},
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(100.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., 0., TELEPORTER_Z)
+                        .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+                    ..default()
+                },
+            );
+            level.spawn.push((location, Box::new(bundle)));
+        }
+    }
+}
+
+const NETHER_CANDY_COLOR: Color = Color::rgba(0.7, 0.4, 1., 0.8);
+
+fn add_nether_candies_to_level(
+    mut level: ResMut<Level>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+) {
+    if loop_counter.0 != 0 {
+        return;
+    }
+
+    for &location in level_spec.nether_candies.iter() {
+        level.spawn.push((location, Box::new(item_bundle(Item::NetherCandy, &asset_server, &palette))));
+    }
+}
 
-        level.spawn.push(Box::new(bundle));
+// Nether candy doesn't exist yet on the first loop; it's there to reward routes planned for your future past self.
+fn reveal_nether_candies(mut items: Query<(&Item, &mut Visibility)>, loop_counter: Res<LoopCounter>) {
+    for (item, mut visibility) in items.iter_mut() {
+        if let Item::NetherCandy = item {
+            *visibility = if loop_counter.0 == 0 { Visibility::Hidden } else { Visibility::Inherited };
+        }
     }
 }
 
-fn reset_level(mut level: ResMut<Level>, loop_counter: Res<LoopCounter>) {
+fn add_numbered_candies_to_level(
+    mut level: ResMut<Level>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+) {
     if loop_counter.0 != 0 {
         return;
     }
 
-    level.spawn.clear();
+    for (index, &location) in level_spec.numbered_candies.iter().enumerate() {
+        level.spawn.push((location, Box::new(item_bundle(Item::NumberedCandy(index as u8 + 1), &asset_server, &palette))));
+    }
+}
+
+fn label_numbered_candies(mut commands: Commands, items: Query<(Entity, &Item), Added<Item>>) {
+    for (entity, item) in items.iter() {
+        if let Item::NumberedCandy(number) = item {
+            commands.entity(entity).with_children(|parent| {
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(number.to_string(), TextStyle {
+                        font_size: 40.,
+                        color: Color::BLACK,
+                        ..default()
+                    }),
+                    transform: Transform::from_xyz(0., 0., 0.01),
+                    ..default()
+                });
+            });
+        }
+    }
+}
+
+fn spawn_level(mut commands: Commands, level: Res<Level>, cells: Res<GridCells>) {
+    for (location, spawn) in level.spawn.iter() {
+        let Some(cell) = cells.get(*location) else {
+            warn!("Nowhere to put something at {}, it's off the board", location);
+            continue;
+        };
+        commands.entity(cell).with_children(|parent| spawn.apply_bundle(parent));
+    }
+}
+
+fn count_candy_on_board(mut candy_remaining: ResMut<CandyRemaining>, items: Query<(&Item, &Visibility)>) {
+    candy_remaining.0 = items.iter()
+        .filter(|(item, visibility)| item.is_candy() && **visibility != Visibility::Hidden)
+        .count() as i32;
+}
+
+/// Over an exit while the candy quota is still to meet.
+#[derive(Component)]
+struct ExitLock;
+
+const EXIT_LOCK_Z: f32 = 0.3;
+const EXIT_LOCK_COLOR: Color = Color::rgb(0.35, 0.3, 0.25);
+
+// Every loop's exit, since past selves may be headed somewhere other than the player.
+fn add_exit_locks(
+    mut commands: Commands,
+    rules: Res<GameRules>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    cells: Res<GridCells>,
+) {
+    if rules.candy_quota.is_none() {
+        return;
+    }
+    let mut exits: Vec<IVec2> = (0..=loop_counter.0).map(|loop_number| level_spec.end_for_loop(loop_number)).collect();
+    exits.sort_by_key(|exit| (exit.x, exit.y));
+    exits.dedup();
+    for cell in exits.into_iter().filter_map(|exit| cells.get(exit)) {
+        commands.entity(cell).with_children(|parent| {
+            // A padlock: the body, with the shackle sticking up behind it.
+            parent.spawn((
+                ExitLock,
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: EXIT_LOCK_COLOR,
+                        custom_size: Some(Vec2::new(44., 36.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., -8., EXIT_LOCK_Z),
+                    ..default()
+                },
+            )).with_children(|parent| {
+                parent.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: EXIT_LOCK_COLOR,
+                        custom_size: Some(Vec2::new(28., 30.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., 22., -0.01),
+                    ..default()
+                });
+                parent.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb(0.6, 0.55, 0.5),
+                        custom_size: Some(Vec2::new(16., 22.)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., 24., -0.005),
+                    ..default()
+                });
+            });
+        });
+    }
 }
 
-fn spawn_level(mut commands: Commands, level: Res<Level>) {
-    for spawn in level.spawn.iter() {
-        spawn.apply_bundle(&mut commands);
+// Hidden rather than despawned, so an undo can lock the exits again.
+fn unlock_exits(
+    rules: Res<GameRules>,
+    candy_remaining: Res<CandyRemaining>,
+    candy_collected: Res<CandyCollected>,
+    mut locks: Query<&mut Visibility, With<ExitLock>>,
+) {
+    let visibility = if rules.exits_open(candy_collected.0, candy_remaining.0) { Visibility::Hidden } else { Visibility::Inherited };
+    for mut lock in locks.iter_mut() {
+        if *lock != visibility {
+            *lock = visibility;
+        }
     }
 }
 
+/// Shares its cell with the other items there, arranged around the cell center.
 #[derive(Component, Clone, Copy)]
 pub struct DistributeOnGrid;
 
-fn distribute_on_grid(mut query: Query<(&mut Transform, &GridLocation), With<DistributeOnGrid>>) {
-    // Group by location.
-    let mut transforms_per_location = query.iter_mut().fold(HashMap::new(),
-        |mut map, (transform, grid_location)| {
-            map.entry(grid_location).or_insert(vec![]).push(transform);
-            map
-        });
+const ITEM_Z: f32 = 0.2;
 
-    for (grid_location, entities) in transforms_per_location.iter_mut() {
-        let center: Vec2 = (grid_location.0 * GRID_SPACING).as_vec2();
+// Runs whenever a cell's contents change, so the items left behind after a pickup spread back out.
+fn distribute_on_grid(
+    cells: Query<&Children, (With<GridCell>, Changed<Children>)>,
+    mut items: Query<&mut Transform, With<DistributeOnGrid>>,
+) {
+    for children in cells.iter() {
+        let mut entities: Vec<_> = children.iter().filter(|child| items.contains(**child)).collect();
         let count = entities.len() as i32;
         match count {
+            0 => {},
             1 => {
-                let transform = entities.first_mut().unwrap();
-                transform.translation = center.extend(0.);
+                let mut transform = items.get_mut(*entities.remove(0)).unwrap();
+                transform.translation = Vec3::new(0., 0., ITEM_Z);
+                transform.scale = Vec3::ONE;
             },
             _ => {
                 // Arrange the entities radially around the center.
                 let angle = 2. * std::f32::consts::PI / count as f32;
                 let initial_angle = if count % 2 == 0 { angle / 2. } else { 0. };
-                for (i, transform) in entities.iter_mut().enumerate() {
+                for (i, entity) in entities.into_iter().enumerate() {
                     let radial_vector = Vec2 {
                         x: GRID_SPACING as f32 / 4. * (i as f32 * angle + initial_angle).cos(),
                         y: GRID_SPACING as f32 / 4. * (i as f32 * angle + initial_angle).sin()
                     };
-                    transform.translation = (center + radial_vector).extend(0.);
+                    let mut transform = items.get_mut(*entity).unwrap();
+                    transform.translation = radial_vector.extend(ITEM_Z);
                     transform.scale = Vec3::splat(0.7);
                 }
             },
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use crate::SootSprite;
+use crate::grid::AnimateTranslation;
+
+/// Each frame of the soot sheet is the size of the old single soot sprite, so soots come out the same size.
+const SOOT_FRAME_SIZE: f32 = 125.;
+const SOOT_FRAMES: usize = 8;
+/// Breathing in and out while standing still.
+pub const SOOT_IDLE: AnimationClip = AnimationClip {first: 0, len: 4, fps: 4.};
+/// Hopping along, while a move is being animated.
+pub const SOOT_WALK: AnimationClip = AnimationClip {first: 4, len: 4, fps: 12.};
+
+pub struct SpriteAnimationPlugin;
+
+impl Plugin for SpriteAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, load_soot_sheet)
+            .add_systems(Update, (walk_while_moving, animate_sprites).chain());
+    }
+}
+
+/// A run of frames in a sprite sheet, played on a loop.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnimationClip {
+    pub first: usize,
+    pub len: usize,
+    pub fps: f32,
+}
+
+/// Steps a `TextureAtlasSprite` through the frames of a clip.
+#[derive(Component)]
+pub struct AnimatedSprite {
+    clip: AnimationClip,
+    frame: usize,
+    timer: Timer,
+}
+
+impl AnimatedSprite {
+    pub fn new(clip: AnimationClip) -> Self {
+        AnimatedSprite {clip, frame: 0, timer: Timer::from_seconds(1. / clip.fps, TimerMode::Repeating)}
+    }
+
+    /// Switches to `clip` from its first frame, unless it's already playing.
+    pub fn play(&mut self, clip: AnimationClip) {
+        if self.clip != clip {
+            *self = AnimatedSprite::new(clip);
+        }
+    }
+}
+
+/// Which way a sprite was last headed. Only sideways moves turn it; there's no art for facing up or down. The sheets
+/// are drawn facing right.
+#[derive(Component, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Facing {
+    Left,
+    #[default]
+    Right,
+}
+
+impl Facing {
+    /// The way a move of `offset` turns something already facing `self`.
+    pub fn after_move(self, offset: IVec2) -> Facing {
+        match offset.x {
+            x if x < 0 => Facing::Left,
+            x if x > 0 => Facing::Right,
+            _ => self,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct SootSheet(pub Handle<TextureAtlas>);
+
+fn load_soot_sheet(mut commands: Commands, asset_server: Res<AssetServer>, mut atlases: ResMut<Assets<TextureAtlas>>) {
+    let atlas = TextureAtlas::from_grid(asset_server.load("soot-sheet.png"), Vec2::splat(SOOT_FRAME_SIZE), SOOT_FRAMES, 1, None, None);
+    commands.insert_resource(SootSheet(atlases.add(atlas)));
+}
+
+/// What a soot on the board is drawn with, idling to start with.
+pub fn soot_sprite_bundle(sheet: &SootSheet, color: Color, transform: Transform) -> (SpriteSheetBundle, AnimatedSprite, Facing) {
+    (
+        SpriteSheetBundle {
+            texture_atlas: sheet.0.clone(),
+            sprite: TextureAtlasSprite {color, ..default()},
+            transform,
+            ..default()
+        },
+        AnimatedSprite::new(SOOT_IDLE),
+        Facing::default(),
+    )
+}
+
+fn walk_while_moving(mut soots: Query<(&AnimateTranslation, &mut AnimatedSprite), With<SootSprite>>) {
+    for (animation, mut sprite) in soots.iter_mut() {
+        sprite.play(if animation.timer.finished() { SOOT_IDLE } else { SOOT_WALK });
+    }
+}
+
+fn animate_sprites(time: Res<Time>, mut sprites: Query<(&mut AnimatedSprite, &mut TextureAtlasSprite, Option<&Facing>)>) {
+    for (mut animated, mut sprite, facing) in sprites.iter_mut() {
+        let ticks = animated.timer.tick(time.delta()).times_finished_this_tick() as usize;
+        animated.frame = (animated.frame + ticks) % animated.clip.len;
+        let index = animated.clip.first + animated.frame;
+        if sprite.index != index {
+            sprite.index = index;
+        }
+        let flip_x = facing == Some(&Facing::Left);
+        if sprite.flip_x != flip_x {
+            sprite.flip_x = flip_x;
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::{Path, PathBuf};
+
+// Saved files are plain files natively. The browser has no filesystem, so there each path is a key in the page's
+// local storage instead.
+
+/// Set to keep saves somewhere other than the usual place, e.g. so a test run can't touch the player's own.
+pub const DATA_DIR_VAR: &str = "INTERFERENCE_FACTORY_DATA_DIR";
+
+/// Where a save file goes: the platform's per-user data directory natively, or just its name in the browser.
+pub fn save_path(file: &str) -> Option<PathBuf> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(dir) = std::env::var_os(DATA_DIR_VAR) {
+        return Some(PathBuf::from(dir).join(file));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    return directories::ProjectDirs::from("", "", "interference-factory").map(|dirs| dirs.data_dir().join(file));
+    #[cfg(target_arch = "wasm32")]
+    return Some(PathBuf::from(file));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|err| format!("couldn't read {}: {}", path.display(), err))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| format!("couldn't create {}: {}", dir.display(), err))?;
+    }
+    std::fs::write(path, contents).map_err(|err| format!("couldn't write {}: {}", path.display(), err))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, String> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| "local storage isn't available".to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read(path: &Path) -> Result<String, String> {
+    local_storage()?
+        .get_item(&path.to_string_lossy())
+        .ok()
+        .flatten()
+        .ok_or_else(|| format!("couldn't read {}: not saved in this browser", path.display()))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(path: &Path, contents: &str) -> Result<(), String> {
+    local_storage()?
+        .set_item(&path.to_string_lossy(), contents)
+        .map_err(|_| format!("couldn't write {}: local storage is full or blocked", path.display()))
+}
+
+/// Seconds since the Unix epoch, for naming saved files.
+pub fn unix_time() -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    return std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    // SystemTime isn't implemented on wasm32-unknown-unknown.
+    #[cfg(target_arch = "wasm32")]
+    return (js_sys::Date::now() / 1000.) as u64;
+}
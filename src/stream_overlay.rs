@@ -0,0 +1,109 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{LoopCounter, Move, Player, SootSprite};
+use crate::inventory::Inventory;
+use crate::scoring::Score;
+
+/// Streamers point an OBS browser source (or anything that can poll HTTP) at this address.
+const OVERLAY_ADDRESS: &str = "127.0.0.1:8910";
+
+pub struct StreamOverlayPlugin;
+
+impl Plugin for StreamOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        let listener = match TcpListener::bind(OVERLAY_ADDRESS) {
+            Ok(listener) => listener,
+            Err(err) => {
+                warn!("Stream overlay disabled, couldn't bind {}: {}", OVERLAY_ADDRESS, err);
+                return;
+            }
+        };
+        if let Err(err) = listener.set_nonblocking(true) {
+            warn!("Stream overlay disabled, couldn't make listener non-blocking: {}", err);
+            return;
+        }
+        info!("Stream overlay serving game state on http://{}", OVERLAY_ADDRESS);
+
+        app
+            .insert_resource(OverlayListener(listener))
+            .insert_resource(OverlayState::default())
+            .add_systems(Update, (
+                (track_score, track_loop, track_last_move),
+                serve_overlay_requests,
+            ).chain());
+    }
+}
+
+#[derive(Resource)]
+struct OverlayListener(TcpListener);
+
+#[derive(Resource, Default)]
+struct OverlayState {
+    score: i32,
+    loop_number: i32,
+    last_move: Option<IVec2>,
+}
+
+impl OverlayState {
+    fn to_json(&self) -> String {
+        let last_move = match self.last_move {
+            Some(offset) => format!("[{}, {}]", offset.x, offset.y),
+            None => "null".to_string(),
+        };
+        format!("{{\"score\": {}, \"loop\": {}, \"last_move\": {}}}", self.score, self.loop_number, last_move)
+    }
+}
+
+fn track_score(
+    player: Query<(&Inventory, &Score), (With<Player>, Or<(Changed<Inventory>, Changed<Score>)>)>,
+    mut state: ResMut<OverlayState>,
+) {
+    for (inventory, score) in player.iter() {
+        state.score = score.total(inventory);
+    }
+}
+
+fn track_loop(loop_counter: Res<LoopCounter>, mut state: ResMut<OverlayState>) {
+    if loop_counter.is_changed() {
+        state.loop_number = loop_counter.0 + 1;
+    }
+}
+
+// Chasers move through the same events, but viewers only care where the soots went.
+fn track_last_move(mut moves: EventReader<Move>, mut state: ResMut<OverlayState>, soots: Query<(), With<SootSprite>>) {
+    if let Some(event) = moves.iter().filter(|event| soots.contains(event.mover)).last() {
+        state.last_move = Some(event.offset);
+    }
+}
+
+fn serve_overlay_requests(listener: Res<OverlayListener>, state: Res<OverlayState>) {
+    loop {
+        let mut stream = match listener.0.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => return,
+            Err(err) => {
+                warn!("Stream overlay accept failed: {}", err);
+                return;
+            }
+        };
+
+        // We answer every request with the same document, so the request itself only needs draining.
+        let _ = stream.set_nonblocking(false);
+        let _ = stream.set_read_timeout(Some(Duration::from_millis(50)));
+        let mut request = [0u8; 1024];
+        let _ = stream.read(&mut request);
+
+        let body = state.to_json();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\n\
+            Cache-Control: no-store\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(), body);
+        if let Err(err) = stream.write_all(response.as_bytes()) {
+            warn!("Stream overlay write failed: {}", err);
+        }
+    }
+}
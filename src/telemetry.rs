@@ -0,0 +1,101 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::{AppState, StartLoop, LoopCounter};
+use crate::inventory::{CandyByLoop, Item};
+use crate::rules::GameRules;
+use crate::shop::Upgrades;
+
+/// Playtest telemetry is strictly opt-in: nothing is recorded unless this variable is set.
+const OPT_IN_VAR: &str = "INTERFERENCE_FACTORY_TELEMETRY";
+const TELEMETRY_FILE: &str = "telemetry.jsonl";
+
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        if std::env::var_os(OPT_IN_VAR).is_none() {
+            return;
+        }
+        info!("Telemetry enabled, appending session summary to {}", TELEMETRY_FILE);
+
+        app
+            .insert_resource(TelemetrySession::default())
+            .add_systems(OnEnter(AppState::Playing), count_level_attempts.in_set(StartLoop))
+            .add_systems(OnEnter(AppState::LoopTransition), record_loop_end)
+            .add_systems(OnEnter(AppState::GameOver), record_loop_end)
+            .add_systems(Last, write_session_on_exit);
+    }
+}
+
+/// Aggregate counters only; no identifiers, timestamps or inputs are kept.
+#[derive(Resource, Default)]
+struct TelemetrySession {
+    levels_attempted: i32,
+    runs_completed: i32,
+    loops_played: i32,
+    candy_left_at_run_end: Vec<i32>,
+    /// Points scored on the last loop of each run, per loop whose soot collected them.
+    candy_by_loop_at_run_end: Vec<Vec<i32>>,
+}
+
+impl TelemetrySession {
+    fn to_json(&self, ended_in: AppState, ended_on_loop: i32) -> String {
+        let candy_left: Vec<String> = self.candy_left_at_run_end.iter().map(i32::to_string).collect();
+        let candy_by_loop: Vec<String> = self.candy_by_loop_at_run_end.iter()
+            .map(|run| format!("[{}]", run.iter().map(i32::to_string).collect::<Vec<_>>().join(", ")))
+            .collect();
+        format!(
+            "{{\"levels_attempted\": {}, \"runs_completed\": {}, \"loops_played\": {}, \
+            \"candy_left_at_run_end\": [{}], \"candy_by_loop_at_run_end\": [{}], \"ended_in\": \"{:?}\", \
+            \"ended_on_loop\": {}}}",
+            self.levels_attempted, self.runs_completed, self.loops_played,
+            candy_left.join(", "), candy_by_loop.join(", "), ended_in, ended_on_loop + 1)
+    }
+}
+
+fn count_level_attempts(mut session: ResMut<TelemetrySession>, loop_counter: Res<LoopCounter>) {
+    if loop_counter.0 == 0 {
+        session.levels_attempted += 1;
+    }
+}
+
+fn record_loop_end(
+    mut session: ResMut<TelemetrySession>,
+    loop_counter: Res<LoopCounter>,
+    rules: Res<GameRules>,
+    upgrades: Res<Upgrades>,
+    items: Query<&Item>,
+    candy_by_loop: Res<CandyByLoop>,
+) {
+    session.loops_played += 1;
+    if loop_counter.is_final_loop(&rules, &upgrades) {
+        session.runs_completed += 1;
+        let candy_left = items.iter().filter(|item| item.is_candy()).count() as i32;
+        session.candy_left_at_run_end.push(candy_left);
+        // Every loop gets an entry, including any whose soot came away empty-handed.
+        let by_loop = (0..=loop_counter.0).map(|loop_number| candy_by_loop.0.get(&loop_number).copied().unwrap_or(0)).collect();
+        session.candy_by_loop_at_run_end.push(by_loop);
+    }
+}
+
+fn write_session_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    session: Res<TelemetrySession>,
+    app_state: Res<State<AppState>>,
+    loop_counter: Res<LoopCounter>,
+) {
+    if exit_events.iter().next().is_none() {
+        return;
+    }
+
+    let line = session.to_json(*app_state.get(), loop_counter.0);
+    let result = OpenOptions::new().create(true).append(true).open(TELEMETRY_FILE)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(err) = result {
+        warn!("Couldn't write telemetry to {}: {}", TELEMETRY_FILE, err);
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+
+use crate::{move_soot_on_grid, soot_blocks, AppState, LoopCounter, Move, SootSprite, WAIT};
+use crate::grid::{AnimateTranslation, ApplyGridMovement, FollowUpMove, GridCells, GridLocation};
+use crate::inventory::PickUpItems;
+use crate::level_spec::LevelSpec;
+use crate::rules::GameRules;
+
+pub struct TeleporterPlugin;
+
+impl Plugin for TeleporterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            enter_teleporters.after(move_soot_on_grid).before(ApplyGridMovement),
+            animate_teleports.after(ApplyGridMovement).before(PickUpItems),
+        ).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// One end of a linked pair. A soot that finishes a move here is carried on to `partner` before it picks anything
+/// up, within the same turn.
+#[derive(Component, Clone, Copy)]
+pub struct Teleporter {
+    pub partner: IVec2,
+}
+
+/// On a soot between the two ends of a teleporter: it blinks out at one end and back in at the other.
+#[derive(Component)]
+struct Teleporting;
+
+// Where soots block each other, one standing on the far end keeps the teleporter from going off.
+pub fn enter_teleporters(
+    mut commands: Commands,
+    mut moves: EventReader<Move>,
+    movers: Query<(Entity, &GridLocation, Option<&SootSprite>)>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    teleporters: Query<&Teleporter>,
+    rules: Res<GameRules>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+) {
+    // Waiting on a teleporter doesn't set it off again.
+    for event in moves.iter().filter(|event| event.offset != WAIT) {
+        let Ok((_, location, soot)) = movers.get(event.mover) else {
+            continue;
+        };
+        let soots = movers.iter().filter_map(|(entity, location, soot)| soot.map(|soot| (entity, location.0, soot.id)));
+        let teleporter = cells.contents(location.0, &children).iter()
+            .find_map(|&entity| teleporters.get(entity).ok())
+            .filter(|teleporter| !(soot.is_some()
+                && soot_blocks(teleporter.partner, event.mover, soots, &rules, &level_spec, &loop_counter)));
+        if let Some(teleporter) = teleporter {
+            commands.entity(event.mover).insert((FollowUpMove(teleporter.partner), Teleporting));
+        }
+    }
+}
+
+// Runs over the second leg only; the step onto the teleporter animates like any other move.
+fn animate_teleports(
+    mut commands: Commands,
+    mut soots: Query<(Entity, &mut Transform, &AnimateTranslation), (With<Teleporting>, Without<FollowUpMove>)>,
+) {
+    for (entity, mut transform, animation) in soots.iter_mut() {
+        if animation.timer.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<Teleporting>();
+            continue;
+        }
+        // Shrink away at the entrance, then grow back at the exit instead of sliding across the board.
+        let progress = animation.timer.percent();
+        let position = if progress < 0.5 { animation.start } else { animation.end };
+        transform.translation = position.extend(transform.translation.z);
+        transform.scale = Vec3::splat((2. * progress - 1.).abs());
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+
+pub struct TextInputPlugin;
+
+impl Plugin for TextInputPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(TextInputFocus(None))
+            .add_systems(Update, (focus_on_click, type_into_focused, render_text_inputs).chain());
+    }
+}
+
+const FIELD_COLOR: Color = Color::rgb(0.08, 0.08, 0.08);
+const FOCUSED_FIELD_COLOR: Color = Color::rgb(0.2, 0.2, 0.25);
+const PLACEHOLDER_COLOR: Color = Color::GRAY;
+
+/// A single-line text field. Click it to type; read what was typed from `value`.
+#[derive(Component)]
+pub struct TextInput {
+    pub value: String,
+    placeholder: String,
+    max_len: usize,
+    /// Characters that can't be typed or pasted in are dropped.
+    accepts: fn(char) -> bool,
+}
+
+/// The text input taking keyboard input, if any.
+#[derive(Resource)]
+pub struct TextInputFocus(pub Option<Entity>);
+
+/// A text input with a greyed-out hint shown while it's empty. `marker` tells the menu's systems which field it is.
+pub fn spawn_text_input(
+    parent: &mut ChildBuilder,
+    placeholder: impl Into<String>,
+    max_len: usize,
+    accepts: fn(char) -> bool,
+    marker: impl Component,
+) {
+    parent.spawn((
+        marker,
+        TextInput {value: String::new(), placeholder: placeholder.into(), max_len, accepts},
+        Interaction::default(),
+        NodeBundle {
+            style: Style {
+                min_width: Val::Px(300.),
+                height: Val::Px(50.),
+                padding: UiRect::horizontal(Val::Px(10.)),
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: FIELD_COLOR.into(),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("", TextStyle::default()));
+    });
+}
+
+// Clicking or tapping a field focuses it; clicking anywhere else lets go of the keyboard.
+fn focus_on_click(
+    mouse: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    inputs: Query<(Entity, &Interaction), With<TextInput>>,
+    mut focus: ResMut<TextInputFocus>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) && !touches.any_just_pressed() {
+        return;
+    }
+    let clicked = inputs.iter().find(|(_, interaction)| **interaction == Interaction::Pressed).map(|(entity, _)| entity);
+    if focus.0 != clicked {
+        focus.0 = clicked;
+    }
+}
+
+fn type_into_focused(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut focus: ResMut<TextInputFocus>,
+    mut inputs: Query<&mut TextInput>,
+) {
+    let Some(mut input) = focus.0.and_then(|entity| inputs.get_mut(entity).ok()) else {
+        characters.clear();
+        return;
+    };
+
+    if keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::Escape]) {
+        characters.clear();
+        focus.0 = None;
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        input.value.pop();
+    }
+
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight]);
+    let mut typed: String = characters.iter().map(|event| event.char).filter(|c| !c.is_control()).collect();
+    // Holding ctrl still sends the V through as a character on some platforms.
+    if ctrl {
+        typed.clear();
+    }
+    // The browser only hands the clipboard to a page through paste events, which never reach the game.
+    #[cfg(not(target_arch = "wasm32"))]
+    if ctrl && keyboard_input.just_pressed(KeyCode::V) {
+        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) => typed = text,
+            Err(err) => warn!("Couldn't paste: {}", err),
+        }
+    }
+
+    let accepts = input.accepts;
+    for c in typed.chars().filter(|c| accepts(*c)) {
+        if input.value.chars().count() >= input.max_len {
+            break;
+        }
+        input.value.push(c);
+    }
+}
+
+fn render_text_inputs(
+    time: Res<Time>,
+    focus: Res<TextInputFocus>,
+    mut inputs: Query<(Entity, &TextInput, &mut BackgroundColor, &Children)>,
+    mut labels: Query<&mut Text>,
+) {
+    // Real time, so the caret still blinks with the game paused or slowed down.
+    let caret_shown = time.raw_elapsed_seconds().fract() < 0.5;
+    for (entity, input, mut background, children) in inputs.iter_mut() {
+        let focused = focus.0 == Some(entity);
+        let (value, color) = match (input.value.is_empty(), focused) {
+            (true, false) => (input.placeholder.clone(), PLACEHOLDER_COLOR),
+            _ if focused && caret_shown => (format!("{}|", input.value), Color::WHITE),
+            _ => (input.value.clone(), Color::WHITE),
+        };
+        *background = if focused { FOCUSED_FIELD_COLOR } else { FIELD_COLOR }.into();
+        for &child in children.iter() {
+            if let Ok(mut text) = labels.get_mut(child) {
+                text.sections[0].value = value.clone();
+                text.sections[0].style.color = color;
+            }
+        }
+    }
+}
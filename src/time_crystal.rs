@@ -0,0 +1,46 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{AppState, LoopCounter, StartLoop};
+use crate::inventory::{Item, ItemGet, PickUpItems};
+use crate::rules::GameRules;
+use crate::toast::Toast;
+
+pub struct TimeCrystalPlugin;
+
+impl Plugin for TimeCrystalPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(ClaimedCrystals::default())
+            .add_systems(OnEnter(AppState::Playing), clear_claimed_crystals.in_set(StartLoop))
+            .add_systems(Update, add_bonus_loops.after(PickUpItems).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Cells whose time crystal has already added its loop this run. The board is put back every loop, and past selves
+/// pick their crystals up again as they replay, so each one only counts the first time.
+#[derive(Resource, Default, Clone)]
+pub struct ClaimedCrystals(HashSet<IVec2>);
+
+fn clear_claimed_crystals(loop_counter: Res<LoopCounter>, mut claimed: ResMut<ClaimedCrystals>) {
+    if loop_counter.0 == 0 {
+        claimed.0.clear();
+    }
+}
+
+// Whoever picks it up, past self or present, the whole run gets the extra loop.
+fn add_bonus_loops(
+    mut rules: ResMut<GameRules>,
+    mut claimed: ResMut<ClaimedCrystals>,
+    mut pickups: EventReader<ItemGet>,
+    mut toasts: EventWriter<Toast>,
+) {
+    for pickup in pickups.iter().filter(|pickup| matches!(pickup.item, Item::TimeCrystal)) {
+        if !claimed.0.insert(pickup.cell) {
+            continue;
+        }
+        rules.bonus_loops += 1;
+        toasts.send(Toast::success("Time crystal! One more loop this run"));
+    }
+}
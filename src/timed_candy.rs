@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+
+use crate::{next_turn, AppState, LoopCounter, RoundCounter, SootSprite};
+use crate::grid::{GridCell, GridLocation};
+use crate::inventory::{CandyRemaining, Item};
+use crate::palette::Palette;
+use crate::spawn_level::item_bundle;
+use crate::undo::Undone;
+
+/// Rounds left at which the countdown turns red.
+const HURRY_ROUNDS: i32 = 2;
+const COUNTDOWN_COLOR: Color = Color::BLACK;
+const HURRY_COLOR: Color = Color::rgb(0.8, 0.05, 0.05);
+
+pub struct TimedCandyPlugin;
+
+impl Plugin for TimedCandyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            label_timed_candies,
+            (expire_timed_candy, restore_expired_candy.run_if(on_event::<Undone>())),
+            count_down,
+        ).chain().after(next_turn).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Shows how many rounds a timed candy has left.
+#[derive(Component)]
+struct Countdown;
+
+/// Timed candy that ran out, kept out of sight on its cell so undoing back before then can put it back. It's only gone
+/// for the rest of the loop: the next loop lays the board out afresh, timer and all.
+#[derive(Component)]
+struct ExpiredCandy {
+    item: Item,
+    loop_number: i32,
+    round: i32,
+}
+
+fn rounds_left(item: &Item, round: i32) -> Option<i32> {
+    match item {
+        Item::TimedCandy(_, rounds) => Some(rounds - round),
+        _ => None,
+    }
+}
+
+// Added in Update rather than on spawn, so candy put back by undo gets one too.
+fn label_timed_candies(mut commands: Commands, items: Query<(Entity, &Item), Added<Item>>) {
+    for (entity, _) in items.iter().filter(|(_, item)| matches!(item, Item::TimedCandy(..))) {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Countdown,
+                Text2dBundle {
+                    text: Text::from_section("", TextStyle {
+                        font_size: 40.,
+                        color: COUNTDOWN_COLOR,
+                        ..default()
+                    }),
+                    transform: Transform::from_xyz(0., 0., 0.01),
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+// Like decaying candy, it hangs on while a soot is standing on it, which also keeps it from running out on the turn
+// it's being picked up.
+fn expire_timed_candy(
+    mut commands: Commands,
+    loop_counter: Res<LoopCounter>,
+    round_counter: Res<RoundCounter>,
+    soots: Query<&GridLocation, With<SootSprite>>,
+    items: Query<(Entity, &Item, &Parent)>,
+    cell_locations: Query<&GridLocation, With<GridCell>>,
+    mut candy_remaining: ResMut<CandyRemaining>,
+) {
+    let round = round_counter.0;
+    for (entity, item, parent) in items.iter() {
+        if rounds_left(item, round).is_none_or(|left| left > 0) {
+            continue;
+        }
+        let Ok(&location) = cell_locations.get(parent.get()) else {
+            continue;
+        };
+        if soots.iter().any(|soot_location| *soot_location == location) {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        commands.entity(parent.get()).with_children(|parent| {
+            parent.spawn(ExpiredCandy {item: *item, loop_number: loop_counter.0, round});
+        });
+        candy_remaining.0 -= 1;
+    }
+}
+
+// Undo puts the candy count back along with the round.
+fn restore_expired_candy(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    palette: Res<Palette>,
+    loop_counter: Res<LoopCounter>,
+    round_counter: Res<RoundCounter>,
+    expired: Query<(Entity, &ExpiredCandy, &Parent)>,
+) {
+    let round = round_counter.0;
+    for (entity, expired, parent) in expired.iter() {
+        if expired.loop_number != loop_counter.0 || expired.round <= round {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        commands.entity(parent.get()).with_children(|parent| {
+            parent.spawn(item_bundle(expired.item, &asset_server, &palette));
+        });
+    }
+}
+
+fn count_down(
+    round_counter: Res<RoundCounter>,
+    items: Query<&Item>,
+    mut countdowns: Query<(&Parent, &mut Text), With<Countdown>>,
+) {
+    let round = round_counter.0;
+    for (parent, mut text) in countdowns.iter_mut() {
+        let Some(left) = items.get(parent.get()).ok().and_then(|item| rounds_left(item, round)) else {
+            continue;
+        };
+        let value = left.max(0).to_string();
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+            text.sections[0].style.color = if left <= HURRY_ROUNDS { HURRY_COLOR } else { COUNTDOWN_COLOR };
+        }
+    }
+}
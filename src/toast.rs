@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::palette::Palette;
+
+/// How many toasts are on screen at once; the rest wait their turn.
+const MAX_SHOWN: usize = 3;
+const TOAST_SECS: f32 = 3.;
+const FADE_SECS: f32 = 0.3;
+/// Above the menus, below the debug overlay.
+const TOAST_Z_INDEX: i32 = 90;
+
+pub struct ToastPlugin;
+
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<Toast>()
+            .insert_resource(ToastQueue::default())
+            .add_systems(Startup, spawn_toast_stack)
+            .add_systems(Update, (queue_toasts, show_queued_toasts, fade_toasts).chain());
+    }
+}
+
+/// A short message shown in the corner for a few seconds. Send one from anywhere to notify the player.
+#[derive(Event, Debug, Clone)]
+pub struct Toast {
+    text: String,
+    kind: ToastKind,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ToastKind {
+    Info,
+    Success,
+    Warning,
+}
+
+impl Toast {
+    pub fn info(text: impl Into<String>) -> Self {
+        Self {text: text.into(), kind: ToastKind::Info}
+    }
+
+    /// Something worth celebrating, like an achievement or a record.
+    pub fn success(text: impl Into<String>) -> Self {
+        Self {text: text.into(), kind: ToastKind::Success}
+    }
+
+    /// Something went wrong that the player should know about.
+    pub fn warning(text: impl Into<String>) -> Self {
+        Self {text: text.into(), kind: ToastKind::Warning}
+    }
+}
+
+impl ToastKind {
+    fn color(&self, palette: &Palette) -> Color {
+        match self {
+            ToastKind::Info => palette.info,
+            ToastKind::Success => palette.success,
+            ToastKind::Warning => palette.warning,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct ToastQueue(VecDeque<Toast>);
+
+/// Column in the top-right corner that shown toasts stack in, oldest on top.
+#[derive(Component)]
+struct ToastStack;
+
+#[derive(Component)]
+struct ShownToast {
+    timer: Timer,
+    color: Color,
+}
+
+fn spawn_toast_stack(mut commands: Commands) {
+    commands.spawn((
+        ToastStack,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.),
+                right: Val::Px(10.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                row_gap: Val::Px(8.),
+                ..default()
+            },
+            z_index: ZIndex::Global(TOAST_Z_INDEX),
+            ..default()
+        },
+    ));
+}
+
+// A message that is already waiting to be shown only needs showing once.
+fn queue_toasts(mut events: EventReader<Toast>, mut queue: ResMut<ToastQueue>) {
+    for toast in events.iter() {
+        if !queue.0.iter().any(|queued| queued.text == toast.text) {
+            queue.0.push_back(toast.clone());
+        }
+    }
+}
+
+fn show_queued_toasts(
+    mut commands: Commands,
+    mut queue: ResMut<ToastQueue>,
+    palette: Res<Palette>,
+    stack: Query<Entity, With<ToastStack>>,
+    shown: Query<(), With<ShownToast>>,
+) {
+    let Ok(stack) = stack.get_single() else {
+        return;
+    };
+    let free_slots = MAX_SHOWN.saturating_sub(shown.iter().count()).min(queue.0.len());
+    for toast in queue.0.drain(..free_slots) {
+        let color = toast.kind.color(&palette);
+        commands.entity(stack).with_children(|parent| {
+            parent.spawn((
+                ShownToast {timer: Timer::from_seconds(TOAST_SECS, TimerMode::Once), color},
+                NodeBundle {
+                    style: Style {padding: UiRect::all(Val::Px(12.)), ..default()},
+                    background_color: color.with_a(0.).into(),
+                    ..default()
+                },
+            )).with_children(|parent| {
+                parent.spawn(TextBundle::from_section(toast.text, TextStyle {
+                    font_size: 28.,
+                    color: Color::NONE,
+                    ..default()
+                }));
+            });
+        });
+    }
+}
+
+// Real time, so toasts still come and go behind the pause menu.
+fn fade_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toasts: Query<(Entity, &mut ShownToast, &mut BackgroundColor, &Children)>,
+    mut labels: Query<&mut Text>,
+) {
+    for (entity, mut toast, mut background, children) in toasts.iter_mut() {
+        toast.timer.tick(time.raw_delta());
+        if toast.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let elapsed = toast.timer.elapsed_secs();
+        let remaining = TOAST_SECS - elapsed;
+        let opacity = (elapsed / FADE_SECS).min(remaining / FADE_SECS).min(1.);
+        background.0 = toast.color.with_a(toast.color.a() * opacity);
+        for &child in children.iter() {
+            if let Ok(mut text) = labels.get_mut(child) {
+                text.sections[0].style.color = Color::WHITE.with_a(opacity);
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+
+use crate::{replay_move_attempts, AppState, CurrentSoot, Player, SootId, SootSprite, Recordings};
+use crate::grid::{AnimateTranslation, ApplyGridMovement, GridLocation};
+use crate::inventory::Inventory;
+use crate::rules::GameRules;
+use crate::undo::Undone;
+
+pub struct TradingPlugin;
+
+impl Plugin for TradingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            take_items_from_past_self,
+            replay_trades.before(replay_move_attempts),
+        ).before(ApplyGridMovement).run_if(in_state(AppState::Playing).and_then(trading_enabled)));
+    }
+}
+
+/// A recorded hand-over: at the start of turn `turn_number`, the recorded soot took everything carried by the soot
+/// `loop_offset` loops older than itself. Stored relative so it still lines up once everyone shifts back a loop.
+#[derive(Debug, Clone, Copy)]
+pub struct Trade {
+    pub turn_number: i32,
+    loop_offset: i32,
+}
+
+fn trading_enabled(rules: Res<GameRules>) -> bool {
+    rules.item_trading
+}
+
+fn take_items_from_past_self(
+    keyboard_input: Res<Input<KeyCode>>,
+    current_soot: Res<CurrentSoot>,
+    player: Query<(Entity, &SootSprite, &GridLocation, &AnimateTranslation), With<Player>>,
+    past_selves: Query<(Entity, &SootSprite, &GridLocation), Without<Player>>,
+    mut inventories: Query<&mut Inventory>,
+    mut recordings: ResMut<Recordings>,
+) {
+    if current_soot.0 != SootId::Player || !keyboard_input.just_pressed(KeyCode::T) {
+        return;
+    }
+
+    let (player, player_soot, player_location, animation) = player.single();
+    if !animation.timer.finished() {
+        return;
+    }
+
+    for (past_self, past_soot, location) in past_selves.iter() {
+        if location != player_location {
+            continue;
+        }
+
+        take_items(&mut inventories, player, past_self);
+        recordings.current_mut().trades.push(Trade {
+            turn_number: player_soot.turn_number,
+            loop_offset: past_soot.id.loop_number(),
+        });
+    }
+}
+
+fn replay_trades(
+    current_soot: Res<CurrentSoot>,
+    soots: Query<(Entity, &SootSprite, &GridLocation, &AnimateTranslation)>,
+    mut inventories: Query<&mut Inventory>,
+    recordings: Res<Recordings>,
+    mut last_replayed: Local<Option<(Entity, i32)>>,
+    mut undone: EventReader<Undone>,
+) {
+    // A rolled-back turn gets played again, hand-over included.
+    if undone.iter().count() > 0 {
+        *last_replayed = None;
+    }
+
+    let SootId::Recording(loop_number) = current_soot.0 else {
+        return;
+    };
+
+    let Some((taker, taker_soot, taker_location, animation)) = soots.iter()
+        .find(|(_, soot, _, _)| soot.id == current_soot.0) else {
+        return;
+    };
+    // The replayed soot can wait on this turn for several frames; only hand over once.
+    if !animation.timer.finished() || *last_replayed == Some((taker, taker_soot.turn_number)) {
+        return;
+    }
+    *last_replayed = Some((taker, taker_soot.turn_number));
+
+    let Some(recording) = recordings.for_soot(current_soot.0) else {
+        return;
+    };
+    for trade in recording.trades.iter().filter(|trade| trade.turn_number == taker_soot.turn_number) {
+        let victim_id = SootId::Recording(loop_number + trade.loop_offset);
+        // If interference moved either soot off the shared cell, the hand-over simply doesn't happen this time.
+        let victim = soots.iter()
+            .find(|(_, soot, location, _)| soot.id == victim_id && *location == taker_location);
+        if let Some((victim, _, _, _)) = victim {
+            take_items(&mut inventories, taker, victim);
+        }
+    }
+}
+
+fn take_items(inventories: &mut Query<&mut Inventory>, taker: Entity, giver: Entity) {
+    let Ok([mut taker, mut giver]) = inventories.get_many_mut([taker, giver]) else {
+        return;
+    };
+    taker.points += giver.points;
+    taker.add_fuel(giver.fuel);
+    taker.keys += giver.keys;
+    taker.bombs += giver.bombs;
+    giver.points = 0;
+    giver.fuel = 0;
+    giver.keys = 0;
+    giver.bombs = 0;
+}
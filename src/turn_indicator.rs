@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+
+use crate::{AppState, CurrentSoot, DespawnOnExitPlaying, LoopCounter, SootId, SootSprite, StartLoop, GRID_SPACING, SOOT_Z};
+use crate::palette::Palette;
+use crate::settings::Settings;
+use crate::ui::UpdateUi;
+
+/// Just above the soots, so the arrow isn't hidden by whoever is standing under it.
+const ARROW_Z: f32 = SOOT_Z + 0.5;
+const ARROW_SIZE: f32 = 28.;
+/// How far above the soot's center the arrow floats.
+const ARROW_HEIGHT: f32 = GRID_SPACING as f32 * 0.55;
+const BOB_HEIGHT: f32 = 6.;
+const BOB_RATE: f32 = 4.;
+const YOUR_TURN_COLOR: Color = Color::GOLD;
+
+pub struct TurnIndicatorPlugin;
+
+impl Plugin for TurnIndicatorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Playing), spawn_turn_indicator.in_set(StartLoop))
+            .add_systems(Update, (
+                update_turn_display,
+                follow_current_soot,
+            ).in_set(UpdateUi).run_if(in_state(AppState::Playing)));
+    }
+}
+
+#[derive(Component)]
+struct TurnDisplay;
+
+/// Bobs over the soot whose turn it is.
+#[derive(Component)]
+struct TurnArrow;
+
+fn spawn_turn_indicator(mut commands: Commands) {
+    commands.spawn((
+        TurnDisplay,
+        TextBundle::from_section("", TextStyle {font_size: 40., ..default()}).with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.),
+            top: Val::Px(70.),
+            ..default()
+        }),
+        DespawnOnExitPlaying,
+    ));
+    // A diamond, which reads as a pointer without needing a texture.
+    commands.spawn((
+        TurnArrow,
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(ARROW_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., ARROW_Z).with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        DespawnOnExitPlaying,
+    ));
+}
+
+fn update_turn_display(
+    current_soot: Res<CurrentSoot>,
+    loop_counter: Res<LoopCounter>,
+    palette: Res<Palette>,
+    mut display: Query<&mut Text, With<TurnDisplay>>,
+    added: Query<(), Added<TurnDisplay>>,
+) {
+    // A new loop usually starts on the player's turn, same as the last one ended, so that's no change to go on.
+    if !current_soot.is_changed() && added.is_empty() {
+        return;
+    }
+    let (value, color) = match current_soot.0 {
+        SootId::Player => ("Your move".to_string(), YOUR_TURN_COLOR),
+        SootId::Recording(loops_back) => {
+            (format!("Replaying loop {}...", loop_counter.0 - loops_back + 1), palette.past_self.with_a(1.))
+        },
+    };
+    for mut text in display.iter_mut() {
+        text.sections[0].value = value.clone();
+        text.sections[0].style.color = color;
+    }
+}
+
+// Follows the soot's sprite rather than its cell, so it rides along with the move animation.
+fn follow_current_soot(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    current_soot: Res<CurrentSoot>,
+    palette: Res<Palette>,
+    soots: Query<(&SootSprite, &Transform), Without<TurnArrow>>,
+    mut arrows: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<TurnArrow>>,
+) {
+    let soot = soots.iter().find(|(soot, _)| soot.id == current_soot.0);
+    for (mut transform, mut sprite, mut visibility) in arrows.iter_mut() {
+        let Some((_, soot_transform)) = soot else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Visible;
+        let bob = if settings.reduce_motion { 0. } else { (time.elapsed_seconds() * BOB_RATE).sin() * BOB_HEIGHT };
+        let position = soot_transform.translation.truncate() + Vec2::new(0., ARROW_HEIGHT + bob);
+        transform.translation = position.extend(ARROW_Z);
+        sprite.color = if current_soot.0 == SootId::Player { YOUR_TURN_COLOR } else { palette.past_self.with_a(1.) };
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{buffer_move_intents, debuffer_move_inputs, AppState, CurrentSoot, LoopCounter, MoveBuffer, Player, SootId, WAIT};
+use crate::grid::{AnimateTranslation, GridLocation};
+use crate::level_spec::LevelSpec;
+use crate::palette::Palette;
+use crate::rules::GameRules;
+
+const BAR_WIDTH: f32 = 100.;
+const BAR_HEIGHT: f32 = 10.;
+/// Above the player's head, and drawn over it.
+const BAR_OFFSET: Vec3 = Vec3::new(0., 70., 0.2);
+const BAR_BACKGROUND_COLOR: Color = Color::rgba(0., 0., 0., 0.6);
+
+pub struct TurnTimerPlugin;
+
+impl Plugin for TurnTimerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(TurnTimer(Timer::default()))
+            .add_systems(Update, (
+                spawn_timer_bar,
+                tick_turn_timer.after(buffer_move_intents).before(debuffer_move_inputs),
+                update_timer_bar,
+            ).chain().run_if(in_state(AppState::Playing)).run_if(timed));
+    }
+}
+
+fn timed(rules: Res<GameRules>) -> bool {
+    rules.turn_time.is_some()
+}
+
+/// Time left for the player to pick a move this turn. Counts game time, so it stops behind the pause menu.
+#[derive(Resource)]
+struct TurnTimer(Timer);
+
+#[derive(Component)]
+struct TimerBar;
+
+#[derive(Component)]
+struct TimerBarFill;
+
+fn spawn_timer_bar(mut commands: Commands, palette: Res<Palette>, players: Query<Entity, Added<Player>>) {
+    for player in players.iter() {
+        commands.entity(player).with_children(|parent| {
+            parent.spawn((
+                TimerBar,
+                SpriteBundle {
+                    sprite: Sprite {color: BAR_BACKGROUND_COLOR, custom_size: Some(Vec2::new(BAR_WIDTH, BAR_HEIGHT)), ..default()},
+                    transform: Transform::from_translation(BAR_OFFSET),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+            )).with_children(|parent| {
+                parent.spawn((
+                    TimerBarFill,
+                    SpriteBundle {
+                        sprite: Sprite {color: palette.free_move, custom_size: Some(Vec2::new(BAR_WIDTH, BAR_HEIGHT)), ..default()},
+                        transform: Transform::from_xyz(0., 0., 0.01),
+                        ..default()
+                    },
+                ));
+            });
+        });
+    }
+}
+
+/// Runs the clock while it's the player's turn and they're standing still. A turn left to run out is spent waiting,
+/// which is recorded like any other move.
+fn tick_turn_timer(
+    time: Res<Time>,
+    rules: Res<GameRules>,
+    current_soot: Res<CurrentSoot>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    player: Query<(&GridLocation, &AnimateTranslation), With<Player>>,
+    mut turn_timer: ResMut<TurnTimer>,
+    mut move_buffer: ResMut<MoveBuffer>,
+) {
+    let Some(turn_time) = rules.turn_time else {
+        return;
+    };
+    let Ok((location, animation)) = player.get_single() else {
+        return;
+    };
+    let deciding = current_soot.0 == SootId::Player
+        && animation.timer.finished()
+        && move_buffer.moves.is_empty()
+        && location.0 != level_spec.end_for_loop(loop_counter.0);
+    if !deciding {
+        turn_timer.0 = Timer::new(Duration::from_secs_f32(turn_time), TimerMode::Once);
+        return;
+    }
+
+    if turn_timer.0.tick(time.delta()).just_finished() {
+        move_buffer.push(WAIT);
+    }
+}
+
+fn update_timer_bar(
+    turn_timer: Res<TurnTimer>,
+    palette: Res<Palette>,
+    mut bars: Query<&mut Visibility, With<TimerBar>>,
+    mut fills: Query<(&mut Sprite, &mut Transform), With<TimerBarFill>>,
+) {
+    // A reset timer means nobody is deciding right now.
+    let shown = turn_timer.0.elapsed() > Duration::ZERO;
+    for mut visibility in bars.iter_mut() {
+        *visibility = if shown { Visibility::Inherited } else { Visibility::Hidden };
+    }
+
+    let left = turn_timer.0.percent_left();
+    for (mut sprite, mut transform) in fills.iter_mut() {
+        sprite.custom_size = Some(Vec2::new(BAR_WIDTH * left, BAR_HEIGHT));
+        // Shrinks toward the left edge.
+        transform.translation.x = -BAR_WIDTH * (1. - left) / 2.;
+        let [r, g, b, _] = palette.blocked_move.as_rgba_f32();
+        let [full_r, full_g, full_b, _] = palette.free_move.as_rgba_f32();
+        sprite.color = Color::rgb(r + (full_r - r) * left, g + (full_g - g) * left, b + (full_b - b) * left);
+    }
+}
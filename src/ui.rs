@@ -1,7 +1,14 @@
 use bevy::prelude::*;
 
-use crate::{AppState, DespawnOnExitPlaying, Player};
-use crate::inventory::Inventory;
+use crate::{AppState, LoopCounter, SootSprite, StartLoop, DespawnOnExitPlaying, Player};
+use crate::inventory::{CandyCollected, CandyRemaining, Inventory};
+use crate::palette::Palette;
+use crate::pickup_flight::{HudTarget, InFlight};
+use crate::rules::GameRules;
+use crate::scoring::{FinishedLoopsScore, Score};
+use crate::run_log::RunLog;
+use crate::settings::Settings;
+use crate::shop::Upgrades;
 
 
 #[derive(SystemSet, Hash, Debug, Clone, Eq, PartialEq)]
@@ -12,20 +19,96 @@ pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(OnEnter(AppState::Playing), spawn_ui)
+            .add_systems(OnEnter(AppState::Playing), (spawn_ui, apply_deferred, update_game_speed_display, update_loop_display).chain().in_set(StartLoop))
+            .add_systems(Update, highlight_buttons)
             .add_systems(Update, (
                 update_score_display,
+                update_combo_display,
+                update_run_total_display,
+                update_candy_left_display,
+                update_quota_display,
                 update_fuel_display,
+                update_loop_display.run_if(resource_changed::<GameRules>()),
+                update_moves_left_display,
+                update_ticker.run_if(resource_changed::<RunLog>()),
+                update_game_speed_display.run_if(resource_changed::<Settings>()),
             ).in_set(UpdateUi).chain().run_if(in_state(AppState::Playing)));
     }
 }
 
+/// Lines of recent events shown at the bottom of the screen, newest last.
+const TICKER_LINES: usize = 3;
+
+
+/// A menu button with a text label. `marker` tells the menu's systems which button was pressed.
+pub fn spawn_button(parent: &mut ChildBuilder, label: impl Into<String>, marker: impl Component) {
+    parent.spawn((
+        marker,
+        ButtonBundle {
+            style: Style {
+                min_width: Val::Px(150.),
+                height: Val::Px(65.),
+                padding: UiRect::horizontal(Val::Px(20.)),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            // Colored in by highlight_buttons, which also sees new buttons.
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(label, TextStyle::default()));
+    });
+}
+
+fn highlight_buttons(
+    palette: Res<Palette>,
+    mut buttons: Query<(Ref<Interaction>, &mut BackgroundColor), With<Button>>,
+) {
+    for (interaction, mut color) in buttons.iter_mut() {
+        if !interaction.is_changed() && !palette.is_changed() {
+            continue;
+        }
+        *color = match *interaction {
+            Interaction::Pressed => palette.button_pressed.into(),
+            Interaction::Hovered => palette.button_hovered.into(),
+            Interaction::None => palette.button.into(),
+        };
+    }
+}
+
 #[derive(Component)]
 struct FuelDisplay;
 
 #[derive(Component)]
 struct ScoreDisplay;
 
+#[derive(Component)]
+struct ComboDisplay;
+
+#[derive(Component)]
+struct RunTotalDisplay;
+
+#[derive(Component)]
+struct CandyLeftDisplay;
+
+#[derive(Component)]
+struct QuotaDisplay;
+
+#[derive(Component)]
+struct LoopDisplay;
+
+#[derive(Component)]
+struct MovesLeftDisplay;
+
+#[derive(Component)]
+struct GameSpeedDisplay;
+
+#[derive(Component)]
+struct Ticker;
+
 fn spawn_ui(mut commands: Commands) {
     commands.spawn((
         NodeBundle{
@@ -42,40 +125,204 @@ fn spawn_ui(mut commands: Commands) {
     )).with_children(|parent|{
         parent.spawn((
             ScoreDisplay,
+            HudTarget::Score,
             TextBundle::from_section("Score: 0", TextStyle {font_size: 50., ..default()}),
         ));
+        parent.spawn((
+            RunTotalDisplay,
+            TextBundle::from_section("Run total: 0", TextStyle {font_size: 50., ..default()}),
+        ));
+        parent.spawn((
+            ComboDisplay,
+            TextBundle::from_section("", TextStyle {font_size: 50., color: Color::GOLD, ..default()}),
+        ));
+        parent.spawn((
+            CandyLeftDisplay,
+            TextBundle::from_section("Candy left: 0", TextStyle {font_size: 50., ..default()}),
+        ));
+        parent.spawn((
+            QuotaDisplay,
+            TextBundle::from_section("", TextStyle {font_size: 50., ..default()}),
+        ));
         parent.spawn((
             FuelDisplay,
+            HudTarget::Fuel,
             TextBundle::from_section("Fuel: 0", TextStyle {font_size: 50., ..default()}),
         ));
+        parent.spawn((
+            MovesLeftDisplay,
+            TextBundle::from_section("", TextStyle {font_size: 50., ..default()}),
+        ));
+        parent.spawn((
+            LoopDisplay,
+            TextBundle::from_section("Loop 1/1", TextStyle {font_size: 50., ..default()}),
+        ));
+        parent.spawn((
+            GameSpeedDisplay,
+            TextBundle::from_section("", TextStyle {font_size: 50., ..default()}),
+        ));
     });
+    commands.spawn((
+        Ticker,
+        TextBundle::from_sections((0..TICKER_LINES).map(|_| TextSection::default())).with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.),
+            bottom: Val::Px(20.),
+            ..default()
+        }),
+        DespawnOnExitPlaying,
+    ));
 }
 
 
+// Candy still flying up from the board isn't counted until it gets here.
 fn update_score_display(
-    player: Query<&Inventory, (With<Player>, Changed<Inventory>)>,
+    player: Query<(Ref<Inventory>, Ref<Score>), With<Player>>,
+    in_flight: Res<InFlight>,
     mut display: Query<&mut Text, With<ScoreDisplay>>
 ) {
-    if player.is_empty() {
+    let Ok((inventory, score)) = player.get_single() else {
+        return;
+    };
+    if !inventory.is_changed() && !score.is_changed() && !in_flight.is_changed() {
+        return;
+    }
+
+    for mut text in display.iter_mut() {
+        text.sections[0].value = format!("Score: {}", score.total(&inventory) - in_flight.points);
+    }
+}
+
+// Past selves and earlier loops included.
+fn update_run_total_display(
+    finished_loops: Res<FinishedLoopsScore>,
+    soots: Query<(&Inventory, &Score)>,
+    changed: Query<(), Or<(Changed<Inventory>, Changed<Score>)>>,
+    mut display: Query<&mut Text, With<RunTotalDisplay>>,
+) {
+    if changed.is_empty() && !finished_loops.is_changed() {
         return;
     }
-    
-    let player = player.single();
+
+    let total = finished_loops.run_total(soots.iter());
     for mut text in display.iter_mut() {
-        text.sections[0].value = format!("Score: {}", player.candies);
+        text.sections[0].value = format!("Run total: {}", total);
+    }
+}
+
+// Only shown once there's a streak going.
+fn update_combo_display(
+    player: Query<&Score, (With<Player>, Changed<Score>)>,
+    mut display: Query<&mut Text, With<ComboDisplay>>,
+) {
+    let Ok(score) = player.get_single() else {
+        return;
+    };
+    let value = if score.combo > 1 { format!("Combo x{}", score.combo) } else { String::new() };
+    for mut text in display.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+fn update_candy_left_display(
+    candy_remaining: Res<CandyRemaining>,
+    mut display: Query<&mut Text, With<CandyLeftDisplay>>
+) {
+    if !candy_remaining.is_changed() {
+        return;
+    }
+
+    for mut text in display.iter_mut() {
+        text.sections[0].value = format!("Candy left: {}", candy_remaining.0);
     }
 }
 
 fn update_fuel_display(
-    player: Query<&Inventory, (With<Player>, Changed<Inventory>)>,
+    player: Query<Ref<Inventory>, With<Player>>,
+    in_flight: Res<InFlight>,
     mut display: Query<&mut Text, With<FuelDisplay>>
 ) {
-    if player.is_empty() {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    if !player.is_changed() && !in_flight.is_changed() {
         return;
     }
 
-    let player = player.single();
     for mut text in display.iter_mut() {
-        text.sections[0].value = format!("Fuel: {}", player.fuel);
+        let mut value = format!("Fuel: {}/{}", (player.fuel - in_flight.fuel).max(0), player.max_fuel);
+        if player.keys > 0 {
+            value += &format!("  Keys: {}", player.keys);
+        }
+        if player.bombs > 0 {
+            value += &format!("  Bombs: {}", player.bombs);
+        }
+        text.sections[0].value = value;
+    }
+}
+
+// Only shown when there's a candy quota.
+fn update_quota_display(
+    rules: Res<GameRules>,
+    candy_remaining: Res<CandyRemaining>,
+    candy_collected: Res<CandyCollected>,
+    mut display: Query<&mut Text, With<QuotaDisplay>>,
+    added: Query<(), Added<QuotaDisplay>>,
+) {
+    if rules.candy_quota.is_none() || (!candy_remaining.is_changed() && !candy_collected.is_changed() && added.is_empty()) {
+        return;
+    }
+
+    let value = match rules.candy_needed(candy_collected.0, candy_remaining.0) {
+        0 => "Exit open!".to_string(),
+        needed => format!("Exit opens in: {} candy", needed),
+    };
+    for mut text in display.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+// Only shown when there's a move budget.
+fn update_moves_left_display(
+    rules: Res<GameRules>,
+    player: Query<&SootSprite, (With<Player>, Changed<SootSprite>)>,
+    mut display: Query<&mut Text, With<MovesLeftDisplay>>,
+) {
+    let (Some(limit), Ok(soot)) = (rules.turn_limit, player.get_single()) else {
+        return;
+    };
+    for mut text in display.iter_mut() {
+        text.sections[0].value = format!("Moves left: {}", (limit - soot.turn_number).max(0));
+    }
+}
+
+// Time crystals can add to the total partway through a loop.
+fn update_loop_display(
+    loop_counter: Res<LoopCounter>,
+    rules: Res<GameRules>,
+    upgrades: Res<Upgrades>,
+    mut display: Query<&mut Text, With<LoopDisplay>>,
+) {
+    for mut text in display.iter_mut() {
+        text.sections[0].value = format!("Loop {}/{}", loop_counter.0 + 1, rules.num_loops(&upgrades));
+    }
+}
+
+// Only shown when it's not running at normal speed.
+fn update_game_speed_display(settings: Res<Settings>, mut display: Query<&mut Text, With<GameSpeedDisplay>>) {
+    for mut text in display.iter_mut() {
+        text.sections[0].value = if settings.game_speed == 1. { String::new() } else { format!("{}x", settings.game_speed) };
+    }
+}
+
+// Older lines fade out as newer ones push them up.
+fn update_ticker(log: Res<RunLog>, mut tickers: Query<&mut Text, With<Ticker>>) {
+    let recent = &log.0[log.0.len().saturating_sub(TICKER_LINES)..];
+    for mut text in tickers.iter_mut() {
+        for (index, section) in text.sections.iter_mut().enumerate() {
+            let age = recent.len().saturating_sub(index + 1);
+            section.value = recent.get(index).map_or(String::new(), |event| format!("{}\n", event.describe()));
+            section.style = TextStyle {font_size: 22., color: Color::rgba(1., 1., 1., 1. - 0.3 * age as f32), ..default()};
+        }
     }
 }
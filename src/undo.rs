@@ -0,0 +1,226 @@
+use bevy::prelude::*;
+
+use crate::{move_soot_on_grid, validate_move, AppState, CurrentSoot, Move, MoveBuffer, Player, Recordings, RoundCounter, SootId, SootSprite, StartLoop, GRID_SPACING};
+use crate::doors::{door_bundle, DoorUnlocked};
+use crate::enemies::{enemies_done, Chaser};
+use crate::gates::SwitchStates;
+use crate::grid::{AnimateTranslation, GridCells, GridLocation};
+use crate::inventory::{CandyByLoop, CandyCollected, CandyRemaining, Inventory, Item, ItemGet, PickUpItems};
+use crate::magnet::Magnetized;
+use crate::painting::PaintedCells;
+use crate::palette::Palette;
+use crate::rules::GameRules;
+use crate::scoring::Score;
+use crate::spawn_level::{item_bundle, wall_bundle};
+use crate::time_crystal::ClaimedCrystals;
+use crate::usable_items::WallBlasted;
+
+pub struct UndoPlugin;
+
+impl Plugin for UndoPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(UndoLog::default())
+            .add_event::<Undone>()
+            .add_systems(OnEnter(AppState::Playing), clear_undo_log.in_set(StartLoop))
+            .add_systems(Update, (
+                take_snapshot.after(validate_move).before(move_soot_on_grid),
+                (log_pickups, log_unlocks, log_blasts).after(PickUpItems),
+                undo.before(validate_move).run_if(enemies_done),
+            ).run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// Sent after a round has been rolled back, for anything keeping its own per-turn bookkeeping.
+#[derive(Event)]
+pub struct Undone;
+
+/// The board as it was just before one of the player's moves. Past selves move in between the player's moves, so
+/// undoing a move rolls back the whole round that followed it.
+struct Snapshot {
+    soots: Vec<(Entity, GridLocation, Inventory, Score, SootSprite, Option<Magnetized>)>,
+    /// Chasers go wherever the soots led them, so unlike patrols they can't be put back by the round alone.
+    chasers: Vec<(Entity, GridLocation)>,
+    round: i32,
+    bonus_loops: i32,
+    /// So a crystal put back by the undo adds its loop again when it's picked up again.
+    claimed_crystals: ClaimedCrystals,
+    candy_remaining: i32,
+    candy_collected: i32,
+    candy_by_loop: CandyByLoop,
+    painted: PaintedCells,
+    switches: SwitchStates,
+    /// Items picked up since, by anyone, and the cells to put them back on.
+    picked_up: Vec<(IVec2, Item)>,
+    /// Doors opened since, to lock again.
+    unlocked: Vec<IVec2>,
+    /// Walls blown up since, to build again.
+    blasted: Vec<IVec2>,
+}
+
+/// One snapshot per player move this loop, oldest first.
+#[derive(Resource, Default)]
+struct UndoLog(Vec<Snapshot>);
+
+fn clear_undo_log(mut undo_log: ResMut<UndoLog>) {
+    undo_log.0.clear();
+}
+
+fn take_snapshot(
+    mut undo_log: ResMut<UndoLog>,
+    mut moves: EventReader<Move>,
+    player: Query<(), With<Player>>,
+    soots: Query<(Entity, &GridLocation, &Inventory, &Score, &SootSprite, Option<&Magnetized>)>,
+    chasers: Query<(Entity, &GridLocation), With<Chaser>>,
+    round_counter: Res<RoundCounter>,
+    (rules, claimed_crystals): (Res<GameRules>, Res<ClaimedCrystals>),
+    (candy_remaining, candy_collected): (Res<CandyRemaining>, Res<CandyCollected>),
+    candy_by_loop: Res<CandyByLoop>,
+    painted: Res<PaintedCells>,
+    switches: Res<SwitchStates>,
+) {
+    if !moves.iter().any(|event| player.contains(event.mover)) {
+        return;
+    }
+    undo_log.0.push(Snapshot {
+        soots: soots.iter()
+            .map(|(entity, location, inventory, score, soot, magnetized)| {
+                (entity, *location, *inventory, *score, *soot, magnetized.copied())
+            })
+            .collect(),
+        chasers: chasers.iter().map(|(entity, location)| (entity, *location)).collect(),
+        round: round_counter.0,
+        bonus_loops: rules.bonus_loops,
+        claimed_crystals: claimed_crystals.clone(),
+        candy_remaining: candy_remaining.0,
+        candy_collected: candy_collected.0,
+        candy_by_loop: candy_by_loop.clone(),
+        painted: painted.clone(),
+        switches: switches.clone(),
+        picked_up: vec![],
+        unlocked: vec![],
+        blasted: vec![],
+    });
+}
+
+fn log_pickups(mut undo_log: ResMut<UndoLog>, mut pickups: EventReader<ItemGet>) {
+    for pickup in pickups.iter() {
+        if let Some(snapshot) = undo_log.0.last_mut() {
+            snapshot.picked_up.push((pickup.cell, pickup.item));
+        }
+    }
+}
+
+fn log_unlocks(mut undo_log: ResMut<UndoLog>, mut unlocks: EventReader<DoorUnlocked>) {
+    for &DoorUnlocked(location) in unlocks.iter() {
+        if let Some(snapshot) = undo_log.0.last_mut() {
+            snapshot.unlocked.push(location);
+        }
+    }
+}
+
+fn log_blasts(mut undo_log: ResMut<UndoLog>, mut blasts: EventReader<WallBlasted>) {
+    for &WallBlasted(location) in blasts.iter() {
+        if let Some(snapshot) = undo_log.0.last_mut() {
+            snapshot.blasted.push(location);
+        }
+    }
+}
+
+fn undo(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    // What it takes to put things back on the board.
+    (asset_server, palette): (Res<AssetServer>, Res<Palette>),
+    current_soot: Res<CurrentSoot>,
+    cells: Res<GridCells>,
+    mut undo_log: ResMut<UndoLog>,
+    mut recordings: ResMut<Recordings>,
+    mut move_buffer: ResMut<MoveBuffer>,
+    (mut round_counter, mut rules, mut claimed_crystals): (ResMut<RoundCounter>, ResMut<GameRules>, ResMut<ClaimedCrystals>),
+    (mut candy_remaining, mut candy_collected): (ResMut<CandyRemaining>, ResMut<CandyCollected>),
+    mut candy_by_loop: ResMut<CandyByLoop>,
+    mut painted: ResMut<PaintedCells>,
+    mut switches: ResMut<SwitchStates>,
+    mut soots: Query<(&mut GridLocation, &mut Inventory, &mut Score, &mut SootSprite, &mut Transform, &mut AnimateTranslation)>,
+    mut chasers: Query<(&mut GridLocation, &mut Transform, &mut AnimateTranslation), (With<Chaser>, Without<SootSprite>)>,
+    mut undone: EventWriter<Undone>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Z) || current_soot.0 != SootId::Player {
+        return;
+    }
+    // Wait for everyone to land, so nothing is left mid-move.
+    if soots.iter().any(|(_, _, _, _, _, animation)| !animation.timer.finished()) {
+        return;
+    }
+    let Some(snapshot) = undo_log.0.pop() else {
+        return;
+    };
+
+    for (entity, location, inventory, score, soot_then, magnetized) in snapshot.soots {
+        let turn_number = soot_then.turn_number;
+        let Ok((mut grid_location, mut soot_inventory, mut soot_score, mut soot, mut transform, mut animation)) = soots.get_mut(entity) else {
+            continue;
+        };
+        // Put the soot straight back; moving it through change detection would animate it and count as a turn.
+        grid_location.bypass_change_detection().0 = location.0;
+        let position = (location.0 * GRID_SPACING).as_vec2();
+        transform.translation = position.extend(transform.translation.z);
+        animation.end = position;
+        *soot_inventory = inventory;
+        *soot_score = score;
+        match magnetized {
+            Some(magnetized) => commands.entity(entity).insert(magnetized),
+            None => commands.entity(entity).remove::<Magnetized>(),
+        };
+        if soot.id == SootId::Player {
+            let recording = recordings.current_mut();
+            recording.moves.truncate(turn_number as usize);
+            recording.trades.retain(|trade| trade.turn_number <= turn_number);
+            recording.item_uses.retain(|item_use| item_use.turn_number <= turn_number);
+        }
+        // Moves left in a double move come back along with the turn number.
+        *soot = soot_then;
+    }
+    for (entity, location) in snapshot.chasers {
+        let Ok((mut grid_location, mut transform, mut animation)) = chasers.get_mut(entity) else {
+            continue;
+        };
+        grid_location.bypass_change_detection().0 = location.0;
+        let position = (location.0 * GRID_SPACING).as_vec2();
+        transform.translation = position.extend(transform.translation.z);
+        animation.end = position;
+    }
+
+    for (location, item) in snapshot.picked_up {
+        if let Some(cell) = cells.get(location) {
+            commands.entity(cell).with_children(|parent| {
+                parent.spawn(item_bundle(item, &asset_server, &palette));
+            });
+        }
+    }
+    for location in snapshot.unlocked {
+        if let Some(cell) = cells.get(location) {
+            commands.entity(cell).with_children(|parent| {
+                parent.spawn(door_bundle(&palette));
+            });
+        }
+    }
+    for location in snapshot.blasted {
+        if let Some(cell) = cells.get(location) {
+            commands.entity(cell).with_children(|parent| {
+                parent.spawn(wall_bundle(&palette));
+            });
+        }
+    }
+    round_counter.0 = snapshot.round;
+    rules.bonus_loops = snapshot.bonus_loops;
+    *claimed_crystals = snapshot.claimed_crystals;
+    candy_remaining.0 = snapshot.candy_remaining;
+    candy_collected.0 = snapshot.candy_collected;
+    *candy_by_loop = snapshot.candy_by_loop;
+    *painted = snapshot.painted;
+    *switches = snapshot.switches;
+    move_buffer.clear();
+    undone.send(Undone);
+}
@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+
+use crate::{replay_move_attempts, AppState, CurrentSoot, DespawnOnExitPlaying, Player, Recordings, SootId, SootSprite, GRID_SPACING};
+use crate::doors::Door;
+use crate::gates::Gate;
+use crate::grid::{AnimateTranslation, ApplyGridMovement, GridCells, GridLocation};
+use crate::inventory::{Inventory, Item};
+use crate::spawn_level::Wall;
+use crate::undo::Undone;
+
+const EXPLOSION_SECS: f32 = 0.4;
+const EXPLOSION_COLOR: Color = Color::rgb(1., 0.55, 0.1);
+const EXPLOSION_Z: f32 = 5.;
+/// Cells a bomb clears, around the soot setting it off.
+const BLAST: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+pub struct UsableItemsPlugin;
+
+impl Plugin for UsableItemsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<ItemUsed>()
+            .add_event::<WallBlasted>()
+            .add_systems(Update, (
+                (use_item, replay_item_uses),
+                set_off_bombs,
+                // A past self can blow a wall and walk through it in the same frame.
+                apply_deferred,
+            ).chain().before(replay_move_attempts).before(ApplyGridMovement).run_if(in_state(AppState::Playing)))
+            .add_systems(Update, (play_explosion_sound, spawn_explosions, animate_explosions)
+                .run_if(in_state(AppState::Playing)));
+    }
+}
+
+/// A recorded use of a carried item, at the start of turn `turn_number`, for past selves to do again.
+#[derive(Debug, Clone, Copy)]
+pub struct ItemUse {
+    pub turn_number: i32,
+    item: Item,
+}
+
+/// Sent when a soot uses up one of the items it carries.
+#[derive(Event)]
+pub struct ItemUsed {
+    pub soot: Entity,
+    pub item: Item,
+}
+
+/// Sent for each wall a bomb clears, with the cell it was on.
+#[derive(Event)]
+pub struct WallBlasted(pub IVec2);
+
+#[derive(Component)]
+struct Explosion(Timer);
+
+/// Takes one usable item out of the inventory, if there's one to use.
+fn take_usable(inventory: &mut Inventory, item: Item) -> bool {
+    let count = match item {
+        Item::Bomb => &mut inventory.bombs,
+        _ => return false,
+    };
+    if *count == 0 {
+        return false;
+    }
+    *count -= 1;
+    true
+}
+
+// Using an item doesn't take up the turn; the player still moves afterwards.
+fn use_item(
+    keyboard_input: Res<Input<KeyCode>>,
+    current_soot: Res<CurrentSoot>,
+    mut player: Query<(Entity, &SootSprite, &mut Inventory, &AnimateTranslation), With<Player>>,
+    mut recordings: ResMut<Recordings>,
+    mut used: EventWriter<ItemUsed>,
+) {
+    if current_soot.0 != SootId::Player || !keyboard_input.just_pressed(KeyCode::E) {
+        return;
+    }
+    let Ok((player, soot, mut inventory, animation)) = player.get_single_mut() else {
+        return;
+    };
+    if !animation.timer.finished() || !take_usable(&mut inventory, Item::Bomb) {
+        return;
+    }
+
+    recordings.current_mut().item_uses.push(ItemUse {turn_number: soot.turn_number, item: Item::Bomb});
+    used.send(ItemUsed {soot: player, item: Item::Bomb});
+}
+
+fn replay_item_uses(
+    current_soot: Res<CurrentSoot>,
+    mut soots: Query<(Entity, &SootSprite, &mut Inventory, &AnimateTranslation)>,
+    recordings: Res<Recordings>,
+    mut last_replayed: Local<Option<(Entity, i32)>>,
+    mut undone: EventReader<Undone>,
+    mut used: EventWriter<ItemUsed>,
+) {
+    // A rolled-back turn gets played again, items included.
+    if undone.iter().count() > 0 {
+        *last_replayed = None;
+    }
+    if current_soot.0 == SootId::Player {
+        return;
+    }
+
+    let Some((entity, soot, mut inventory, animation)) = soots.iter_mut()
+        .find(|(_, soot, _, _)| soot.id == current_soot.0) else {
+        return;
+    };
+    // The replayed soot can wait on this turn for several frames; only use things once.
+    if !animation.timer.finished() || *last_replayed == Some((entity, soot.turn_number)) {
+        return;
+    }
+    *last_replayed = Some((entity, soot.turn_number));
+
+    let Some(recording) = recordings.for_soot(current_soot.0) else {
+        return;
+    };
+    // A past self that didn't get hold of the item this time round just goes without.
+    for item_use in recording.item_uses.iter().filter(|item_use| item_use.turn_number == soot.turn_number) {
+        if take_usable(&mut inventory, item_use.item) {
+            used.send(ItemUsed {soot: entity, item: item_use.item});
+        }
+    }
+}
+
+// Doors and gates have their own ways of opening, so only plain walls go.
+fn set_off_bombs(
+    mut commands: Commands,
+    mut used: EventReader<ItemUsed>,
+    soots: Query<&GridLocation>,
+    cells: Res<GridCells>,
+    children: Query<&Children>,
+    walls: Query<(), (With<Wall>, Without<Door>, Without<Gate>)>,
+    mut blasted: EventWriter<WallBlasted>,
+) {
+    for event in used.iter().filter(|event| matches!(event.item, Item::Bomb)) {
+        let Ok(location) = soots.get(event.soot) else {
+            continue;
+        };
+        for cell in BLAST.map(|offset| location.0 + offset) {
+            for &entity in cells.contents(cell, &children).iter().filter(|&&entity| walls.contains(entity)) {
+                commands.entity(entity).despawn_recursive();
+                blasted.send(WallBlasted(cell));
+            }
+        }
+    }
+}
+
+fn play_explosion_sound(mut commands: Commands, asset_server: Res<AssetServer>, mut used: EventReader<ItemUsed>) {
+    for _ in used.iter().filter(|event| matches!(event.item, Item::Bomb)) {
+        commands.spawn(AudioBundle {
+            source: asset_server.load("explosion.wav"),
+            settings: PlaybackSettings::DESPAWN,
+        });
+    }
+}
+
+// A burst over the bomb's cell and each cell it reaches.
+fn spawn_explosions(mut commands: Commands, mut used: EventReader<ItemUsed>, soots: Query<&GridLocation>) {
+    for event in used.iter().filter(|event| matches!(event.item, Item::Bomb)) {
+        let Ok(location) = soots.get(event.soot) else {
+            continue;
+        };
+        for cell in std::iter::once(location.0).chain(BLAST.map(|offset| location.0 + offset)) {
+            commands.spawn((
+                Explosion(Timer::from_seconds(EXPLOSION_SECS, TimerMode::Once)),
+                DespawnOnExitPlaying,
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: EXPLOSION_COLOR,
+                        custom_size: Some(Vec2::splat(128.)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation((cell * GRID_SPACING).as_vec2().extend(EXPLOSION_Z))
+                        .with_scale(Vec3::splat(0.2)),
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+// Swells out and fades away.
+fn animate_explosions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut explosions: Query<(Entity, &mut Explosion, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut explosion, mut transform, mut sprite) in explosions.iter_mut() {
+        if explosion.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let progress = explosion.0.percent();
+        transform.scale = Vec3::splat(0.2 + 0.8 * progress);
+        sprite.color.set_a(1. - progress);
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use crate::{AppState, StartLoop, CurrentSoot, DespawnOnExitPlaying, LoopCounter, SootId, SootSprite, Recordings};
+use crate::grid::{AnimateTranslation, GridLocation, MovementComplete};
+use crate::level_spec::LevelSpec;
+use crate::ui::spawn_button;
+
+/// How long every soot may sit idle on a non-player turn before we call it a soft-lock.
+const STALL_TIMEOUT_SECS: f32 = 5.;
+
+pub struct WatchdogPlugin;
+
+impl Plugin for WatchdogPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Watchdog::default())
+            .add_systems(OnEnter(AppState::Playing), reset_watchdog.in_set(StartLoop))
+            .add_systems(Update, (
+                watch_for_stalled_turns,
+                recover_turn_button,
+            ).chain().run_if(in_state(AppState::Playing)));
+    }
+}
+
+#[derive(Resource, Default)]
+struct Watchdog {
+    progress: Option<(SootId, i32)>,
+    stalled_for: f32,
+    prompt: Option<Entity>,
+}
+
+#[derive(Component)]
+struct RecoverTurnButton;
+
+fn reset_watchdog(mut watchdog: ResMut<Watchdog>) {
+    *watchdog = Watchdog::default();
+}
+
+fn watch_for_stalled_turns(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut watchdog: ResMut<Watchdog>,
+    current_soot: Res<CurrentSoot>,
+    recordings: Res<Recordings>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+    soots: Query<(&SootSprite, &GridLocation, &AnimateTranslation)>,
+) {
+    let turns_taken = soots.iter().map(|(soot, _, _)| soot.turn_number).sum();
+    let progress = Some((current_soot.0, turns_taken));
+    let animating = soots.iter().any(|(_, _, animation)| !animation.timer.finished());
+    // Waiting on the player's input is normal, unless the player is already parked on the exit.
+    let waiting_on_player = current_soot.0 == SootId::Player && soots.iter()
+        .any(|(soot, location, _)| soot.id == SootId::Player && location.0 != level_spec.end_for_loop(loop_counter.0));
+
+    if progress != watchdog.progress || animating || waiting_on_player {
+        watchdog.progress = progress;
+        watchdog.stalled_for = 0.;
+        if let Some(prompt) = watchdog.prompt.take() {
+            commands.entity(prompt).despawn_recursive();
+        }
+        return;
+    }
+
+    watchdog.stalled_for += time.delta_seconds();
+    if watchdog.stalled_for < STALL_TIMEOUT_SECS || watchdog.prompt.is_some() {
+        return;
+    }
+
+    warn!("Turn hasn't advanced in {:.1}s; current soot is {:?}.", watchdog.stalled_for, current_soot.0);
+    for (soot, location, animation) in soots.iter() {
+        warn!("  {:?}: turn {}, at {}, animation finished: {}",
+            soot.id, soot.turn_number, location.0, animation.timer.finished());
+    }
+    for (loop_number, recording) in recordings.0.iter().enumerate() {
+        warn!("  recording {}: {} moves", loop_number, recording.moves.len());
+    }
+
+    watchdog.prompt = Some(spawn_recover_prompt(&mut commands));
+}
+
+fn spawn_recover_prompt(commands: &mut Commands) -> Entity {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(20.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(20.),
+                ..default()
+            },
+            ..default()
+        },
+        DespawnOnExitPlaying,
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("The turn seems stuck.", TextStyle {font_size: 30., ..default()}));
+        spawn_button(parent, "Recover turn", RecoverTurnButton);
+    }).id()
+}
+
+// Skips the stuck soot's turn. A soot that can't finish its route is sent to the exit so the loop can still end.
+fn recover_turn_button(
+    interactions: Query<&Interaction, (With<RecoverTurnButton>, Changed<Interaction>)>,
+    current_soot: Res<CurrentSoot>,
+    mut soots: Query<(Entity, &SootSprite, &mut GridLocation)>,
+    mut movement_complete: EventWriter<MovementComplete>,
+    level_spec: Res<LevelSpec>,
+    loop_counter: Res<LoopCounter>,
+) {
+    if !interactions.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        return;
+    }
+
+    for (entity, soot, mut location) in soots.iter_mut() {
+        if soot.id != current_soot.0 {
+            continue;
+        }
+
+        warn!("Recovering stalled turn for {:?}.", soot.id);
+        let exit = level_spec.end_for_loop(loop_counter.0 - soot.id.loop_number());
+        if location.0 == exit {
+            movement_complete.send(MovementComplete{entity});
+        } else {
+            location.0 = exit;
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::{validate_move, AppState, MoveRejected};
+use crate::settings::Settings;
+
+const WIGGLE_SECS: f32 = 0.3;
+/// How far toward the blocked cell the soot lunges, as a share of its sprite.
+const LUNGE: f32 = 0.12;
+/// How far it leans into the lunge.
+const LEAN_RADIANS: f32 = 0.25;
+const SHAKES: f32 = 3.;
+/// Side to side, as a share of its sprite.
+const SHAKE: f32 = 0.04;
+
+pub struct WigglePlugin;
+
+impl Plugin for WigglePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, start_wiggles.after(validate_move).run_if(in_state(AppState::Playing)))
+            .add_systems(Update, wiggle);
+    }
+}
+
+/// A soot bumping against a move it couldn't make. It's played through the sprite's anchor and the transform's
+/// rotation, so it stays out of the way of anything moving the soot itself, like AnimateTranslation.
+#[derive(Component)]
+struct Wiggle {
+    direction: Vec2,
+    timer: Timer,
+}
+
+fn start_wiggles(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut rejections: EventReader<MoveRejected>,
+    sprites: Query<(), With<TextureAtlasSprite>>,
+) {
+    for rejection in rejections.iter().filter(|rejection| sprites.contains(rejection.mover)) {
+        if settings.reduce_motion {
+            continue;
+        }
+        commands.entity(rejection.mover).insert(Wiggle {
+            direction: rejection.offset.as_vec2().normalize_or_zero(),
+            timer: Timer::from_seconds(WIGGLE_SECS, TimerMode::Once),
+        });
+    }
+}
+
+// Out toward the cell and back, shaking its head on the way.
+fn wiggle(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut wiggles: Query<(Entity, &mut Wiggle, &mut Transform, &mut TextureAtlasSprite)>,
+) {
+    for (entity, mut wiggle, mut transform, mut sprite) in wiggles.iter_mut() {
+        if wiggle.timer.tick(time.delta()).finished() {
+            sprite.anchor = Anchor::Center;
+            transform.rotation = Quat::IDENTITY;
+            commands.entity(entity).remove::<Wiggle>();
+            continue;
+        }
+
+        let progress = wiggle.timer.percent();
+        let lunge = (progress * PI).sin();
+        let shake = (progress * SHAKES * 2. * PI).sin() * (1. - progress);
+        let across = wiggle.direction.perp();
+        let offset = wiggle.direction * LUNGE * lunge + if across == Vec2::ZERO { Vec2::X } else { across } * SHAKE * shake;
+        // The anchor is the point of the sprite placed at the transform, so it moves the opposite way.
+        sprite.anchor = Anchor::Custom(-offset);
+        transform.rotation = Quat::from_rotation_z(-wiggle.direction.x * LEAN_RADIANS * lunge);
+    }
+}